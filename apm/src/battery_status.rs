@@ -0,0 +1,35 @@
+use core::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatterySample {
+    pub capacity: u32,
+    pub charge: u32,
+}
+
+impl BatterySample {
+    pub fn percent(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+
+        self.charge.min(self.capacity) as f64 * 100.0 / self.capacity as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChargeState {
+    Charging,
+    Discharging,
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryStatus {
+    pub sample: BatterySample,
+    pub percent: f64,
+    pub state: ChargeState,
+    // Charge units per second, negative while discharging.
+    pub rate: Option<f64>,
+    // Time to empty while discharging, time to full while charging.
+    pub remaining: Option<Duration>,
+}
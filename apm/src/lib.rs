@@ -1,7 +1,21 @@
 #![allow(clippy::missing_safety_doc)]
 #![no_std]
 
+mod battery_status;
+mod power_manager;
+mod power_policy;
+mod shutdown_coordinator;
+mod shutdown_error;
+mod shutdown_hook;
+
+pub use battery_status::{BatterySample, BatteryStatus, ChargeState};
 use mmio::Mmio;
+pub use power_manager::{PowerEvent, PowerManager};
+pub use power_policy::PowerPolicy;
+use rtc::Rtc;
+pub use shutdown_coordinator::{ShutdownCoordinator, ShutdownReport, MAX_SHUTDOWN_HOOKS};
+pub use shutdown_error::ShutdownError;
+pub use shutdown_hook::{FnHook, HookStatus, ShutdownHook, ShutdownKind};
 
 pub const MMIO_ADDRESS: usize = 0x2000;
 const HAS_BATTERY_REGISTER: usize = 0x0;
@@ -35,6 +49,30 @@ impl Apm {
     pub unsafe fn reboot(&mut self) {
         self.mmio.write_u8(0x1, REBOOT_REGISTER);
     }
+
+    pub unsafe fn graceful_shutdown(
+        &mut self,
+        coordinator: &mut ShutdownCoordinator,
+        rtc: &Rtc,
+    ) -> ShutdownReport {
+        let report = coordinator.run(ShutdownKind::Shutdown, || rtc.now());
+
+        self.shutdown();
+
+        report
+    }
+
+    pub unsafe fn graceful_reboot(
+        &mut self,
+        coordinator: &mut ShutdownCoordinator,
+        rtc: &Rtc,
+    ) -> ShutdownReport {
+        let report = coordinator.run(ShutdownKind::Reboot, || rtc.now());
+
+        self.reboot();
+
+        report
+    }
 }
 
 impl Default for Apm {
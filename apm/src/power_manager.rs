@@ -0,0 +1,198 @@
+use core::time::Duration;
+
+use rtc::Rtc;
+
+use crate::{
+    Apm, BatterySample, BatteryStatus, ChargeState, PowerPolicy, ShutdownCoordinator, ShutdownKind,
+    ShutdownReport,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerEvent {
+    LowBattery(BatteryStatus),
+    Shutdown(BatteryStatus),
+}
+
+#[derive(Debug, Clone)]
+pub struct PowerManager {
+    pub policy: PowerPolicy,
+    last: Option<(BatterySample, Duration)>,
+    rate: Option<f64>,
+    status: Option<BatteryStatus>,
+    warned: bool,
+    shutdown_requested: bool,
+}
+
+impl PowerManager {
+    pub fn new(policy: PowerPolicy) -> Self {
+        Self {
+            policy,
+            last: None,
+            rate: None,
+            status: None,
+            warned: false,
+            shutdown_requested: false,
+        }
+    }
+
+    pub fn status(&self) -> Option<BatteryStatus> {
+        self.status
+    }
+
+    pub unsafe fn poll(
+        &mut self,
+        apm: &mut Apm,
+        rtc: &Rtc,
+        coordinator: &mut ShutdownCoordinator,
+    ) -> Option<PowerEvent> {
+        if !apm.has_battery() {
+            self.status = None;
+
+            return None;
+        }
+
+        let sample = BatterySample {
+            capacity: apm.battery_capacity(),
+            charge: apm.battery_charge(),
+        };
+        let event = self.update(sample, rtc.now())?;
+
+        self.handle(&event, coordinator, || rtc.now(), || apm.shutdown());
+
+        Some(event)
+    }
+
+    // Carries out the policy for an event returned by `update`. A critical battery runs the
+    // shutdown hooks and then `power_off`, the report is returned if that happened.
+    pub fn handle(
+        &self,
+        event: &PowerEvent,
+        coordinator: &mut ShutdownCoordinator,
+        now: impl FnMut() -> Duration,
+        power_off: impl FnOnce(),
+    ) -> Option<ShutdownReport> {
+        match event {
+            PowerEvent::LowBattery(status) => {
+                if let Some(on_low_battery) = self.policy.on_low_battery {
+                    on_low_battery(status);
+                }
+
+                None
+            }
+            PowerEvent::Shutdown(_) if self.policy.shutdown_on_critical => {
+                let report = coordinator.run(ShutdownKind::Shutdown, now);
+
+                power_off();
+
+                Some(report)
+            }
+            PowerEvent::Shutdown(_) => None,
+        }
+    }
+
+    pub fn update(&mut self, sample: BatterySample, now: Duration) -> Option<PowerEvent> {
+        self.update_rate(sample, now);
+
+        let percent = sample.percent();
+        let status = BatteryStatus {
+            sample,
+            percent,
+            state: self.state(),
+            rate: self.rate,
+            remaining: self.remaining(sample),
+        };
+
+        self.status = Some(status);
+
+        if let Some(threshold) = self.policy.shutdown_percent {
+            if percent <= threshold && !self.shutdown_requested {
+                self.shutdown_requested = true;
+                self.warned = true;
+
+                return Some(PowerEvent::Shutdown(status));
+            }
+
+            if percent > threshold + self.policy.hysteresis {
+                self.shutdown_requested = false;
+            }
+        }
+
+        if let Some(threshold) = self.policy.warn_percent {
+            if percent <= threshold && !self.warned {
+                self.warned = true;
+
+                return Some(PowerEvent::LowBattery(status));
+            }
+
+            if percent > threshold + self.policy.hysteresis {
+                self.warned = false;
+            }
+        }
+
+        None
+    }
+
+    // Charge is an integer, so on a slow drain most samples see no change. The rate is measured
+    // between charge changes instead of between samples.
+    fn update_rate(&mut self, sample: BatterySample, now: Duration) {
+        let Some((last, last_time)) = self.last else {
+            self.last = Some((sample, now));
+
+            return;
+        };
+
+        let elapsed = now.saturating_sub(last_time);
+
+        if sample.charge == last.charge {
+            if elapsed >= self.policy.idle_timeout {
+                self.rate = Some(0.0);
+                self.last = Some((sample, now));
+            }
+
+            return;
+        }
+
+        if elapsed.is_zero() {
+            return;
+        }
+
+        let rate = (sample.charge as f64 - last.charge as f64) / elapsed.as_secs_f64();
+
+        self.rate = Some(match self.rate {
+            Some(average) if average * rate > 0.0 => {
+                average + (rate - average) * self.policy.smoothing
+            }
+            _ => rate,
+        });
+        self.last = Some((sample, now));
+    }
+
+    fn state(&self) -> ChargeState {
+        match self.rate {
+            Some(rate) if rate > 0.0 => ChargeState::Charging,
+            Some(rate) if rate < 0.0 => ChargeState::Discharging,
+            _ => ChargeState::Idle,
+        }
+    }
+
+    fn remaining(&self, sample: BatterySample) -> Option<Duration> {
+        let rate = self.rate?;
+        let charge = sample.charge.min(sample.capacity) as f64;
+
+        let left = if rate < 0.0 {
+            charge
+        } else if rate > 0.0 {
+            sample.capacity as f64 - charge
+        } else {
+            return None;
+        };
+
+        Duration::try_from_secs_f64(left / rate.abs()).ok()
+    }
+}
+
+impl Default for PowerManager {
+    fn default() -> Self {
+        Self::new(PowerPolicy::default())
+    }
+}
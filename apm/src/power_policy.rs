@@ -0,0 +1,33 @@
+use core::time::Duration;
+
+use crate::BatteryStatus;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PowerPolicy {
+    pub warn_percent: Option<f64>,
+    pub shutdown_percent: Option<f64>,
+    // Percent above a threshold the battery has to recover before the threshold fires again.
+    pub hysteresis: f64,
+    // Time without any charge change after which the battery is considered idle.
+    pub idle_timeout: Duration,
+    // Weight of the newest rate measurement in the moving average, in `0.0..=1.0`.
+    pub smoothing: f64,
+    // Called when the battery drops to `warn_percent`, e.g. to show a banner or speak through tts.
+    pub on_low_battery: Option<fn(&BatteryStatus)>,
+    // Run a graceful shutdown once the battery drops to `shutdown_percent`.
+    pub shutdown_on_critical: bool,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            warn_percent: Some(20.0),
+            shutdown_percent: Some(5.0),
+            hysteresis: 2.0,
+            idle_timeout: Duration::from_secs(60),
+            smoothing: 0.25,
+            on_low_battery: None,
+            shutdown_on_critical: true,
+        }
+    }
+}
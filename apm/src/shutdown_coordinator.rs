@@ -0,0 +1,85 @@
+use core::time::Duration;
+
+use crate::{HookStatus, ShutdownError, ShutdownHook, ShutdownKind};
+
+pub const MAX_SHUTDOWN_HOOKS: usize = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub completed: usize,
+    pub timed_out: usize,
+    timed_out_hooks: [&'static str; MAX_SHUTDOWN_HOOKS],
+}
+
+impl ShutdownReport {
+    // Names of the hooks that timed out, in the order they ran.
+    pub fn timed_out_hooks(&self) -> &[&'static str] {
+        &self.timed_out_hooks[..self.timed_out]
+    }
+}
+
+struct RegisteredHook<'h> {
+    priority: u8,
+    hook: &'h mut dyn ShutdownHook,
+}
+
+pub struct ShutdownCoordinator<'h> {
+    hooks: [Option<RegisteredHook<'h>>; MAX_SHUTDOWN_HOOKS],
+    pub hook_timeout: Duration,
+}
+
+impl<'h> ShutdownCoordinator<'h> {
+    pub const fn new(hook_timeout: Duration) -> Self {
+        Self {
+            hooks: [const { None }; MAX_SHUTDOWN_HOOKS],
+            hook_timeout,
+        }
+    }
+
+    // Hooks run in ascending priority, hooks with equal priority run in registration order.
+    pub fn register(
+        &mut self,
+        priority: u8,
+        hook: &'h mut dyn ShutdownHook,
+    ) -> Result<(), ShutdownError> {
+        let len = self.hooks.iter().take_while(|h| h.is_some()).count();
+
+        if len == MAX_SHUTDOWN_HOOKS {
+            return Err(ShutdownError::TooManyHooks);
+        }
+
+        let position = self.hooks[..len]
+            .iter()
+            .flatten()
+            .position(|h| h.priority > priority)
+            .unwrap_or(len);
+
+        self.hooks[position..=len].rotate_right(1);
+        self.hooks[position] = Some(RegisteredHook { priority, hook });
+
+        Ok(())
+    }
+
+    pub fn run(&mut self, kind: ShutdownKind, mut now: impl FnMut() -> Duration) -> ShutdownReport {
+        let mut report = ShutdownReport::default();
+
+        for registered in self.hooks.iter_mut().flatten() {
+            let deadline = now() + self.hook_timeout;
+
+            loop {
+                if registered.hook.run(kind) == HookStatus::Done {
+                    report.completed += 1;
+                    break;
+                }
+
+                if now() >= deadline {
+                    report.timed_out_hooks[report.timed_out] = registered.hook.name();
+                    report.timed_out += 1;
+                    break;
+                }
+            }
+        }
+
+        report
+    }
+}
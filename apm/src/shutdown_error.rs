@@ -0,0 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownError {
+    TooManyHooks,
+}
@@ -0,0 +1,34 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownKind {
+    Shutdown,
+    Reboot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HookStatus {
+    Done,
+    Pending,
+}
+
+pub trait ShutdownHook {
+    fn name(&self) -> &'static str;
+
+    // Called repeatedly while it returns `HookStatus::Pending` until the hook times out.
+    fn run(&mut self, kind: ShutdownKind) -> HookStatus;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FnHook {
+    pub name: &'static str,
+    pub hook: fn(ShutdownKind) -> HookStatus,
+}
+
+impl ShutdownHook for FnHook {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&mut self, kind: ShutdownKind) -> HookStatus {
+        (self.hook)(kind)
+    }
+}
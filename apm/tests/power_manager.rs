@@ -0,0 +1,256 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use apm::{
+    BatterySample, BatteryStatus, ChargeState, FnHook, HookStatus, PowerEvent, PowerManager,
+    PowerPolicy, ShutdownCoordinator, ShutdownKind,
+};
+
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+static SHUTDOWN_HOOK_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+struct SimulatedBattery {
+    capacity: u32,
+    charge: f64,
+    // Charge units per second, negative while discharging.
+    rate: f64,
+}
+
+impl SimulatedBattery {
+    fn new(capacity: u32, charge: u32, rate: f64) -> Self {
+        Self {
+            capacity,
+            charge: charge as f64,
+            rate,
+        }
+    }
+
+    fn tick(&mut self, seconds: f64) -> BatterySample {
+        self.charge = (self.charge + self.rate * seconds).clamp(0.0, self.capacity as f64);
+
+        BatterySample {
+            capacity: self.capacity,
+            charge: self.charge as u32,
+        }
+    }
+}
+
+fn run(
+    manager: &mut PowerManager,
+    battery: &mut SimulatedBattery,
+    clock: &mut Duration,
+    seconds: u64,
+) -> Vec<PowerEvent> {
+    let mut events = Vec::new();
+
+    for _ in 0..seconds {
+        *clock += Duration::from_secs(1);
+
+        let sample = battery.tick(1.0);
+
+        events.extend(manager.update(sample, *clock));
+    }
+
+    events
+}
+
+#[test]
+fn percent_is_relative_to_capacity() {
+    let sample = BatterySample {
+        capacity: 2000,
+        charge: 500,
+    };
+
+    assert_eq!(sample.percent(), 25.0);
+    assert_eq!(
+        BatterySample {
+            capacity: 0,
+            charge: 10
+        }
+        .percent(),
+        0.0
+    );
+}
+
+#[test]
+fn estimates_discharge_rate_and_remaining_time() {
+    let mut manager = PowerManager::default();
+    // One unit every ten seconds, most samples see no change in charge.
+    let mut battery = SimulatedBattery::new(1000, 800, -0.1);
+    let mut clock = Duration::ZERO;
+
+    run(&mut manager, &mut battery, &mut clock, 600);
+
+    let status = manager.status().unwrap();
+    let rate = status.rate.unwrap();
+    let remaining = status.remaining.unwrap().as_secs_f64();
+    let expected = battery.charge / 0.1;
+
+    assert_eq!(status.state, ChargeState::Discharging);
+    assert!((rate + 0.1).abs() < 0.01, "rate: {rate}");
+    assert!(
+        (remaining - expected).abs() / expected < 0.1,
+        "remaining: {remaining}, expected: {expected}"
+    );
+}
+
+#[test]
+fn estimates_time_to_full_while_charging() {
+    let mut manager = PowerManager::default();
+    let mut battery = SimulatedBattery::new(1000, 100, 2.0);
+    let mut clock = Duration::ZERO;
+
+    run(&mut manager, &mut battery, &mut clock, 100);
+
+    let status = manager.status().unwrap();
+    let remaining = status.remaining.unwrap().as_secs_f64();
+
+    assert_eq!(status.state, ChargeState::Charging);
+    assert!((remaining - 350.0).abs() < 5.0, "remaining: {remaining}");
+}
+
+#[test]
+fn battery_becomes_idle_without_changes() {
+    let mut manager = PowerManager::default();
+    let mut battery = SimulatedBattery::new(1000, 900, -1.0);
+    let mut clock = Duration::ZERO;
+
+    run(&mut manager, &mut battery, &mut clock, 10);
+    battery.rate = 0.0;
+    run(&mut manager, &mut battery, &mut clock, 120);
+
+    let status = manager.status().unwrap();
+
+    assert_eq!(status.state, ChargeState::Idle);
+    assert_eq!(status.remaining, None);
+}
+
+#[test]
+fn warns_once_then_requests_shutdown() {
+    let mut manager = PowerManager::default();
+    let mut battery = SimulatedBattery::new(1000, 250, -1.0);
+    let mut clock = Duration::ZERO;
+
+    let events = run(&mut manager, &mut battery, &mut clock, 240);
+
+    assert_eq!(events.len(), 2, "{events:?}");
+    assert!(matches!(events[0], PowerEvent::LowBattery(status) if status.percent <= 20.0));
+    assert!(matches!(events[1], PowerEvent::Shutdown(status) if status.percent <= 5.0));
+}
+
+#[test]
+fn warning_rearms_after_recharge() {
+    let mut manager = PowerManager::default();
+    let mut battery = SimulatedBattery::new(1000, 210, -1.0);
+    let mut clock = Duration::ZERO;
+
+    let events = run(&mut manager, &mut battery, &mut clock, 20);
+
+    assert_eq!(events.len(), 1);
+
+    battery.rate = 1.0;
+    // Within the hysteresis nothing fires again.
+    assert!(run(&mut manager, &mut battery, &mut clock, 30).is_empty());
+
+    battery.rate = -1.0;
+    assert!(run(&mut manager, &mut battery, &mut clock, 30).is_empty());
+
+    battery.rate = 1.0;
+    run(&mut manager, &mut battery, &mut clock, 60);
+    battery.rate = -1.0;
+
+    let events = run(&mut manager, &mut battery, &mut clock, 60);
+
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], PowerEvent::LowBattery(_)));
+}
+
+#[test]
+fn thresholds_are_configurable() {
+    let mut manager = PowerManager::new(PowerPolicy {
+        warn_percent: None,
+        shutdown_percent: Some(50.0),
+        ..PowerPolicy::default()
+    });
+    let mut battery = SimulatedBattery::new(100, 60, -1.0);
+    let mut clock = Duration::ZERO;
+
+    let events = run(&mut manager, &mut battery, &mut clock, 30);
+
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], PowerEvent::Shutdown(_)));
+}
+
+fn warn(status: &BatteryStatus) {
+    assert!(status.percent <= 20.0);
+    WARNINGS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn save_state(kind: ShutdownKind) -> HookStatus {
+    assert_eq!(kind, ShutdownKind::Shutdown);
+    SHUTDOWN_HOOK_RUNS.fetch_add(1, Ordering::SeqCst);
+
+    HookStatus::Done
+}
+
+#[test]
+fn policy_warns_and_shuts_down_gracefully() {
+    let mut manager = PowerManager::new(PowerPolicy {
+        on_low_battery: Some(warn),
+        ..PowerPolicy::default()
+    });
+    let mut battery = SimulatedBattery::new(1000, 250, -1.0);
+    let mut clock = Duration::ZERO;
+    let mut hook = FnHook {
+        name: "save state",
+        hook: save_state,
+    };
+    let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+    let powered_off = Cell::new(false);
+    let mut reports = Vec::new();
+
+    coordinator.register(0, &mut hook).unwrap();
+
+    for event in run(&mut manager, &mut battery, &mut clock, 240) {
+        let power_off = || {
+            assert!(!powered_off.get());
+            powered_off.set(true);
+        };
+
+        reports.extend(manager.handle(&event, &mut coordinator, || clock, power_off));
+    }
+
+    assert_eq!(WARNINGS.load(Ordering::SeqCst), 1);
+    assert_eq!(SHUTDOWN_HOOK_RUNS.load(Ordering::SeqCst), 1);
+    assert!(powered_off.get());
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].completed, 1);
+}
+
+#[test]
+fn critical_battery_can_only_notify() {
+    let mut manager = PowerManager::new(PowerPolicy {
+        warn_percent: None,
+        shutdown_on_critical: false,
+        ..PowerPolicy::default()
+    });
+    let mut battery = SimulatedBattery::new(100, 10, -1.0);
+    let mut clock = Duration::ZERO;
+    let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+
+    let events = run(&mut manager, &mut battery, &mut clock, 10);
+
+    assert!(matches!(events[..], [PowerEvent::Shutdown(_)]));
+    assert_eq!(
+        manager.handle(
+            &events[0],
+            &mut coordinator,
+            || clock,
+            || panic!("powered off")
+        ),
+        None
+    );
+}
@@ -0,0 +1,128 @@
+use std::{cell::RefCell, time::Duration};
+
+use apm::{
+    FnHook, HookStatus, ShutdownCoordinator, ShutdownError, ShutdownHook, ShutdownKind,
+    MAX_SHUTDOWN_HOOKS,
+};
+
+struct RecordingHook<'l> {
+    name: &'static str,
+    log: &'l RefCell<Vec<&'static str>>,
+    // Number of `Pending` results before the hook is done, `None` never finishes.
+    pending: Option<usize>,
+}
+
+impl ShutdownHook for RecordingHook<'_> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&mut self, _kind: ShutdownKind) -> HookStatus {
+        match &mut self.pending {
+            Some(0) => {
+                self.log.borrow_mut().push(self.name);
+
+                HookStatus::Done
+            }
+            Some(pending) => {
+                *pending -= 1;
+
+                HookStatus::Pending
+            }
+            None => HookStatus::Pending,
+        }
+    }
+}
+
+fn clock() -> impl FnMut() -> Duration {
+    let mut time = Duration::ZERO;
+
+    move || {
+        time += Duration::from_millis(1);
+        time
+    }
+}
+
+#[test]
+fn hooks_run_in_priority_order() {
+    let log = RefCell::new(Vec::new());
+    let hook = |name, pending| RecordingHook {
+        name,
+        log: &log,
+        pending: Some(pending),
+    };
+    let mut screen = hook("blank screen", 0);
+    let mut disk = hook("flush disk", 3);
+    let mut floppy = hook("eject floppy", 0);
+    let mut net = hook("notify peers", 1);
+    let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+
+    coordinator.register(30, &mut screen).unwrap();
+    coordinator.register(0, &mut disk).unwrap();
+    coordinator.register(20, &mut net).unwrap();
+    coordinator.register(0, &mut floppy).unwrap();
+
+    let report = coordinator.run(ShutdownKind::Shutdown, clock());
+
+    assert_eq!(report.completed, 4);
+    assert_eq!(report.timed_out, 0);
+    assert!(report.timed_out_hooks().is_empty());
+    assert_eq!(
+        *log.borrow(),
+        ["flush disk", "eject floppy", "notify peers", "blank screen"]
+    );
+}
+
+#[test]
+fn stuck_hook_times_out_and_the_rest_still_run() {
+    let log = RefCell::new(Vec::new());
+    let mut stuck = RecordingHook {
+        name: "stuck",
+        log: &log,
+        pending: None,
+    };
+    let mut also_stuck = FnHook {
+        name: "also stuck",
+        hook: |_| HookStatus::Pending,
+    };
+    let mut screen = FnHook {
+        name: "blank screen",
+        hook: |_| HookStatus::Done,
+    };
+    let mut disk = RecordingHook {
+        name: "flush disk",
+        log: &log,
+        pending: Some(2),
+    };
+    let mut coordinator = ShutdownCoordinator::new(Duration::from_millis(50));
+
+    coordinator.register(0, &mut stuck).unwrap();
+    coordinator.register(1, &mut screen).unwrap();
+    coordinator.register(2, &mut also_stuck).unwrap();
+    coordinator.register(3, &mut disk).unwrap();
+
+    let report = coordinator.run(ShutdownKind::Reboot, clock());
+
+    assert_eq!(report.completed, 2);
+    assert_eq!(report.timed_out, 2);
+    assert_eq!(report.timed_out_hooks(), ["stuck", "also stuck"]);
+}
+
+#[test]
+fn registration_is_bounded() {
+    let mut hooks = [FnHook {
+        name: "noop",
+        hook: |_| HookStatus::Done,
+    }; MAX_SHUTDOWN_HOOKS + 1];
+    let (hooks, extra) = hooks.split_at_mut(MAX_SHUTDOWN_HOOKS);
+    let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+
+    for hook in hooks {
+        coordinator.register(0, hook).unwrap();
+    }
+
+    assert_eq!(
+        coordinator.register(0, &mut extra[0]),
+        Err(ShutdownError::TooManyHooks)
+    );
+}
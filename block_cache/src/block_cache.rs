@@ -0,0 +1,252 @@
+use core::ops::Range;
+
+use apm::{HookStatus, ShutdownHook, ShutdownKind};
+use block_device::{check_range, BlockDevice, BlockError};
+
+use crate::{CacheStats, BLOCK_SIZE};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    block: Option<usize>,
+    dirty: bool,
+    last_used: u64,
+}
+
+// Keeps the last `N` blocks used in memory. Writes stay in the cache until `flush` or until
+// their block is evicted, so register the cache as a shutdown hook, or flush it by hand,
+// before the power goes.
+pub struct BlockCache<D: BlockDevice, const N: usize> {
+    device: D,
+    slots: [Slot; N],
+    data: [[u8; BLOCK_SIZE]; N],
+    clock: u64,
+    stats: CacheStats,
+    generation: u64,
+}
+
+impl<D: BlockDevice, const N: usize> BlockCache<D, N> {
+    pub fn new(device: D) -> Self {
+        const { assert!(N > 0, "the cache needs at least one block") };
+
+        Self {
+            generation: device.media_generation(),
+            device,
+            slots: [Slot::default(); N],
+            data: [[0; BLOCK_SIZE]; N],
+            clock: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    // For driving the device past the cache, ejecting or polling for a new disk. A media
+    // change made through it is picked up on the next access.
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    // Writes the dirty blocks back first.
+    pub fn into_inner(mut self) -> Result<D, BlockError> {
+        BlockDevice::flush(&mut self)?;
+
+        Ok(self.device)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+
+    pub fn dirty_blocks(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.dirty).count()
+    }
+
+    // Forgets every cached block, dirty ones included. Happens on its own when the device
+    // reports a media change.
+    pub fn invalidate(&mut self) {
+        self.slots = [Slot::default(); N];
+    }
+
+    // Once the media has changed nothing cached belongs to it, dirty blocks included. Losing
+    // dirty blocks fails the access that noticed, the next one goes to the new media.
+    fn check_media(&mut self) -> Result<(), BlockError> {
+        let generation = self.device.media_generation();
+
+        if generation == self.generation {
+            return Ok(());
+        }
+
+        let lost_blocks = self.dirty_blocks();
+
+        self.invalidate();
+        self.generation = generation;
+
+        match lost_blocks {
+            0 => Ok(()),
+            lost_blocks => Err(BlockError::MediaChanged { lost_blocks }),
+        }
+    }
+
+    fn block_length(&self, block: usize) -> usize {
+        BLOCK_SIZE.min(self.device.size() - block * BLOCK_SIZE)
+    }
+
+    // The slot holding `block`, loading it unless the caller is about to overwrite all of it.
+    fn slot(&mut self, block: usize, overwrite: bool) -> Result<usize, BlockError> {
+        self.clock += 1;
+
+        if let Some(index) = self.slots.iter().position(|slot| slot.block == Some(block)) {
+            self.stats.hits += 1;
+            self.slots[index].last_used = self.clock;
+
+            return Ok(index);
+        }
+
+        self.stats.misses += 1;
+
+        let index = match self.slots.iter().position(|slot| slot.block.is_none()) {
+            Some(index) => index,
+            None => {
+                let index = (0..N)
+                    .min_by_key(|&index| self.slots[index].last_used)
+                    .unwrap();
+
+                self.write_back(index)?;
+                self.stats.evictions += 1;
+                index
+            }
+        };
+        let length = self.block_length(block);
+
+        self.slots[index] = Slot::default();
+
+        if !overwrite {
+            self.device
+                .read_at(block * BLOCK_SIZE, &mut self.data[index][..length])?;
+        }
+
+        self.slots[index] = Slot {
+            block: Some(block),
+            dirty: false,
+            last_used: self.clock,
+        };
+
+        Ok(index)
+    }
+
+    fn write_back(&mut self, index: usize) -> Result<(), BlockError> {
+        let slot = self.slots[index];
+
+        if let (Some(block), true) = (slot.block, slot.dirty) {
+            let length = self.block_length(block);
+
+            self.device
+                .write_at(block * BLOCK_SIZE, &self.data[index][..length])?;
+            self.slots[index].dirty = false;
+            self.stats.write_backs += 1;
+        }
+
+        Ok(())
+    }
+}
+
+// Splits a transfer at block boundaries, as the block, the range within it and the range of
+// the buffer that goes with it.
+fn pieces(
+    offset: usize,
+    length: usize,
+) -> impl Iterator<Item = (usize, Range<usize>, Range<usize>)> {
+    let mut done = 0;
+
+    core::iter::from_fn(move || {
+        (done < length).then(|| {
+            let position = offset + done;
+            let start = position % BLOCK_SIZE;
+            let piece = (BLOCK_SIZE - start).min(length - done);
+            let item = (
+                position / BLOCK_SIZE,
+                start..start + piece,
+                done..done + piece,
+            );
+
+            done += piece;
+            item
+        })
+    })
+}
+
+impl<D: BlockDevice, const N: usize> BlockDevice for BlockCache<D, N> {
+    fn size(&self) -> usize {
+        self.device.size()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        self.check_media()?;
+        check_range(self.size(), offset, buffer.len())?;
+
+        for (block, range, buffer_range) in pieces(offset, buffer.len()) {
+            let index = self.slot(block, false)?;
+
+            buffer[buffer_range].copy_from_slice(&self.data[index][range]);
+        }
+
+        Ok(())
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        self.check_media()?;
+        check_range(self.size(), offset, data.len())?;
+
+        for (block, range, data_range) in pieces(offset, data.len()) {
+            let overwrite = range.len() == self.block_length(block);
+            let index = self.slot(block, overwrite)?;
+
+            self.data[index][range].copy_from_slice(&data[data_range]);
+            self.slots[index].dirty = true;
+        }
+
+        Ok(())
+    }
+
+    // Writes the dirty blocks back in block order, then flushes the device.
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.check_media()?;
+
+        while let Some(index) = (0..N)
+            .filter(|&index| self.slots[index].dirty)
+            .min_by_key(|&index| self.slots[index].block)
+        {
+            self.write_back(index)?;
+        }
+
+        self.device.flush()
+    }
+
+    fn sector_size(&self) -> usize {
+        self.device.sector_size()
+    }
+
+    fn media_generation(&self) -> u64 {
+        self.device.media_generation()
+    }
+}
+
+impl<D: BlockDevice, const N: usize> ShutdownHook for BlockCache<D, N> {
+    fn name(&self) -> &'static str {
+        "block cache"
+    }
+
+    // A failed flush is retried until the hook times out.
+    fn run(&mut self, _kind: ShutdownKind) -> HookStatus {
+        match BlockDevice::flush(self) {
+            Ok(()) => HookStatus::Done,
+            Err(_) => HookStatus::Pending,
+        }
+    }
+}
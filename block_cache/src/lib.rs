@@ -0,0 +1,10 @@
+#![no_std]
+
+mod block_cache;
+mod cache_stats;
+
+pub use block_cache::BlockCache;
+pub use block_device;
+pub use cache_stats::CacheStats;
+
+pub const BLOCK_SIZE: usize = block_device::SECTOR_SIZE;
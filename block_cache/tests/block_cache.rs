@@ -0,0 +1,387 @@
+use std::time::Duration;
+
+use apm::{ShutdownCoordinator, ShutdownKind};
+use block_cache::{
+    block_device::{BlockDevice, BlockError, MemoryDisk},
+    BlockCache, CacheStats, BLOCK_SIZE,
+};
+use fat::{FatType, FileSystem};
+use floppy_drive::{Fingerprint, MediaState, MediaTracker};
+
+const FLOPPY: usize = 1474560;
+
+// Counts what reaches the disk under the cache.
+struct CountingDisk<'a> {
+    disk: MemoryDisk<'a>,
+    reads: usize,
+    writes: usize,
+}
+
+impl<'a> CountingDisk<'a> {
+    fn new(data: &'a mut [u8]) -> Self {
+        Self {
+            disk: MemoryDisk::new(data),
+            reads: 0,
+            writes: 0,
+        }
+    }
+}
+
+impl BlockDevice for CountingDisk<'_> {
+    fn size(&self) -> usize {
+        self.disk.size()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        self.reads += 1;
+        self.disk.read_at(offset, buffer)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        self.writes += 1;
+        self.disk.write_at(offset, data)
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.disk.flush()
+    }
+}
+
+fn pattern(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i * 7 + i / 251) as u8).collect()
+}
+
+#[test]
+fn repeated_reads_hit_the_cache() {
+    let mut data = pattern(16 * BLOCK_SIZE);
+    let expected = data.clone();
+    let mut cache = BlockCache::<_, 4>::new(CountingDisk::new(&mut data));
+    let mut buffer = [0; 700];
+
+    cache.read_at(300, &mut buffer).unwrap();
+    assert_eq!(buffer, expected[300..1000]);
+    cache.read_at(600, &mut buffer[..100]).unwrap();
+    assert_eq!(buffer[..100], expected[600..700]);
+
+    assert_eq!(cache.device().reads, 2);
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 2,
+            write_backs: 0,
+            evictions: 0,
+        }
+    );
+    assert_eq!(cache.stats().hit_ratio(), 1.0 / 3.0);
+}
+
+#[test]
+fn evicts_least_recently_used() {
+    let mut data = pattern(16 * BLOCK_SIZE);
+    let mut cache = BlockCache::<_, 2>::new(CountingDisk::new(&mut data));
+    let mut byte = [0];
+    let mut touch = |cache: &mut BlockCache<_, 2>, block: usize| {
+        cache.read_at(block * BLOCK_SIZE, &mut byte).unwrap();
+    };
+
+    touch(&mut cache, 0);
+    touch(&mut cache, 1);
+    touch(&mut cache, 0);
+    touch(&mut cache, 2);
+    assert_eq!(cache.stats().evictions, 1);
+
+    // Block 1 went, block 0 stayed.
+    touch(&mut cache, 0);
+    assert_eq!(cache.stats().misses, 3);
+    touch(&mut cache, 1);
+    assert_eq!(cache.stats().misses, 4);
+    assert_eq!(cache.device().reads, 4);
+}
+
+#[test]
+fn writes_stay_in_cache_until_flush() {
+    let mut data = vec![0; 8 * BLOCK_SIZE];
+    let mut cache = BlockCache::<_, 4>::new(CountingDisk::new(&mut data));
+    let record = pattern(BLOCK_SIZE + 200);
+
+    cache.write_at(BLOCK_SIZE - 100, &record).unwrap();
+    // Whole blocks are overwritten without reading them first.
+    cache.write_at(4 * BLOCK_SIZE, &[9; BLOCK_SIZE]).unwrap();
+
+    assert_eq!(cache.device().writes, 0);
+    assert_eq!(cache.device().reads, 2);
+    assert_eq!(cache.dirty_blocks(), 4);
+
+    let mut buffer = vec![0; record.len()];
+
+    cache.read_at(BLOCK_SIZE - 100, &mut buffer).unwrap();
+    assert_eq!(buffer, record);
+
+    cache.flush().unwrap();
+    assert_eq!(cache.dirty_blocks(), 0);
+    assert_eq!(cache.stats().write_backs, 4);
+    assert_eq!(cache.device().disk.flushes(), 1);
+
+    let disk = cache.into_inner().unwrap();
+
+    assert_eq!(disk.writes, 4);
+    assert_eq!(disk.disk.data()[BLOCK_SIZE - 100..][..record.len()], record);
+    assert!(disk.disk.data()[4 * BLOCK_SIZE..5 * BLOCK_SIZE]
+        .iter()
+        .all(|&byte| byte == 9));
+}
+
+#[test]
+fn eviction_writes_back_dirty_blocks() {
+    let mut data = vec![0; 8 * BLOCK_SIZE + 100];
+    let mut cache = BlockCache::<_, 1>::new(MemoryDisk::new(&mut data));
+
+    cache.write_at(10, b"first").unwrap();
+    // The last block is short.
+    cache.write_at(8 * BLOCK_SIZE + 95, b"last").unwrap();
+    assert_eq!(cache.stats().write_backs, 1);
+    assert_eq!(&cache.device().data()[10..15], b"first");
+    assert_eq!(
+        cache.write_at(8 * BLOCK_SIZE + 98, b"end"),
+        Err(BlockError::OutOfRange)
+    );
+
+    cache.invalidate();
+    assert_eq!(cache.dirty_blocks(), 0);
+
+    let disk = cache.into_inner().unwrap();
+
+    assert!(disk.data()[8 * BLOCK_SIZE..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn shutdown_flushes_dirty_blocks() {
+    let mut data = vec![0; 8 * BLOCK_SIZE];
+    let mut cache = BlockCache::<_, 4>::new(MemoryDisk::new(&mut data));
+
+    cache.write_at(0, b"station log").unwrap();
+
+    {
+        let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let mut time = Duration::ZERO;
+
+        coordinator.register(0, &mut cache).unwrap();
+
+        let report = coordinator.run(ShutdownKind::Shutdown, || {
+            time += Duration::from_millis(1);
+            time
+        });
+
+        assert_eq!(report.completed, 1);
+    }
+
+    assert_eq!(cache.dirty_blocks(), 0);
+    assert_eq!(&cache.device().data()[..11], b"station log");
+}
+
+fn reader(disk: &[u8]) -> impl FnMut(usize, &mut [u8]) -> bool + '_ {
+    |address, buffer| {
+        buffer.copy_from_slice(&disk[address..][..buffer.len()]);
+        true
+    }
+}
+
+// Shaped like `FloppyDrive`: ejecting, inserting and polling take the drive by `&mut self`.
+struct Drive {
+    disks: [Vec<u8>; 2],
+    slot: Option<usize>,
+    inserted: Option<usize>,
+    media: MediaTracker,
+}
+
+impl Drive {
+    fn new(first: u8, second: u8) -> Self {
+        Self::with_disks([vec![first; 4 * BLOCK_SIZE], vec![second; 4 * BLOCK_SIZE]])
+    }
+
+    fn with_disks(disks: [Vec<u8>; 2]) -> Self {
+        Self {
+            disks,
+            slot: None,
+            inserted: None,
+            media: MediaTracker::new(),
+        }
+    }
+
+    fn eject(&mut self) {
+        self.slot = None;
+        self.media.removed();
+    }
+
+    // Nothing notices a new disk until the next poll.
+    fn insert(&mut self, disk: usize) {
+        self.inserted = Some(disk);
+    }
+
+    fn poll_media(&mut self) -> MediaState {
+        self.slot = self.inserted.take().or(self.slot);
+
+        let fingerprint = self
+            .slot
+            .and_then(|disk| Fingerprint::read(self.disks[disk].len(), reader(&self.disks[disk])));
+
+        self.media.observe(fingerprint)
+    }
+
+    fn disk(&mut self) -> Result<MemoryDisk<'_>, BlockError> {
+        let disk = self.slot.ok_or(BlockError::NoMedia)?;
+
+        Ok(MemoryDisk::new(&mut self.disks[disk]))
+    }
+}
+
+impl BlockDevice for Drive {
+    fn size(&self) -> usize {
+        self.slot.map_or(0, |disk| self.disks[disk].len())
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        self.disk()?.read_at(offset, buffer)
+    }
+
+    // Like the driver, follows its own writes with the fingerprint.
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        self.disk()?.write_at(offset, data)?;
+
+        let disk = &self.disks[self.slot.unwrap()];
+
+        self.media
+            .written(offset..offset + data.len(), reader(disk));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        Ok(())
+    }
+
+    fn media_generation(&self) -> u64 {
+        self.media.generation()
+    }
+}
+
+#[test]
+fn media_change_drops_cached_blocks() {
+    let mut drive = Drive::new(1, 2);
+
+    drive.insert(0);
+    drive.poll_media();
+
+    let mut cache = BlockCache::<_, 4>::new(drive);
+    let mut buffer = [0; 4];
+
+    cache.read_at(0, &mut buffer).unwrap();
+    assert_eq!(buffer, [1; 4]);
+
+    cache.device_mut().eject();
+    cache.device_mut().insert(1);
+    assert_eq!(cache.device_mut().poll_media(), MediaState::Changed);
+
+    cache.read_at(0, &mut buffer).unwrap();
+    assert_eq!(buffer, [2; 4]);
+    assert_eq!(cache.stats().misses, 2);
+}
+
+#[test]
+fn media_change_reports_lost_writes() {
+    let mut drive = Drive::new(1, 2);
+
+    drive.insert(0);
+    drive.poll_media();
+
+    let mut cache = BlockCache::<_, 4>::new(drive);
+    let mut buffer = [0; 4];
+
+    cache.write_at(0, b"lost").unwrap();
+    cache.write_at(BLOCK_SIZE, b"lost").unwrap();
+
+    // Swapped without an eject, only the poll sees it.
+    cache.device_mut().insert(1);
+    cache.device_mut().poll_media();
+
+    assert_eq!(
+        cache.read_at(0, &mut buffer),
+        Err(BlockError::MediaChanged { lost_blocks: 2 })
+    );
+    assert_eq!(cache.dirty_blocks(), 0);
+    cache.read_at(0, &mut buffer).unwrap();
+    assert_eq!(buffer, [2; 4]);
+
+    // Nothing meant for the first disk ends up on either of them.
+    let drive = cache.into_inner().unwrap();
+
+    assert!(drive.disks[0].iter().all(|&byte| byte == 1));
+    assert!(drive.disks[1].iter().all(|&byte| byte == 2));
+}
+
+#[test]
+fn reinserting_the_same_disk_keeps_nothing_stale() {
+    let mut drive = Drive::new(1, 2);
+
+    drive.insert(0);
+    drive.poll_media();
+
+    let mut cache = BlockCache::<_, 4>::new(drive);
+
+    cache.write_at(0, b"kept").unwrap();
+    cache.flush().unwrap();
+
+    cache.device_mut().eject();
+    assert_eq!(cache.device().media.state(), MediaState::Absent);
+
+    cache.device_mut().insert(0);
+    cache.device_mut().poll_media();
+
+    let mut buffer = [0; 4];
+
+    cache.read_at(0, &mut buffer).unwrap();
+    assert_eq!(&buffer, b"kept");
+}
+
+#[test]
+fn own_writes_are_not_a_media_change() {
+    let mut drive = Drive::with_disks([vec![0; FLOPPY], vec![0; FLOPPY]]);
+
+    drive.insert(0);
+    drive.poll_media();
+    drive.media.acknowledge();
+
+    // Few enough blocks that formatting alone writes back through the FAT and root directory.
+    let mut cache = BlockCache::<_, 8>::new(drive);
+
+    FileSystem::format(&mut cache, FatType::Fat12)
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(cache.device_mut().poll_media(), MediaState::Present);
+
+    {
+        let mut fs = FileSystem::mount(&mut cache).unwrap();
+
+        fs.create_dir("/logs").unwrap();
+        fs.create_file("/logs/station.txt")
+            .unwrap()
+            .write(b"station log")
+            .unwrap();
+    }
+
+    assert!(cache.stats().write_backs > 0);
+    assert!(cache.dirty_blocks() > 0);
+    assert_eq!(cache.device_mut().poll_media(), MediaState::Present);
+    assert_eq!(cache.device().media.generation(), 1);
+
+    // Nothing dropped, the file is all there once flushed.
+    cache.flush().unwrap();
+
+    let mut fs = FileSystem::mount(&mut cache).unwrap();
+    let mut file = fs.open("/logs/station.txt").unwrap();
+    let mut buffer = [0; 11];
+
+    assert_eq!(file.read(&mut buffer).unwrap(), 11);
+    assert_eq!(&buffer, b"station log");
+}
@@ -0,0 +1,106 @@
+use core::ops::Range;
+
+use crate::{BlockError, MAX_READ_WRITE_SIZE, SECTOR_SIZE};
+
+// Anything that stores bytes at offsets: the HDD, the floppy drive, a partition of either or
+// a disk image in memory. Filesystems, caches and partition tables are written against this
+// so they run on any of them and on the host.
+pub trait BlockDevice {
+    fn size(&self) -> usize;
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError>;
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError>;
+
+    fn flush(&mut self) -> Result<(), BlockError>;
+
+    fn sector_size(&self) -> usize {
+        SECTOR_SIZE
+    }
+
+    fn sector_count(&self) -> usize {
+        self.size() / self.sector_size()
+    }
+
+    // Goes up every time the media is removed or swapped, so layers above can tell that what
+    // they kept from it is stale. Fixed disks leave it at zero.
+    fn media_generation(&self) -> u64 {
+        0
+    }
+}
+
+impl<D: BlockDevice + ?Sized> BlockDevice for &mut D {
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        (**self).read_at(offset, buffer)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        (**self).write_at(offset, data)
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        (**self).flush()
+    }
+
+    fn sector_size(&self) -> usize {
+        (**self).sector_size()
+    }
+
+    fn media_generation(&self) -> u64 {
+        (**self).media_generation()
+    }
+}
+
+pub fn check_range(size: usize, offset: usize, length: usize) -> Result<(), BlockError> {
+    match offset.checked_add(length) {
+        Some(end) if end <= size => Ok(()),
+        _ => Err(BlockError::OutOfRange),
+    }
+}
+
+// `BlockDevice` wants all or nothing, a device that moved fewer bytes than asked fails the
+// access with both counts.
+pub fn check_transferred(expected: usize, actual: usize) -> Result<(), BlockError> {
+    match actual == expected {
+        true => Ok(()),
+        false => Err(BlockError::ShortTransfer { expected, actual }),
+    }
+}
+
+// Splits a transfer into pieces the devices accept in one op, as the device offset and the
+// range of the buffer that goes with it.
+pub fn chunks(offset: usize, length: usize) -> impl Iterator<Item = (usize, Range<usize>)> {
+    (0..length).step_by(MAX_READ_WRITE_SIZE).map(move |start| {
+        let end = length.min(start + MAX_READ_WRITE_SIZE);
+
+        (offset + start, start..end)
+    })
+}
+
+// Runs a transfer a piece from `chunks` at a time, stopping early once the device moves less
+// than asked. `op` gets the device offset and the range of the buffer and returns the bytes
+// it moved. Returns the number of bytes transferred.
+pub fn transfer<E>(
+    offset: usize,
+    length: usize,
+    mut op: impl FnMut(usize, Range<usize>) -> Result<usize, E>,
+) -> Result<usize, E> {
+    let mut transferred = 0;
+
+    for (address, range) in chunks(offset, length) {
+        let size = range.len();
+        let done = op(address, range)?.min(size);
+
+        transferred += done;
+
+        if done < size {
+            break;
+        }
+    }
+
+    Ok(transferred)
+}
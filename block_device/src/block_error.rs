@@ -0,0 +1,30 @@
+use core::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BlockError {
+    OutOfRange,
+    NoMedia,
+    ReadOnly,
+    // The device finished the op but moved fewer bytes than asked.
+    ShortTransfer { expected: usize, actual: usize },
+    // The media was swapped while a cache held writes for it, they are gone.
+    MediaChanged { lost_blocks: usize },
+    Device(i64),
+}
+
+impl Display for BlockError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BlockError::OutOfRange => f.write_str("block access out of range"),
+            BlockError::NoMedia => f.write_str("no media in the drive"),
+            BlockError::ReadOnly => f.write_str("media is read-only"),
+            BlockError::ShortTransfer { expected, actual } => {
+                write!(f, "short transfer, {actual} of {expected} bytes")
+            }
+            BlockError::MediaChanged { lost_blocks } => {
+                write!(f, "media changed, {lost_blocks} unwritten blocks lost")
+            }
+            BlockError::Device(code) => write!(f, "device error {code}"),
+        }
+    }
+}
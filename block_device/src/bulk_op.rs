@@ -0,0 +1,53 @@
+use mmio::Mmio;
+
+use crate::DeviceArgument;
+
+const CALL_OP: usize = 0x0;
+const OP_RESULT: usize = 0x0;
+
+#[derive(Debug, Clone)]
+pub enum BulkOp {
+    BulkRead {
+        address: usize,
+        size: usize,
+        dst_address: usize,
+    },
+    BulkWrite {
+        address: usize,
+        size: usize,
+        src_address: usize,
+    },
+}
+
+impl BulkOp {
+    pub fn id(&self) -> u32 {
+        match self {
+            BulkOp::BulkRead { .. } => 0x0,
+            BulkOp::BulkWrite { .. } => 0x1,
+        }
+    }
+
+    // Runs the op on a disk device and returns the raw result, negative values are device
+    // error codes.
+    pub unsafe fn call(&self, mmio: &Mmio) -> f64 {
+        match *self {
+            BulkOp::BulkRead {
+                address,
+                size,
+                dst_address,
+            }
+            | BulkOp::BulkWrite {
+                address,
+                size,
+                src_address: dst_address,
+            } => {
+                mmio.write_f64(address as f64, DeviceArgument::Arg0.offset());
+                mmio.write_f64(size as f64, DeviceArgument::Arg1.offset());
+                mmio.write_f64(dst_address as f64, DeviceArgument::Arg2.offset());
+            }
+        }
+
+        mmio.write_u32(self.id(), CALL_OP);
+        mmio.read_f64(OP_RESULT)
+    }
+}
@@ -2,7 +2,7 @@ const ARGUMENTS_OFFSET: usize = 0x100;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub enum HddArgument {
+pub enum DeviceArgument {
     Arg0 = 0,
     Arg1,
     Arg2,
@@ -15,16 +15,16 @@ pub enum HddArgument {
     Arg9,
 }
 
-impl HddArgument {
+impl DeviceArgument {
     pub fn offset(&self) -> usize {
         *self as usize + ARGUMENTS_OFFSET
     }
 }
 
-impl From<u8> for HddArgument {
+impl From<u8> for DeviceArgument {
     fn from(value: u8) -> Self {
         if value >= Self::Arg0 as u8 && value <= Self::Arg9 as u8 {
-            unsafe { core::mem::transmute(value) }
+            unsafe { core::mem::transmute::<u8, Self>(value) }
         } else {
             Self::Arg0
         }
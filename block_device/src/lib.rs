@@ -0,0 +1,17 @@
+#![allow(clippy::missing_safety_doc)]
+#![no_std]
+
+mod block_device;
+mod block_error;
+mod bulk_op;
+mod device_argument;
+mod memory_disk;
+
+pub use block_device::{check_range, check_transferred, chunks, transfer, BlockDevice};
+pub use block_error::BlockError;
+pub use bulk_op::BulkOp;
+pub use device_argument::DeviceArgument;
+pub use memory_disk::MemoryDisk;
+
+pub const MAX_READ_WRITE_SIZE: usize = 65536;
+pub const SECTOR_SIZE: usize = 512;
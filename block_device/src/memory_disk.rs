@@ -0,0 +1,87 @@
+use crate::{check_range, BlockDevice, BlockError, SECTOR_SIZE};
+
+// A disk kept in a byte slice, for running filesystems and disk images on the host.
+// A write cut short by a power cut keeps its first bytes, so sectors can end up torn.
+pub struct MemoryDisk<'a> {
+    data: &'a mut [u8],
+    sector_size: usize,
+    flushes: usize,
+    budget: Option<usize>,
+}
+
+impl<'a> MemoryDisk<'a> {
+    pub fn new(data: &'a mut [u8]) -> Self {
+        Self::with_sector_size(data, SECTOR_SIZE)
+    }
+
+    pub fn with_sector_size(data: &'a mut [u8], sector_size: usize) -> Self {
+        Self {
+            data,
+            sector_size,
+            flushes: 0,
+            budget: None,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+
+    pub fn flushes(&self) -> usize {
+        self.flushes
+    }
+
+    // Power goes out after `bytes` more bytes are written.
+    pub fn cut_power_after(&mut self, bytes: usize) {
+        self.budget = Some(bytes);
+    }
+
+    pub fn restore_power(&mut self) {
+        self.budget = None;
+    }
+
+    pub fn is_powered(&self) -> bool {
+        self.budget != Some(0)
+    }
+}
+
+impl BlockDevice for MemoryDisk<'_> {
+    fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        check_range(self.data.len(), offset, buffer.len())?;
+        buffer.copy_from_slice(&self.data[offset..offset + buffer.len()]);
+
+        Ok(())
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        check_range(self.data.len(), offset, data.len())?;
+
+        let length = match &mut self.budget {
+            Some(budget) => {
+                let length = data.len().min(*budget);
+
+                *budget -= length;
+                length
+            }
+            None => data.len(),
+        };
+
+        self.data[offset..offset + length].copy_from_slice(&data[..length]);
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.flushes += 1;
+
+        Ok(())
+    }
+
+    fn sector_size(&self) -> usize {
+        self.sector_size
+    }
+}
@@ -0,0 +1,101 @@
+use block_device::{
+    check_transferred, chunks, transfer, BlockDevice, BlockError, MemoryDisk, MAX_READ_WRITE_SIZE,
+};
+
+#[test]
+fn chunks_respect_transfer_limit() {
+    let length = 2 * MAX_READ_WRITE_SIZE + 100;
+    let pieces: Vec<_> = chunks(7, length).collect();
+
+    assert_eq!(
+        pieces,
+        [
+            (7, 0..MAX_READ_WRITE_SIZE),
+            (
+                7 + MAX_READ_WRITE_SIZE,
+                MAX_READ_WRITE_SIZE..2 * MAX_READ_WRITE_SIZE
+            ),
+            (7 + 2 * MAX_READ_WRITE_SIZE, 2 * MAX_READ_WRITE_SIZE..length),
+        ]
+    );
+    assert_eq!(chunks(0, 0).count(), 0);
+}
+
+#[test]
+fn transfer_stops_after_short_piece() {
+    let length = 3 * MAX_READ_WRITE_SIZE;
+    let mut ops = Vec::new();
+    let moved = transfer(0, length, |address, range| {
+        ops.push(address);
+
+        // The second piece comes up 10 bytes short.
+        Ok::<_, ()>(range.len() - if ops.len() == 2 { 10 } else { 0 })
+    });
+
+    assert_eq!(moved, Ok(2 * MAX_READ_WRITE_SIZE - 10));
+    assert_eq!(ops, [0, MAX_READ_WRITE_SIZE]);
+    assert_eq!(transfer(0, length, |_, _| Err::<usize, _>(-1)), Err(-1));
+    assert_eq!(
+        check_transferred(length, 2 * MAX_READ_WRITE_SIZE - 10),
+        Err(BlockError::ShortTransfer {
+            expected: length,
+            actual: 2 * MAX_READ_WRITE_SIZE - 10
+        })
+    );
+    assert_eq!(check_transferred(length, length), Ok(()));
+}
+
+#[test]
+fn memory_disk_reads_back_writes() {
+    let mut data = vec![0; 4096];
+    let mut disk = MemoryDisk::new(&mut data);
+
+    assert_eq!(disk.sector_count(), 8);
+
+    disk.write_at(510, b"hello").unwrap();
+
+    let mut buffer = [0; 5];
+
+    disk.read_at(510, &mut buffer).unwrap();
+    assert_eq!(&buffer, b"hello");
+    disk.flush().unwrap();
+    assert_eq!(disk.flushes(), 1);
+}
+
+#[test]
+fn memory_disk_checks_bounds() {
+    let mut data = vec![0; 1024];
+    let mut disk = MemoryDisk::new(&mut data);
+
+    assert_eq!(disk.write_at(1020, &[1; 5]), Err(BlockError::OutOfRange));
+    assert_eq!(
+        disk.read_at(usize::MAX, &mut [0; 2]),
+        Err(BlockError::OutOfRange)
+    );
+    assert!(disk.data().iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn memory_disk_drops_writes_after_power_cut() {
+    let mut data = vec![0; 1024];
+    let mut disk = MemoryDisk::new(&mut data);
+
+    disk.cut_power_after(3);
+    disk.write_at(0, &[1; 5]).unwrap();
+    assert!(!disk.is_powered());
+    disk.write_at(100, &[1; 5]).unwrap();
+    disk.restore_power();
+
+    assert_eq!(disk.data()[..6], [1, 1, 1, 0, 0, 0]);
+    assert!(disk.data()[100..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn short_transfer_reports_both_sizes() {
+    let error = BlockError::ShortTransfer {
+        expected: 1024,
+        actual: 512,
+    };
+
+    assert_eq!(error.to_string(), "short transfer, 512 of 1024 bytes");
+}
@@ -0,0 +1,151 @@
+use core::ops::Range;
+
+use flash::{FlashError, FlashStorage, ERASED_BYTE, SECTOR_SIZE};
+
+use crate::{CrashLogError, CrashRecord, CRASH_RECORD_LENGTH};
+
+const SLOTS_PER_SECTOR: usize = SECTOR_SIZE / CRASH_RECORD_LENGTH;
+
+// Crash records kept in a ring of flash sectors. Slots are filled in ring order, so the
+// oldest records are the ones after the newest, and reaching a used sector erases it,
+// dropping the oldest records to make room. A record torn by a power cut fails its CRC and
+// is skipped.
+pub struct CrashLog<F: FlashStorage> {
+    flash: F,
+    first: usize,
+    slots: usize,
+    next: usize,
+    sequence: u32,
+}
+
+impl<F: FlashStorage> CrashLog<F> {
+    pub fn new(flash: F, sectors: Range<usize>) -> Result<Self, CrashLogError> {
+        if sectors.len() < 2 {
+            return Err(CrashLogError::TooFewSectors);
+        }
+
+        if sectors.end > flash.sector_count() {
+            return Err(CrashLogError::Flash(FlashError::OutOfRange));
+        }
+
+        let mut log = Self {
+            flash,
+            first: sectors.start,
+            slots: sectors.len() * SLOTS_PER_SECTOR,
+            next: 0,
+            sequence: 0,
+        };
+        let mut newest: Option<(usize, u32)> = None;
+
+        for slot in 0..log.slots {
+            if let Some(record) = log.read(slot)? {
+                if newest.is_none_or(|(_, sequence)| record.sequence > sequence) {
+                    newest = Some((slot, record.sequence));
+                }
+            }
+        }
+
+        if let Some((slot, sequence)) = newest {
+            log.next = (slot + 1) % log.slots;
+            log.sequence = sequence.wrapping_add(1);
+        }
+
+        Ok(log)
+    }
+
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    // Returns the sequence number the record was stored under.
+    pub fn append(&mut self, record: &CrashRecord) -> Result<u32, CrashLogError> {
+        let mut record = *record;
+
+        record.sequence = self.sequence;
+
+        loop {
+            let sector = self.next / SLOTS_PER_SECTOR;
+
+            if self.next.is_multiple_of(SLOTS_PER_SECTOR) {
+                if !self.is_erased(sector * SECTOR_SIZE, SECTOR_SIZE)? {
+                    self.flash.erase_sector(self.first + sector)?;
+                }
+
+                break;
+            }
+
+            // Leftovers of an interrupted write, erasing this sector would take the newer
+            // records before the slot with it, so move on to the next sector instead.
+            if self.is_erased(self.next * CRASH_RECORD_LENGTH, CRASH_RECORD_LENGTH)? {
+                break;
+            }
+
+            self.next = (sector + 1) * SLOTS_PER_SECTOR % self.slots;
+        }
+
+        self.flash
+            .write(self.address(self.next), &record.to_bytes())?;
+        self.next = (self.next + 1) % self.slots;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        Ok(record.sequence)
+    }
+
+    // Records from the oldest to the newest.
+    pub fn records(&self) -> impl Iterator<Item = CrashRecord> + '_ {
+        (0..self.slots).filter_map(|i| self.read((self.next + i) % self.slots).ok().flatten())
+    }
+
+    pub fn last(&self) -> Option<CrashRecord> {
+        self.records().last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records().next().is_none()
+    }
+
+    pub fn clear(&mut self) -> Result<(), CrashLogError> {
+        for sector in 0..self.slots / SLOTS_PER_SECTOR {
+            self.flash.erase_sector(self.first + sector)?;
+        }
+
+        self.next = 0;
+
+        Ok(())
+    }
+
+    fn read(&self, slot: usize) -> Result<Option<CrashRecord>, CrashLogError> {
+        let mut bytes = [0; CRASH_RECORD_LENGTH];
+
+        self.flash.read(self.address(slot), &mut bytes)?;
+
+        Ok(CrashRecord::from_bytes(&bytes))
+    }
+
+    fn is_erased(&self, offset: usize, length: usize) -> Result<bool, CrashLogError> {
+        let mut buffer = [0; CRASH_RECORD_LENGTH];
+
+        for chunk in (offset..offset + length).step_by(CRASH_RECORD_LENGTH) {
+            self.flash
+                .read(self.first * SECTOR_SIZE + chunk, &mut buffer)?;
+
+            if buffer.iter().any(|&byte| byte != ERASED_BYTE) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn address(&self, slot: usize) -> usize {
+        self.first * SECTOR_SIZE + slot * CRASH_RECORD_LENGTH
+    }
+}
@@ -0,0 +1,24 @@
+use core::fmt::Display;
+
+use flash::FlashError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CrashLogError {
+    Flash(FlashError),
+    TooFewSectors,
+}
+
+impl Display for CrashLogError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CrashLogError::Flash(error) => write!(f, "flash error: {error}"),
+            CrashLogError::TooFewSectors => f.write_str("crash log needs at least two sectors"),
+        }
+    }
+}
+
+impl From<FlashError> for CrashLogError {
+    fn from(value: FlashError) -> Self {
+        CrashLogError::Flash(value)
+    }
+}
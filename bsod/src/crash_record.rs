@@ -0,0 +1,231 @@
+use core::{fmt::Display, panic::Location, time::Duration};
+
+use crc32::crc32;
+use flash::ERASED_BYTE;
+use heap::HeapStats;
+
+pub const CRASH_RECORD_LENGTH: usize = 512;
+pub const MAX_MESSAGE_LENGTH: usize = 256;
+pub const MAX_FILE_LENGTH: usize = 128;
+
+const MAGIC: [u8; 4] = *b"CRSH";
+const HAS_LOCATION: u8 = 1 << 0;
+const HAS_HEAP: u8 = 1 << 1;
+const HEAP_OFFSET: usize = 32;
+const MESSAGE_OFFSET: usize = HEAP_OFFSET + 8 * 8;
+const FILE_OFFSET: usize = MESSAGE_OFFSET + MAX_MESSAGE_LENGTH;
+const CRC_OFFSET: usize = CRASH_RECORD_LENGTH - 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrashLocation {
+    pub line: u32,
+    pub column: u32,
+    file: [u8; MAX_FILE_LENGTH],
+    file_length: u8,
+}
+
+impl CrashLocation {
+    // Long paths keep their tail, which is the part that tells files apart.
+    pub fn new(file: &str, line: u32, column: u32) -> Self {
+        let mut start = file.len().saturating_sub(MAX_FILE_LENGTH);
+
+        while !file.is_char_boundary(start) {
+            start += 1;
+        }
+
+        let tail = &file.as_bytes()[start..];
+        let mut location = Self {
+            line,
+            column,
+            file: [0; MAX_FILE_LENGTH],
+            file_length: tail.len() as u8,
+        };
+
+        location.file[..tail.len()].copy_from_slice(tail);
+
+        location
+    }
+
+    pub fn file(&self) -> &str {
+        core::str::from_utf8(&self.file[..self.file_length as usize]).unwrap_or("")
+    }
+}
+
+impl From<&Location<'_>> for CrashLocation {
+    fn from(value: &Location<'_>) -> Self {
+        Self::new(value.file(), value.line(), value.column())
+    }
+}
+
+// A crash as it is kept on flash, fixed size so the log can be a plain ring of slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrashRecord {
+    pub sequence: u32,
+    pub timestamp: Duration,
+    pub location: Option<CrashLocation>,
+    pub heap: Option<HeapStats>,
+    message: [u8; MAX_MESSAGE_LENGTH],
+    message_length: u16,
+}
+
+impl CrashRecord {
+    pub fn new(
+        message: &str,
+        location: Option<CrashLocation>,
+        timestamp: Duration,
+        heap: Option<HeapStats>,
+    ) -> Self {
+        let mut length = message.len().min(MAX_MESSAGE_LENGTH);
+
+        while !message.is_char_boundary(length) {
+            length -= 1;
+        }
+
+        let mut record = Self {
+            sequence: 0,
+            timestamp,
+            location,
+            heap,
+            message: [0; MAX_MESSAGE_LENGTH],
+            message_length: length as u16,
+        };
+
+        record.message[..length].copy_from_slice(&message.as_bytes()[..length]);
+
+        record
+    }
+
+    pub fn message(&self) -> &str {
+        core::str::from_utf8(&self.message[..self.message_length as usize]).unwrap_or("")
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; CRASH_RECORD_LENGTH] {
+        let mut bytes = [ERASED_BYTE; CRASH_RECORD_LENGTH];
+        let mut flags = 0;
+
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[8..16].copy_from_slice(&(self.timestamp.as_millis() as u64).to_le_bytes());
+        bytes[18..20].copy_from_slice(&self.message_length.to_le_bytes());
+
+        if let Some(location) = &self.location {
+            flags |= HAS_LOCATION;
+            bytes[20..24].copy_from_slice(&location.line.to_le_bytes());
+            bytes[24..28].copy_from_slice(&location.column.to_le_bytes());
+            bytes[28] = location.file_length;
+            bytes[FILE_OFFSET..FILE_OFFSET + MAX_FILE_LENGTH].copy_from_slice(&location.file);
+        }
+
+        if let Some(heap) = &self.heap {
+            flags |= HAS_HEAP;
+
+            for (i, value) in heap_fields(heap).into_iter().enumerate() {
+                let offset = HEAP_OFFSET + i * 8;
+
+                bytes[offset..offset + 8].copy_from_slice(&(value as u64).to_le_bytes());
+            }
+        }
+
+        bytes[16] = flags;
+        bytes[MESSAGE_OFFSET..FILE_OFFSET].copy_from_slice(&self.message);
+
+        let crc = crc32(&bytes[..CRC_OFFSET]);
+
+        bytes[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8; CRASH_RECORD_LENGTH]) -> Option<Self> {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        if bytes[..4] != MAGIC || crc32(&bytes[..CRC_OFFSET]) != u32_at(CRC_OFFSET) {
+            return None;
+        }
+
+        let flags = bytes[16];
+        let message_length = u16::from_le_bytes([bytes[18], bytes[19]]);
+        let file_length = bytes[28];
+
+        if message_length as usize > MAX_MESSAGE_LENGTH || file_length as usize > MAX_FILE_LENGTH {
+            return None;
+        }
+
+        let location = (flags & HAS_LOCATION != 0).then(|| CrashLocation {
+            line: u32_at(20),
+            column: u32_at(24),
+            file: bytes[FILE_OFFSET..FILE_OFFSET + MAX_FILE_LENGTH]
+                .try_into()
+                .unwrap(),
+            file_length,
+        });
+        let heap = (flags & HAS_HEAP != 0).then(|| {
+            let field = |i: usize| u64_at(HEAP_OFFSET + i * 8) as usize;
+
+            HeapStats {
+                size: field(0),
+                allocated: field(1),
+                peak: field(2),
+                allocations: field(3),
+                total_allocations: field(4),
+                failed_allocations: field(5),
+                cached: field(6),
+                leaked: field(7),
+            }
+        });
+
+        Some(Self {
+            sequence: u32_at(4),
+            timestamp: Duration::from_millis(u64_at(8)),
+            location,
+            heap,
+            message: bytes[MESSAGE_OFFSET..FILE_OFFSET].try_into().unwrap(),
+            message_length,
+        })
+    }
+}
+
+impl Display for CrashRecord {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "#{} at {}.{:03}s: {}",
+            self.sequence,
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_millis(),
+            self.message()
+        )?;
+
+        if let Some(location) = &self.location {
+            write!(
+                f,
+                " ({}:{}:{})",
+                location.file(),
+                location.line,
+                location.column
+            )?;
+        }
+
+        if let Some(heap) = &self.heap {
+            write!(f, " [{heap}]")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn heap_fields(heap: &HeapStats) -> [usize; 8] {
+    [
+        heap.size,
+        heap.allocated,
+        heap.peak,
+        heap.allocations,
+        heap.total_allocations,
+        heap.failed_allocations,
+        heap.cached,
+        heap.leaked,
+    ]
+}
@@ -1,8 +1,19 @@
 #![no_std]
-#![feature(panic_info_message)]
 
-use core::panic::Location;
+mod crash_log;
+mod crash_log_error;
+mod crash_record;
 
+use core::{alloc::Layout, panic::Location};
+
+pub use crash_log::CrashLog;
+pub use crash_log_error::CrashLogError;
+pub use crash_record::{
+    CrashLocation, CrashRecord, CRASH_RECORD_LENGTH, MAX_FILE_LENGTH, MAX_MESSAGE_LENGTH,
+};
+use flash::FlashStorage;
+use heap::HeapStats;
+use rtc::Rtc;
 use sgl::{
     gpu::{Boundable, BoundableExt, Color, MutPositionable, TextAlign},
     Sgl, Text,
@@ -14,19 +25,68 @@ const FONT_SIZE: f64 = 28.0;
 const PANIC_TEXT: &str = "FATAL ERROR!";
 
 pub fn bsod_panic(sgl: &mut Sgl, info: &core::panic::PanicInfo) -> ! {
-    if let Some(reason) = info.payload().downcast_ref::<&str>() {
+    let message = info.message();
+
+    if let Some(reason) = message.as_str() {
         bsod(sgl, Some(reason), info.location());
-    } else if let Some(message) = info.message() {
+    } else {
         let mut msg = StackString::new();
 
         msg.format(format_args!("PANIC: {message}"));
 
         bsod(sgl, Some(msg.str()), info.location());
-    } else {
-        bsod(sgl, Some("PANIC"), info.location());
     }
 }
 
+// Same as `bsod_panic`, but first stores the crash in the log so it can be read after reboot.
+// `heap` should be taken before the panic handler runs, the heap may be the one panicking.
+pub fn bsod_panic_logged(
+    sgl: &mut Sgl,
+    info: &core::panic::PanicInfo,
+    log: &mut CrashLog<impl FlashStorage>,
+    rtc: &Rtc,
+    heap: Option<HeapStats>,
+) -> ! {
+    let message = info.message();
+    let mut msg = StackString::new();
+
+    match message.as_str() {
+        Some(reason) => msg.format(format_args!("{reason}")),
+        None => msg.format(format_args!("PANIC: {message}")),
+    }
+
+    log_crash(log, rtc, msg.str(), info.location(), heap);
+    bsod(sgl, Some(msg.str()), info.location());
+}
+
+// The screen is drawn no matter what, so a crash that can't be logged is just dropped.
+pub fn log_crash(
+    log: &mut CrashLog<impl FlashStorage>,
+    rtc: &Rtc,
+    message: &str,
+    location: Option<&Location>,
+    heap: Option<HeapStats>,
+) -> Option<u32> {
+    let timestamp = unsafe { rtc.now() };
+    let record = CrashRecord::new(message, location.map(CrashLocation::from), timestamp, heap);
+
+    log.append(&record).ok()
+}
+
+pub fn bsod_oom(sgl: &mut Sgl, layout: Layout, stats: &HeapStats) -> ! {
+    let mut msg = StackString::new();
+
+    msg.format(format_args!(
+        "OUT OF MEMORY: {} BYTES, USED: {}/{}, CACHED: {}",
+        layout.size(),
+        stats.allocated,
+        stats.size,
+        stats.cached
+    ));
+
+    bsod(sgl, Some(msg.str()), None);
+}
+
 pub fn bsod(sgl: &mut Sgl, reason: Option<&str>, location: Option<&Location>) -> ! {
     unsafe {
         sgl.fill_screen(Some(Color::blue()));
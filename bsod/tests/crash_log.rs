@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use bsod::{
+    CrashLocation, CrashLog, CrashLogError, CrashRecord, CRASH_RECORD_LENGTH, MAX_FILE_LENGTH,
+    MAX_MESSAGE_LENGTH,
+};
+use flash::{MemoryFlash, SECTOR_SIZE};
+use heap::HeapStats;
+
+type Flash = MemoryFlash<4>;
+
+const SLOTS_PER_SECTOR: usize = SECTOR_SIZE / CRASH_RECORD_LENGTH;
+
+fn crash(i: usize) -> CrashRecord {
+    CrashRecord::new(
+        &format!("crash {i}"),
+        Some(CrashLocation::new("src/main.rs", i as u32, 5)),
+        Duration::from_millis(1000 * i as u64 + 250),
+        None,
+    )
+}
+
+#[test]
+fn stores_whole_record() {
+    let mut flash = Flash::new();
+    let mut log = CrashLog::new(&mut flash, 1..3).unwrap();
+    let heap = HeapStats {
+        size: 1 << 20,
+        allocated: 4096,
+        peak: 8192,
+        allocations: 3,
+        total_allocations: 10,
+        failed_allocations: 1,
+        cached: 64,
+        leaked: 32,
+    };
+    let record = CrashRecord::new(
+        "index out of bounds",
+        Some(CrashLocation::new("net_hub/src/lib.rs", 42, 9)),
+        Duration::from_millis(12_345),
+        Some(heap),
+    );
+
+    assert!(log.is_empty());
+    assert_eq!(log.append(&record).unwrap(), 0);
+
+    let stored = log.last().unwrap();
+    let location = stored.location.unwrap();
+
+    assert_eq!(stored.message(), "index out of bounds");
+    assert_eq!(stored.timestamp, Duration::from_millis(12_345));
+    assert_eq!(stored.heap, Some(heap));
+    assert_eq!(location.file(), "net_hub/src/lib.rs");
+    assert_eq!((location.line, location.column), (42, 9));
+    assert!(stored.to_string().starts_with(
+        "#0 at 12.345s: index out of bounds (net_hub/src/lib.rs:42:9) [size: 1048576"
+    ));
+    assert!(flash.sector(0).iter().all(|&byte| byte == 0xFF));
+}
+
+#[test]
+fn survives_reboot() {
+    let mut flash = Flash::new();
+    let mut log = CrashLog::new(&mut flash, 0..2).unwrap();
+
+    log.append(&crash(0)).unwrap();
+    log.append(&crash(1)).unwrap();
+
+    let mut log = CrashLog::new(&mut flash, 0..2).unwrap();
+
+    assert_eq!(log.append(&crash(2)).unwrap(), 2);
+
+    let messages: Vec<_> = log.records().map(|r| r.message().to_string()).collect();
+
+    assert_eq!(messages, ["crash 0", "crash 1", "crash 2"]);
+}
+
+#[test]
+fn ring_drops_oldest_records() {
+    let mut flash = Flash::new();
+    let mut log = CrashLog::new(&mut flash, 0..2).unwrap();
+
+    for i in 0..40 {
+        log.append(&crash(i)).unwrap();
+    }
+
+    let sequences: Vec<_> = CrashLog::new(&mut flash, 0..2)
+        .unwrap()
+        .records()
+        .map(|record| record.sequence)
+        .collect();
+
+    assert!(sequences.len() > SLOTS_PER_SECTOR);
+    assert!(sequences.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    assert_eq!(*sequences.last().unwrap(), 39);
+}
+
+#[test]
+fn clear_erases_records() {
+    let mut flash = Flash::new();
+    let mut log = CrashLog::new(&mut flash, 0..2).unwrap();
+
+    log.append(&crash(0)).unwrap();
+    log.clear().unwrap();
+
+    assert!(log.is_empty());
+    assert!(CrashLog::new(&mut flash, 0..2).unwrap().is_empty());
+    assert_eq!(
+        CrashLog::new(&mut flash, 0..1).err(),
+        Some(CrashLogError::TooFewSectors)
+    );
+}
+
+#[test]
+fn torn_record_is_skipped() {
+    let mut flash = Flash::new();
+    let mut log = CrashLog::new(&mut flash, 0..2).unwrap();
+
+    for i in 0..3 {
+        log.append(&crash(i)).unwrap();
+    }
+
+    flash.cut_power_after(CRASH_RECORD_LENGTH / 2);
+    CrashLog::new(&mut flash, 0..2)
+        .unwrap()
+        .append(&crash(3))
+        .unwrap();
+    flash.restore_power();
+
+    let mut log = CrashLog::new(&mut flash, 0..2).unwrap();
+
+    assert_eq!(log.len(), 3);
+
+    log.append(&crash(4)).unwrap();
+
+    let messages: Vec<_> = log.records().map(|r| r.message().to_string()).collect();
+
+    assert_eq!(messages, ["crash 0", "crash 1", "crash 2", "crash 4"]);
+}
+
+#[test]
+fn truncates_long_strings_on_char_boundaries() {
+    let message = "ж".repeat(MAX_MESSAGE_LENGTH);
+    let file = format!("{}/lib.rs", "я".repeat(MAX_FILE_LENGTH));
+    let record = CrashRecord::new(
+        &message,
+        Some(CrashLocation::new(&file, 1, 1)),
+        Duration::ZERO,
+        None,
+    );
+
+    assert_eq!(record.message().len(), MAX_MESSAGE_LENGTH);
+    assert!(record.location.unwrap().file().ends_with("/lib.rs"));
+    assert!(record.location.unwrap().file().len() <= MAX_FILE_LENGTH);
+}
@@ -0,0 +1,5 @@
+#![no_std]
+
+mod crc32;
+
+pub use crc32::{crc32, Crc32};
@@ -0,0 +1,21 @@
+use crc32::{crc32, Crc32};
+
+#[test]
+fn check_value() {
+    let mut crc = Crc32::new();
+
+    crc.update(b"1234");
+    crc.update(b"56789");
+
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc.finalize(), 0xCBF4_3926);
+}
+
+#[test]
+fn resumes_finished_checksum() {
+    let mut crc = Crc32::resume(crc32(b"12345"));
+
+    crc.update(b"6789");
+
+    assert_eq!(crc.finalize(), crc32(b"123456789"));
+}
@@ -0,0 +1,92 @@
+use chacha20poly1305::{aead::AeadInPlace, ChaCha20Poly1305, KeyInit};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::CryptoError;
+
+pub const KEY_LENGTH: usize = 32;
+pub const NONCE_LENGTH: usize = 12;
+pub const TAG_LENGTH: usize = 16;
+
+pub type Tag = [u8; TAG_LENGTH];
+
+// ChaCha20-Poly1305 key, wiped from memory on drop.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key([u8; KEY_LENGTH]);
+
+impl Key {
+    pub fn from_bytes(bytes: [u8; KEY_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn generate(rng: &mut (impl RngCore + CryptoRng)) -> Result<Self, CryptoError> {
+        let mut key = Self([0; KEY_LENGTH]);
+
+        rng.try_fill_bytes(&mut key.0)
+            .map_err(|_| CryptoError::RngFailure)?;
+
+        Ok(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LENGTH] {
+        &self.0
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(&self.0.into())
+    }
+}
+
+impl core::fmt::Debug for Key {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            unsafe { core::ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nonce(pub [u8; NONCE_LENGTH]);
+
+impl Nonce {
+    // A nonce must never repeat under the same key, random nonces are safe for up to 2^32
+    // messages per key.
+    pub fn generate(rng: &mut (impl RngCore + CryptoRng)) -> Result<Self, CryptoError> {
+        let mut nonce = Self([0; NONCE_LENGTH]);
+
+        rng.try_fill_bytes(&mut nonce.0)
+            .map_err(|_| CryptoError::RngFailure)?;
+
+        Ok(nonce)
+    }
+}
+
+pub fn encrypt_in_place(
+    key: &Key,
+    nonce: &Nonce,
+    aad: &[u8],
+    buffer: &mut [u8],
+) -> Result<Tag, CryptoError> {
+    key.cipher()
+        .encrypt_in_place_detached(&nonce.0.into(), aad, buffer)
+        .map(Into::into)
+        .map_err(|_| CryptoError::MessageTooLarge)
+}
+
+// The buffer is left untouched if the tag doesn't match.
+pub fn decrypt_in_place(
+    key: &Key,
+    nonce: &Nonce,
+    aad: &[u8],
+    buffer: &mut [u8],
+    tag: &Tag,
+) -> Result<(), CryptoError> {
+    key.cipher()
+        .decrypt_in_place_detached(&nonce.0.into(), aad, buffer, tag.into())
+        .map_err(|_| CryptoError::AuthenticationFailed)
+}
@@ -0,0 +1,18 @@
+use core::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CryptoError {
+    AuthenticationFailed,
+    MessageTooLarge,
+    RngFailure,
+}
+
+impl Display for CryptoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CryptoError::AuthenticationFailed => f.write_str("authentication failed"),
+            CryptoError::MessageTooLarge => f.write_str("message is too large"),
+            CryptoError::RngFailure => f.write_str("random number generator failed"),
+        }
+    }
+}
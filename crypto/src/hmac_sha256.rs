@@ -0,0 +1,39 @@
+use hmac::{Hmac, Mac};
+
+use crate::{CryptoError, SHA256_LENGTH};
+
+#[derive(Debug, Clone)]
+pub struct HmacSha256 {
+    inner: Hmac<sha2::Sha256>,
+}
+
+impl HmacSha256 {
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            // HMAC accepts keys of any length.
+            inner: Mac::new_from_slice(key).unwrap(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; SHA256_LENGTH] {
+        self.inner.finalize().into_bytes().into()
+    }
+
+    // Constant time, so the comparison doesn't leak how much of the tag was right.
+    pub fn verify(self, tag: &[u8]) -> Result<(), CryptoError> {
+        self.inner
+            .verify_slice(tag)
+            .map_err(|_| CryptoError::AuthenticationFailed)
+    }
+}
+
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; SHA256_LENGTH] {
+    let mut mac = HmacSha256::new(key);
+
+    mac.update(data);
+    mac.finalize()
+}
@@ -0,0 +1,14 @@
+#![no_std]
+
+mod aead;
+mod crypto_error;
+mod hmac_sha256;
+mod sha256;
+
+pub use aead::{
+    decrypt_in_place, encrypt_in_place, Key, Nonce, Tag, KEY_LENGTH, NONCE_LENGTH, TAG_LENGTH,
+};
+pub use crypto_error::CryptoError;
+pub use hmac_sha256::{hmac_sha256, HmacSha256};
+pub use rand_core;
+pub use sha256::{sha256, Sha256, SHA256_LENGTH};
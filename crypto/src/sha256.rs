@@ -0,0 +1,26 @@
+use sha2::Digest;
+
+pub const SHA256_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Default)]
+pub struct Sha256 {
+    inner: sha2::Sha256,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; SHA256_LENGTH] {
+        self.inner.finalize().into()
+    }
+}
+
+pub fn sha256(data: &[u8]) -> [u8; SHA256_LENGTH] {
+    sha2::Sha256::digest(data).into()
+}
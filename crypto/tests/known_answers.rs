@@ -0,0 +1,147 @@
+use crypto::{
+    decrypt_in_place, encrypt_in_place, hmac_sha256, sha256, CryptoError, HmacSha256, Key, Nonce,
+    Sha256,
+};
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// FIPS 180-2 appendix B.
+#[test]
+fn sha256_known_answers() {
+    let vectors: [(&[u8], &str); 3] = [
+        (
+            b"",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ];
+
+    for (message, digest) in vectors {
+        assert_eq!(sha256(message).to_vec(), hex(digest));
+    }
+}
+
+#[test]
+fn sha256_incremental_million_a() {
+    let mut hasher = Sha256::new();
+
+    for _ in 0..1000 {
+        hasher.update(&[b'a'; 1000]);
+    }
+
+    assert_eq!(
+        hasher.finalize().to_vec(),
+        hex("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+    );
+}
+
+// RFC 4231 test cases 1, 2 and 6.
+#[test]
+fn hmac_sha256_known_answers() {
+    let vectors: [(&[u8], &[u8], &str); 3] = [
+        (
+            &[0x0B; 20],
+            b"Hi There",
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        ),
+        (
+            b"Jefe",
+            b"what do ya want for nothing?",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        ),
+        (
+            &[0xAA; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        ),
+    ];
+
+    for (key, message, tag) in vectors {
+        assert_eq!(hmac_sha256(key, message).to_vec(), hex(tag));
+
+        let mut mac = HmacSha256::new(key);
+
+        mac.update(message);
+        assert_eq!(mac.verify(&hex(tag)), Ok(()));
+    }
+}
+
+#[test]
+fn hmac_sha256_rejects_wrong_tag() {
+    let mut tag = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+
+    tag[31] ^= 1;
+
+    let mut mac = HmacSha256::new(b"Jefe");
+
+    mac.update(b"what do ya want for nothing?");
+    assert_eq!(mac.verify(&tag), Err(CryptoError::AuthenticationFailed));
+}
+
+const PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+one tip for the future, sunscreen would be it.";
+
+fn rfc8439_inputs() -> (Key, Nonce, Vec<u8>) {
+    let key = Key::from_bytes(core::array::from_fn(|i| 0x80 + i as u8));
+    let nonce = Nonce(hex("070000004041424344454647").try_into().unwrap());
+
+    (key, nonce, hex("50515253c0c1c2c3c4c5c6c7"))
+}
+
+// RFC 8439 section 2.8.2.
+#[test]
+fn chacha20_poly1305_known_answer() {
+    let (key, nonce, aad) = rfc8439_inputs();
+    let mut buffer = PLAINTEXT.to_vec();
+
+    let tag = encrypt_in_place(&key, &nonce, &aad, &mut buffer).unwrap();
+
+    assert_eq!(
+        buffer,
+        hex(concat!(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+            "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+            "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+            "3ff4def08e4b7a9de576d26586cec64b6116"
+        ))
+    );
+    assert_eq!(tag.to_vec(), hex("1ae10b594f09e26a7e902ecbd0600691"));
+
+    decrypt_in_place(&key, &nonce, &aad, &mut buffer, &tag).unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+}
+
+#[test]
+fn chacha20_poly1305_rejects_tampering() {
+    let (key, nonce, aad) = rfc8439_inputs();
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = encrypt_in_place(&key, &nonce, &aad, &mut buffer).unwrap();
+
+    buffer[0] ^= 1;
+
+    let tampered = buffer.clone();
+
+    assert_eq!(
+        decrypt_in_place(&key, &nonce, &aad, &mut buffer, &tag),
+        Err(CryptoError::AuthenticationFailed)
+    );
+    assert_eq!(buffer, tampered);
+
+    buffer[0] ^= 1;
+    assert_eq!(
+        decrypt_in_place(&key, &nonce, b"other aad", &mut buffer, &tag),
+        Err(CryptoError::AuthenticationFailed)
+    );
+}
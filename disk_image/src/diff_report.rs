@@ -0,0 +1,17 @@
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffReport {
+    pub source_size: usize,
+    pub target_size: usize,
+    // Bytes compared, the smaller of the two sizes.
+    pub compared: usize,
+    pub differing_bytes: usize,
+    // Runs of differing bytes found, which can be more than were recorded.
+    pub ranges: usize,
+    pub recorded: usize,
+}
+
+impl DiffReport {
+    pub fn is_identical(&self) -> bool {
+        self.source_size == self.target_size && self.differing_bytes == 0
+    }
+}
@@ -0,0 +1,22 @@
+use core::fmt::Display;
+
+use block_device::BlockError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImageError {
+    Source(BlockError),
+    Target(BlockError),
+    TargetTooSmall,
+    BufferTooSmall,
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ImageError::Source(error) => write!(f, "source device error: {error}"),
+            ImageError::Target(error) => write!(f, "target device error: {error}"),
+            ImageError::TargetTooSmall => f.write_str("target device is smaller than the source"),
+            ImageError::BufferTooSmall => f.write_str("buffer is smaller than one sector"),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use core::ops::Range;
+
+use block_device::{BlockDevice, MAX_READ_WRITE_SIZE, SECTOR_SIZE};
+use crypto::Sha256;
+
+use crate::{DiffReport, ImageError, Progress, VerifyReport};
+
+// Streams the whole source onto the start of the target through `buffer`, at most
+// `MAX_READ_WRITE_SIZE` bytes at a time, so it needs room for at least one sector. Returns
+// the number of bytes copied.
+pub fn copy<S: BlockDevice, T: BlockDevice>(
+    source: &mut S,
+    target: &mut T,
+    buffer: &mut [u8],
+    mut progress: impl FnMut(Progress),
+) -> Result<usize, ImageError> {
+    let total = source.size();
+    let chunk = chunk_length(buffer.len())?;
+
+    if target.size() < total {
+        return Err(ImageError::TargetTooSmall);
+    }
+
+    for offset in (0..total).step_by(chunk) {
+        let piece = &mut buffer[..chunk.min(total - offset)];
+
+        source.read_at(offset, piece).map_err(ImageError::Source)?;
+        target.write_at(offset, piece).map_err(ImageError::Target)?;
+        progress(Progress {
+            done: offset + piece.len(),
+            total,
+        });
+    }
+
+    target.flush().map_err(ImageError::Target)?;
+
+    Ok(total)
+}
+
+// Hashes the source and as much of the target as the source is long, reading both back
+// from the devices.
+pub fn verify<S: BlockDevice, T: BlockDevice>(
+    source: &mut S,
+    target: &mut T,
+    buffer: &mut [u8],
+    mut progress: impl FnMut(Progress),
+) -> Result<VerifyReport, ImageError> {
+    let total = source.size();
+    let chunk = chunk_length(buffer.len())?;
+    let mut source_hash = Sha256::new();
+    let mut target_hash = Sha256::new();
+
+    if target.size() < total {
+        return Err(ImageError::TargetTooSmall);
+    }
+
+    for offset in (0..total).step_by(chunk) {
+        let piece = &mut buffer[..chunk.min(total - offset)];
+
+        source.read_at(offset, piece).map_err(ImageError::Source)?;
+        source_hash.update(piece);
+        target.read_at(offset, piece).map_err(ImageError::Target)?;
+        target_hash.update(piece);
+        progress(Progress {
+            done: offset + piece.len(),
+            total,
+        });
+    }
+
+    Ok(VerifyReport {
+        length: total,
+        source_hash: source_hash.finalize(),
+        target_hash: target_hash.finalize(),
+    })
+}
+
+// Compares the devices byte by byte up to the end of the smaller one. Runs of differing
+// bytes go into `ranges` in order until it is full, the report counts them all. `buffer`
+// is split between the two sides, each needs room for at least one sector.
+pub fn compare<S: BlockDevice, T: BlockDevice>(
+    source: &mut S,
+    target: &mut T,
+    buffer: &mut [u8],
+    ranges: &mut [Range<usize>],
+    mut progress: impl FnMut(Progress),
+) -> Result<DiffReport, ImageError> {
+    let (source_buffer, target_buffer) = buffer.split_at_mut(buffer.len() / 2);
+    let chunk = chunk_length(source_buffer.len())?;
+    let mut report = DiffReport {
+        source_size: source.size(),
+        target_size: target.size(),
+        compared: source.size().min(target.size()),
+        ..DiffReport::default()
+    };
+    let mut current: Option<Range<usize>> = None;
+    let mut record = |report: &mut DiffReport, range: Range<usize>| {
+        if let Some(slot) = ranges.get_mut(report.recorded) {
+            *slot = range;
+            report.recorded += 1;
+        }
+
+        report.ranges += 1;
+    };
+
+    for offset in (0..report.compared).step_by(chunk) {
+        let length = chunk.min(report.compared - offset);
+        let source_piece = &mut source_buffer[..length];
+        let target_piece = &mut target_buffer[..length];
+
+        source
+            .read_at(offset, source_piece)
+            .map_err(ImageError::Source)?;
+        target
+            .read_at(offset, target_piece)
+            .map_err(ImageError::Target)?;
+
+        for (i, (a, b)) in source_piece.iter().zip(target_piece.iter()).enumerate() {
+            let position = offset + i;
+
+            if a == b {
+                if let Some(range) = current.take() {
+                    record(&mut report, range);
+                }
+
+                continue;
+            }
+
+            report.differing_bytes += 1;
+
+            match &mut current {
+                Some(range) => range.end = position + 1,
+                None => current = Some(position..position + 1),
+            }
+        }
+
+        progress(Progress {
+            done: offset + length,
+            total: report.compared,
+        });
+    }
+
+    if let Some(range) = current {
+        record(&mut report, range);
+    }
+
+    Ok(report)
+}
+
+// Whole sectors, never more than the devices take in one op.
+fn chunk_length(buffer: usize) -> Result<usize, ImageError> {
+    match buffer.min(MAX_READ_WRITE_SIZE) {
+        length if length < SECTOR_SIZE => Err(ImageError::BufferTooSmall),
+        length => Ok(length / SECTOR_SIZE * SECTOR_SIZE),
+    }
+}
@@ -0,0 +1,14 @@
+#![no_std]
+
+mod diff_report;
+mod image_error;
+mod imaging;
+mod progress;
+mod verify_report;
+
+pub use block_device;
+pub use diff_report::DiffReport;
+pub use image_error::ImageError;
+pub use imaging::{compare, copy, verify};
+pub use progress::Progress;
+pub use verify_report::VerifyReport;
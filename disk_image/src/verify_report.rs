@@ -0,0 +1,15 @@
+use crypto::SHA256_LENGTH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyReport {
+    // Bytes hashed on each side, the size of the source.
+    pub length: usize,
+    pub source_hash: [u8; SHA256_LENGTH],
+    pub target_hash: [u8; SHA256_LENGTH],
+}
+
+impl VerifyReport {
+    pub fn matches(&self) -> bool {
+        self.source_hash == self.target_hash
+    }
+}
@@ -0,0 +1,146 @@
+use disk_image::{
+    block_device::{BlockDevice, BlockError, MemoryDisk, MAX_READ_WRITE_SIZE},
+    compare, copy, verify, ImageError, Progress,
+};
+use partition_table::{PartitionTable, PartitionType};
+
+const FLOPPY: usize = 1474560;
+
+// Fails any transfer the real drives would refuse as too large.
+struct StrictDisk<'a>(MemoryDisk<'a>);
+
+impl BlockDevice for StrictDisk<'_> {
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        assert!(buffer.len() <= MAX_READ_WRITE_SIZE);
+        self.0.read_at(offset, buffer)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        assert!(data.len() <= MAX_READ_WRITE_SIZE);
+        self.0.write_at(offset, data)
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.0.flush()
+    }
+}
+
+fn pattern(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i * 31 + i / 4093) as u8).collect()
+}
+
+#[test]
+fn copies_and_verifies_a_floppy() {
+    let mut floppy = pattern(FLOPPY);
+    let mut hdd = vec![0; 2 * FLOPPY];
+    let mut source = StrictDisk(MemoryDisk::new(&mut floppy));
+    let mut target = StrictDisk(MemoryDisk::new(&mut hdd));
+    let mut buffer = vec![0; 3 * MAX_READ_WRITE_SIZE];
+    let mut updates = Vec::new();
+
+    let copied = copy(&mut source, &mut target, &mut buffer, |progress| {
+        updates.push(progress)
+    })
+    .unwrap();
+
+    assert_eq!(copied, FLOPPY);
+    assert_eq!(updates.len(), FLOPPY.div_ceil(MAX_READ_WRITE_SIZE));
+    assert_eq!(
+        *updates.last().unwrap(),
+        Progress {
+            done: FLOPPY,
+            total: FLOPPY
+        }
+    );
+    assert!(updates.windows(2).all(|pair| pair[0].done < pair[1].done));
+    assert_eq!(target.0.flushes(), 1);
+
+    let report = verify(&mut source, &mut target, &mut buffer, |_| {}).unwrap();
+
+    assert!(report.matches());
+    assert_eq!(report.length, FLOPPY);
+
+    // And back onto a blank floppy.
+    let mut blank = vec![0; FLOPPY];
+    let mut back = StrictDisk(MemoryDisk::new(&mut blank));
+
+    assert_eq!(
+        copy(&mut target, &mut back, &mut buffer, |_| {}),
+        Err(ImageError::TargetTooSmall)
+    );
+}
+
+#[test]
+fn reports_differences() {
+    let mut original = pattern(FLOPPY);
+    let mut damaged = original.clone();
+
+    damaged[100..110].fill(0xEE);
+    damaged[MAX_READ_WRITE_SIZE - 2..MAX_READ_WRITE_SIZE + 3]
+        .iter_mut()
+        .for_each(|byte| *byte ^= 1);
+    damaged[FLOPPY - 1] ^= 0x80;
+    damaged.push(0);
+
+    let mut source = MemoryDisk::new(&mut original);
+    let mut target = MemoryDisk::new(&mut damaged);
+    let mut buffer = vec![0; 8192];
+    let mut ranges = [0..0, 0..0];
+
+    assert!(!verify(&mut source, &mut target, &mut buffer, |_| {})
+        .unwrap()
+        .matches());
+
+    let report = compare(&mut source, &mut target, &mut buffer, &mut ranges, |_| {}).unwrap();
+
+    assert!(!report.is_identical());
+    assert_eq!(report.source_size, FLOPPY);
+    assert_eq!(report.target_size, FLOPPY + 1);
+    assert_eq!(report.compared, FLOPPY);
+    assert_eq!(report.differing_bytes, 10 + 5 + 1);
+    assert_eq!(report.ranges, 3);
+    assert_eq!(report.recorded, 2);
+    // The run across the chunk boundary stays in one piece.
+    assert_eq!(
+        ranges,
+        [100..110, MAX_READ_WRITE_SIZE - 2..MAX_READ_WRITE_SIZE + 3]
+    );
+}
+
+#[test]
+fn images_a_partition() {
+    let mut data = vec![0; 4 * 1024 * 1024];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_mbr(&disk, 7).unwrap();
+
+    table.add(PartitionType::MBR_FAT12, 2880).unwrap();
+    table.write(&mut disk).unwrap();
+
+    let mut floppy = pattern(FLOPPY);
+    let mut source = MemoryDisk::new(&mut floppy);
+    let mut partition = table.open(&mut disk, 0).unwrap();
+    let mut buffer = vec![0; MAX_READ_WRITE_SIZE];
+
+    copy(&mut source, &mut partition, &mut buffer, |_| {}).unwrap();
+
+    let report = compare(&mut source, &mut partition, &mut buffer, &mut [], |_| {}).unwrap();
+
+    assert!(report.is_identical());
+    assert_eq!(
+        copy(&mut source, &mut partition, &mut [], |_| {}),
+        Err(ImageError::BufferTooSmall)
+    );
+    assert_eq!(
+        copy(&mut source, &mut partition, &mut [0; 511], |_| {}),
+        Err(ImageError::BufferTooSmall)
+    );
+    assert_eq!(
+        ImageError::BufferTooSmall.to_string(),
+        "buffer is smaller than one sector"
+    );
+    assert_eq!(&disk.data()[8 * 512..8 * 512 + 64], &pattern(64)[..]);
+}
@@ -0,0 +1,217 @@
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use block_device::{check_range, BlockDevice, MAX_READ_WRITE_SIZE};
+
+use crate::{
+    request::{Buffer, Request},
+    QueueError, RequestId, MAX_REQUESTS,
+};
+
+// Called from `step` as each request finishes.
+pub type Callback = fn(RequestId, Result<usize, QueueError>);
+
+struct Inner<'b, D: BlockDevice> {
+    device: D,
+    requests: [Option<Request<'b>>; MAX_REQUESTS],
+    next_id: u64,
+    callback: Option<Callback>,
+}
+
+// Reads and writes for a drive, carried out a piece at a time so a large copy doesn't hold up
+// everything else. Completion doesn't come from the device's PLIC interrupt: `call_op` has the
+// result by the time it returns and the drives raise no completion interrupt, so there is
+// nothing to wait on. Instead the queue is cooperative and polled, nothing moves until `step`
+// is called, from the idle loop or a timer tick. Each call blocks on at most
+// `MAX_READ_WRITE_SIZE` bytes.
+// Finished requests are reported to the callback, wake their `RequestFuture` and wait in the
+// queue until their result is taken.
+//
+// Requests are served in the order they were submitted.
+pub struct DiskQueue<'b, D: BlockDevice> {
+    inner: RefCell<Inner<'b, D>>,
+}
+
+impl<'b, D: BlockDevice> DiskQueue<'b, D> {
+    pub fn new(device: D) -> Self {
+        Self {
+            inner: RefCell::new(Inner {
+                device,
+                requests: [const { None }; MAX_REQUESTS],
+                next_id: 0,
+                callback: None,
+            }),
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner.into_inner().device
+    }
+
+    pub fn set_callback(&self, callback: Option<Callback>) {
+        self.inner.borrow_mut().callback = callback;
+    }
+
+    pub fn submit_read(
+        &self,
+        offset: usize,
+        buffer: &'b mut [u8],
+    ) -> Result<RequestId, QueueError> {
+        self.submit(offset, Buffer::Read(buffer))
+    }
+
+    pub fn submit_write(&self, offset: usize, data: &'b [u8]) -> Result<RequestId, QueueError> {
+        self.submit(offset, Buffer::Write(data))
+    }
+
+    fn submit(&self, offset: usize, buffer: Buffer<'b>) -> Result<RequestId, QueueError> {
+        let mut inner = self.inner.borrow_mut();
+
+        check_range(inner.device.size(), offset, buffer.len())?;
+
+        let id = RequestId(inner.next_id);
+        let slot = inner
+            .requests
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(QueueError::QueueFull)?;
+
+        *slot = Some(Request {
+            id,
+            offset,
+            buffer,
+            done: 0,
+            result: None,
+            waker: None,
+        });
+        inner.next_id += 1;
+
+        Ok(id)
+    }
+
+    // Requests submitted and not finished yet.
+    pub fn pending(&self) -> usize {
+        self.inner
+            .borrow()
+            .requests
+            .iter()
+            .flatten()
+            .filter(|request| request.result.is_none())
+            .count()
+    }
+
+    // Takes the result of a finished request, `Poll::Pending` while it is still running.
+    pub fn result(&self, id: RequestId) -> Poll<Result<usize, QueueError>> {
+        let mut inner = self.inner.borrow_mut();
+        let Some(slot) = inner
+            .requests
+            .iter_mut()
+            .find(|slot| slot.as_ref().is_some_and(|request| request.id == id))
+        else {
+            return Poll::Ready(Err(QueueError::UnknownRequest));
+        };
+
+        match slot.as_ref().and_then(|request| request.result) {
+            Some(result) => {
+                *slot = None;
+
+                Poll::Ready(result)
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    pub fn wait(&self, id: RequestId) -> RequestFuture<'_, 'b, D> {
+        RequestFuture { queue: self, id }
+    }
+
+    // Transfers the next piece of the oldest unfinished request. Returns whether there is
+    // more work left.
+    pub fn step(&self) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        let Inner {
+            device,
+            requests,
+            callback,
+            ..
+        } = &mut *inner;
+        let Some(request) = requests
+            .iter_mut()
+            .flatten()
+            .filter(|request| request.result.is_none())
+            .min_by_key(|request| request.id)
+        else {
+            return false;
+        };
+        let length = MAX_READ_WRITE_SIZE.min(request.buffer.len() - request.done);
+        let offset = request.offset + request.done;
+        let range = request.done..request.done + length;
+        let transferred = match &mut request.buffer {
+            Buffer::Read(buffer) => device.read_at(offset, &mut buffer[range]),
+            Buffer::Write(data) => device.write_at(offset, &data[range]),
+        };
+
+        request.result = match transferred {
+            Ok(()) => {
+                request.done += length;
+                (request.done == request.buffer.len()).then_some(Ok(request.done))
+            }
+            Err(error) => Some(Err(error.into())),
+        };
+
+        let finished = request
+            .result
+            .map(|result| (request.id, result, request.waker.take(), *callback));
+        let more = requests
+            .iter()
+            .flatten()
+            .any(|request| request.result.is_none());
+
+        // Released first, so the callback and the woken task may use the queue.
+        drop(inner);
+
+        if let Some((id, result, waker, callback)) = finished {
+            if let Some(callback) = callback {
+                callback(id, result);
+            }
+
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+
+        more
+    }
+}
+
+pub struct RequestFuture<'q, 'b, D: BlockDevice> {
+    queue: &'q DiskQueue<'b, D>,
+    id: RequestId,
+}
+
+impl<D: BlockDevice> Future for RequestFuture<'_, '_, D> {
+    type Output = Result<usize, QueueError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let poll = self.queue.result(self.id);
+
+        if poll.is_pending() {
+            let mut inner = self.queue.inner.borrow_mut();
+
+            if let Some(request) = inner
+                .requests
+                .iter_mut()
+                .flatten()
+                .find(|request| request.id == self.id)
+            {
+                request.waker = Some(cx.waker().clone());
+            }
+        }
+
+        poll
+    }
+}
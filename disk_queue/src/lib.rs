@@ -0,0 +1,12 @@
+#![no_std]
+
+mod disk_queue;
+mod queue_error;
+mod request;
+
+pub use block_device;
+pub use disk_queue::{Callback, DiskQueue, RequestFuture};
+pub use queue_error::QueueError;
+pub use request::RequestId;
+
+pub const MAX_REQUESTS: usize = 8;
@@ -0,0 +1,26 @@
+use core::fmt::Display;
+
+use block_device::BlockError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QueueError {
+    Device(BlockError),
+    QueueFull,
+    UnknownRequest,
+}
+
+impl Display for QueueError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            QueueError::Device(error) => write!(f, "device error: {error}"),
+            QueueError::QueueFull => f.write_str("too many requests in the queue"),
+            QueueError::UnknownRequest => f.write_str("no such request"),
+        }
+    }
+}
+
+impl From<BlockError> for QueueError {
+    fn from(value: BlockError) -> Self {
+        Self::Device(value)
+    }
+}
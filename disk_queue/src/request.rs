@@ -0,0 +1,30 @@
+use core::task::Waker;
+
+use crate::QueueError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestId(pub(crate) u64);
+
+pub(crate) enum Buffer<'b> {
+    Read(&'b mut [u8]),
+    Write(&'b [u8]),
+}
+
+impl Buffer<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Buffer::Read(buffer) => buffer.len(),
+            Buffer::Write(data) => data.len(),
+        }
+    }
+}
+
+pub(crate) struct Request<'b> {
+    pub id: RequestId,
+    pub offset: usize,
+    pub buffer: Buffer<'b>,
+    // Bytes transferred so far.
+    pub done: usize,
+    pub result: Option<Result<usize, QueueError>>,
+    pub waker: Option<Waker>,
+}
@@ -0,0 +1,179 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+use disk_queue::{
+    block_device::{BlockDevice, BlockError, MemoryDisk, MAX_READ_WRITE_SIZE},
+    DiskQueue, QueueError, RequestId, MAX_REQUESTS,
+};
+
+const DISK: usize = 4 * MAX_READ_WRITE_SIZE;
+
+struct CountingWaker(AtomicUsize);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// Rejects writes from a given offset on, like a disk going bad halfway through.
+struct FailingDisk<'a> {
+    disk: MemoryDisk<'a>,
+    bad_from: usize,
+}
+
+impl BlockDevice for FailingDisk<'_> {
+    fn size(&self) -> usize {
+        self.disk.size()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        self.disk.read_at(offset, buffer)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        if offset + data.len() > self.bad_from {
+            return Err(BlockError::Device(-7));
+        }
+
+        self.disk.write_at(offset, data)
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.disk.flush()
+    }
+}
+
+fn pattern(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i * 13 + i / 509) as u8).collect()
+}
+
+#[test]
+fn transfers_one_piece_per_step() {
+    let mut data = vec![0; DISK];
+    let queue = DiskQueue::new(MemoryDisk::new(&mut data));
+    let record = pattern(2 * MAX_READ_WRITE_SIZE + 100);
+    let mut buffer = vec![0; record.len()];
+
+    let write = queue.submit_write(50, &record).unwrap();
+    let read = queue.submit_read(50, &mut buffer).unwrap();
+
+    assert_eq!(queue.pending(), 2);
+    assert_eq!(queue.result(write), Poll::Pending);
+
+    let mut steps = 1;
+
+    while queue.step() {
+        steps += 1;
+    }
+
+    // Three pieces each, the write before the read.
+    assert_eq!(steps, 6);
+    assert!(!queue.step());
+    assert_eq!(queue.pending(), 0);
+    assert_eq!(queue.result(write), Poll::Ready(Ok(record.len())));
+    assert_eq!(queue.result(read), Poll::Ready(Ok(record.len())));
+    assert_eq!(
+        queue.result(read),
+        Poll::Ready(Err(QueueError::UnknownRequest))
+    );
+
+    queue.into_inner();
+    assert_eq!(buffer, record);
+}
+
+#[test]
+fn future_completes_when_woken() {
+    let mut data = pattern(DISK);
+    let expected = data[1000..1000 + 2 * MAX_READ_WRITE_SIZE].to_vec();
+    let queue = DiskQueue::new(MemoryDisk::new(&mut data));
+    let mut buffer = vec![0; expected.len()];
+    let id = queue.submit_read(1000, &mut buffer).unwrap();
+    let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+    let waker = Waker::from(counter.clone());
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(queue.wait(id));
+
+    assert!(future.as_mut().poll(&mut context).is_pending());
+    assert!(queue.step());
+    assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+    assert!(!queue.step());
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        future.as_mut().poll(&mut context),
+        Poll::Ready(Ok(expected.len()))
+    );
+
+    drop(queue);
+    assert_eq!(buffer, expected);
+}
+
+static FINISHED: Mutex<Vec<(RequestId, Result<usize, QueueError>)>> = Mutex::new(Vec::new());
+
+#[test]
+fn reports_to_callback_and_stops_on_errors() {
+    let mut data = vec![0; DISK];
+    let disk = FailingDisk {
+        disk: MemoryDisk::new(&mut data),
+        bad_from: MAX_READ_WRITE_SIZE + 10,
+    };
+    let queue = DiskQueue::new(disk);
+    let record = pattern(2 * MAX_READ_WRITE_SIZE);
+
+    queue.set_callback(Some(|id, result| {
+        FINISHED.lock().unwrap().push((id, result))
+    }));
+
+    let failing = queue.submit_write(0, &record).unwrap();
+    let small = queue.submit_write(0, b"ok").unwrap();
+
+    while queue.step() {}
+
+    assert_eq!(
+        *FINISHED.lock().unwrap(),
+        [
+            (failing, Err(QueueError::Device(BlockError::Device(-7)))),
+            (small, Ok(2)),
+        ]
+    );
+
+    // The piece before the bad one made it to the disk.
+    let disk = queue.into_inner();
+
+    assert_eq!(&disk.disk.data()[..2], b"ok");
+    assert_eq!(
+        disk.disk.data()[2..MAX_READ_WRITE_SIZE],
+        record[2..MAX_READ_WRITE_SIZE]
+    );
+}
+
+#[test]
+fn rejects_bad_requests() {
+    let mut data = vec![0; DISK];
+    let queue = DiskQueue::new(MemoryDisk::new(&mut data));
+    let record = [1; 16];
+
+    assert_eq!(
+        queue.submit_write(DISK - 8, &record),
+        Err(QueueError::Device(BlockError::OutOfRange))
+    );
+
+    let ids: Vec<_> = (0..MAX_REQUESTS)
+        .map(|i| queue.submit_write(i * 16, &record).unwrap())
+        .collect();
+
+    assert_eq!(queue.submit_write(0, &record), Err(QueueError::QueueFull));
+
+    // Finished requests keep their place until their result is taken.
+    while queue.step() {}
+    assert_eq!(queue.submit_write(0, &record), Err(QueueError::QueueFull));
+    assert_eq!(queue.result(ids[0]), Poll::Ready(Ok(16)));
+    assert!(queue.submit_write(0, &record).is_ok());
+}
@@ -0,0 +1,120 @@
+use crate::FatType;
+
+pub(crate) const BOOT_SECTOR_LENGTH: usize = 512;
+pub(crate) const FS_INFO_SECTOR: u16 = 1;
+pub(crate) const BACKUP_BOOT_SECTOR: u16 = 6;
+
+pub(crate) const MEDIA: u8 = 0xF8;
+const LABEL: &[u8; 11] = b"NO NAME    ";
+
+// The BIOS parameter block, the part of the boot sector that describes the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BootSector {
+    pub bytes_per_sector: u16,
+    pub sectors_per_cluster: u8,
+    pub reserved_sectors: u16,
+    pub fats: u8,
+    pub root_entries: u16,
+    pub total_sectors: u32,
+    pub fat_sectors: u32,
+    pub root_cluster: u32,
+}
+
+impl BootSector {
+    pub fn from_bytes(bytes: &[u8; BOOT_SECTOR_LENGTH]) -> Option<Self> {
+        let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        let total_sectors = match u16_at(19) {
+            0 => u32_at(32),
+            sectors => sectors as u32,
+        };
+        let fat_sectors = match u16_at(22) {
+            0 => u32_at(36),
+            sectors => sectors as u32,
+        };
+        let boot = Self {
+            bytes_per_sector: u16_at(11),
+            sectors_per_cluster: bytes[13],
+            reserved_sectors: u16_at(14),
+            fats: bytes[16],
+            root_entries: u16_at(17),
+            total_sectors,
+            fat_sectors,
+            root_cluster: if u16_at(22) == 0 { u32_at(44) } else { 0 },
+        };
+
+        let valid = bytes[510..] == [0x55, 0xAA]
+            && boot.bytes_per_sector.is_power_of_two()
+            && (512..=4096).contains(&boot.bytes_per_sector)
+            && boot.sectors_per_cluster.is_power_of_two()
+            && boot.reserved_sectors > 0
+            && boot.fats > 0
+            && boot.total_sectors > 0
+            && boot.fat_sectors > 0;
+
+        valid.then_some(boot)
+    }
+
+    pub fn to_bytes(self, fat_type: FatType) -> [u8; BOOT_SECTOR_LENGTH] {
+        let mut bytes = [0; BOOT_SECTOR_LENGTH];
+        let small = self.total_sectors <= u16::MAX as u32 && fat_type != FatType::Fat32;
+
+        // A jump over the parameter block, in case anything tries to boot it.
+        bytes[..3].copy_from_slice(&[0xEB, if small { 0x3C } else { 0x58 }, 0x90]);
+        bytes[3..11].copy_from_slice(b"ONYX    ");
+        bytes[11..13].copy_from_slice(&self.bytes_per_sector.to_le_bytes());
+        bytes[13] = self.sectors_per_cluster;
+        bytes[14..16].copy_from_slice(&self.reserved_sectors.to_le_bytes());
+        bytes[16] = self.fats;
+        bytes[17..19].copy_from_slice(&self.root_entries.to_le_bytes());
+        bytes[21] = MEDIA;
+        bytes[24..26].copy_from_slice(&63u16.to_le_bytes());
+        bytes[26..28].copy_from_slice(&255u16.to_le_bytes());
+
+        if small {
+            bytes[19..21].copy_from_slice(&(self.total_sectors as u16).to_le_bytes());
+        } else {
+            bytes[32..36].copy_from_slice(&self.total_sectors.to_le_bytes());
+        }
+
+        // The extended boot record sits after the FAT32 fields when there are any.
+        let extended = if fat_type == FatType::Fat32 {
+            bytes[36..40].copy_from_slice(&self.fat_sectors.to_le_bytes());
+            bytes[44..48].copy_from_slice(&self.root_cluster.to_le_bytes());
+            bytes[48..50].copy_from_slice(&FS_INFO_SECTOR.to_le_bytes());
+            bytes[50..52].copy_from_slice(&BACKUP_BOOT_SECTOR.to_le_bytes());
+
+            64
+        } else {
+            bytes[22..24].copy_from_slice(&(self.fat_sectors as u16).to_le_bytes());
+
+            36
+        };
+
+        bytes[extended] = 0x80;
+        bytes[extended + 2] = 0x29;
+        bytes[extended + 7..extended + 18].copy_from_slice(LABEL);
+        bytes[extended + 18..extended + 26].copy_from_slice(match fat_type {
+            FatType::Fat12 => b"FAT12   ",
+            FatType::Fat16 => b"FAT16   ",
+            FatType::Fat32 => b"FAT32   ",
+        });
+        bytes[510..].copy_from_slice(&[0x55, 0xAA]);
+
+        bytes
+    }
+}
+
+// FAT32 keeps a free cluster hint here. It is only a hint, so it is written as unknown.
+pub(crate) fn fs_info() -> [u8; BOOT_SECTOR_LENGTH] {
+    let mut bytes = [0; BOOT_SECTOR_LENGTH];
+
+    bytes[..4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+    bytes[484..488].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+    bytes[488..496].fill(0xFF);
+    bytes[508..].copy_from_slice(&0xAA55_0000u32.to_le_bytes());
+
+    bytes
+}
@@ -0,0 +1,35 @@
+use block_device::BlockDevice;
+
+use crate::{file_system::DirCursor, DirEntry, FatError, FileSystem};
+
+pub struct Dir<'a, D: BlockDevice> {
+    fs: &'a mut FileSystem<D>,
+    cursor: DirCursor,
+    done: bool,
+}
+
+impl<'a, D: BlockDevice> Dir<'a, D> {
+    pub(crate) fn new(fs: &'a mut FileSystem<D>, cursor: DirCursor) -> Self {
+        Self {
+            fs,
+            cursor,
+            done: false,
+        }
+    }
+}
+
+impl<D: BlockDevice> Iterator for Dir<'_, D> {
+    type Item = Result<DirEntry, FatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = self.fs.next_entry(&mut self.cursor).transpose();
+
+        self.done = !matches!(entry, Some(Ok(_)));
+
+        entry
+    }
+}
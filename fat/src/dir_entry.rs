@@ -0,0 +1,149 @@
+use crate::{
+    long_name::{LongName, NAME_BUFFER_LENGTH},
+    short_name::{self, ShortName, SHORT_NAME_LENGTH},
+};
+
+pub(crate) const DIR_ENTRY_LENGTH: usize = 32;
+pub(crate) const ATTR_VOLUME_ID: u8 = 0x08;
+pub(crate) const ATTR_DIRECTORY: u8 = 0x10;
+pub(crate) const ATTR_ARCHIVE: u8 = 0x20;
+pub(crate) const ATTR_LONG_NAME: u8 = 0x0F;
+pub(crate) const END_OF_DIRECTORY: u8 = 0x00;
+pub(crate) const DELETED: u8 = 0xE5;
+
+// 1980-01-01, the earliest date FAT can store. There is no clock to stamp files with.
+const DATE: u16 = (1 << 5) | 1;
+
+pub(crate) type RawEntry = [u8; DIR_ENTRY_LENGTH];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DirLocation {
+    // The fixed root directory region of FAT12 and FAT16.
+    Root,
+    Cluster(u32),
+}
+
+// Where the short entry of a file sits, and how many long name entries come before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EntryLocation {
+    pub dir: DirLocation,
+    pub index: usize,
+    pub offset: usize,
+    pub long_entries: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirEntry {
+    name: [u8; NAME_BUFFER_LENGTH],
+    name_length: usize,
+    short_name: [u8; 12],
+    short_name_length: usize,
+    pub(crate) attributes: u8,
+    pub(crate) cluster: u32,
+    pub(crate) size: u32,
+    pub(crate) location: EntryLocation,
+}
+
+impl DirEntry {
+    pub(crate) fn from_raw(
+        raw: &RawEntry,
+        long_name: &mut LongName,
+        location: EntryLocation,
+    ) -> Self {
+        let short: &ShortName = raw[..SHORT_NAME_LENGTH].try_into().unwrap();
+        let mut entry = Self {
+            name: [0; NAME_BUFFER_LENGTH],
+            name_length: 0,
+            short_name: [0; 12],
+            short_name_length: 0,
+            attributes: raw[11],
+            cluster: (u16_at(raw, 20) as u32) << 16 | u16_at(raw, 26) as u32,
+            size: u32::from_le_bytes(raw[28..32].try_into().unwrap()),
+            location,
+        };
+
+        entry.short_name_length = short_name::display(short, raw[12], &mut entry.short_name);
+
+        match long_name.take(short_name::checksum(short), &mut entry.name) {
+            Some((length, entries)) => {
+                entry.name_length = length;
+                entry.location.long_entries = entries;
+            }
+            None => {
+                entry.name_length = entry.short_name_length;
+                entry.name[..entry.short_name_length]
+                    .copy_from_slice(&entry.short_name[..entry.short_name_length]);
+            }
+        }
+
+        entry
+    }
+
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_length]).unwrap_or("")
+    }
+
+    pub fn short_name(&self) -> &str {
+        core::str::from_utf8(&self.short_name[..self.short_name_length]).unwrap_or("")
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.attributes & ATTR_DIRECTORY != 0
+    }
+
+    pub fn is_file(&self) -> bool {
+        !self.is_dir()
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn attributes(&self) -> u8 {
+        self.attributes
+    }
+
+    pub(crate) fn is_dot(&self) -> bool {
+        self.short_name() == "." || self.short_name() == ".."
+    }
+
+    // Both names are matched without regard to case, like every other FAT implementation.
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        eq_ignore_case(self.name(), name) || eq_ignore_case(self.short_name(), name)
+    }
+}
+
+pub(crate) fn raw_entry(short: &ShortName, flags: u8, attributes: u8, cluster: u32) -> RawEntry {
+    let mut raw = [0; DIR_ENTRY_LENGTH];
+
+    raw[..SHORT_NAME_LENGTH].copy_from_slice(short);
+    raw[11] = attributes;
+    raw[12] = flags;
+
+    for offset in [16, 18, 24] {
+        raw[offset..offset + 2].copy_from_slice(&DATE.to_le_bytes());
+    }
+
+    set_cluster(&mut raw, cluster);
+
+    raw
+}
+
+pub(crate) fn set_cluster(raw: &mut RawEntry, cluster: u32) {
+    raw[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    raw[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+}
+
+pub(crate) fn set_size(raw: &mut RawEntry, size: u32) {
+    raw[28..32].copy_from_slice(&size.to_le_bytes());
+}
+
+fn u16_at(raw: &RawEntry, offset: usize) -> u16 {
+    u16::from_le_bytes([raw[offset], raw[offset + 1]])
+}
+
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
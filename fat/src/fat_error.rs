@@ -0,0 +1,48 @@
+use core::fmt::Display;
+
+use block_device::BlockError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FatError {
+    Device(BlockError),
+    NotFat,
+    UnsupportedSize,
+    Corrupted,
+    NotFound,
+    AlreadyExists,
+    NotADirectory,
+    IsADirectory,
+    DirectoryNotEmpty,
+    InvalidName,
+    InvalidSeek,
+    DirectoryFull,
+    DiskFull,
+    FileTooLarge,
+}
+
+impl Display for FatError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FatError::Device(error) => write!(f, "device error: {error}"),
+            FatError::NotFat => f.write_str("no FAT filesystem on the device"),
+            FatError::UnsupportedSize => f.write_str("device size is unsupported by the FAT type"),
+            FatError::Corrupted => f.write_str("filesystem is corrupted"),
+            FatError::NotFound => f.write_str("no such file or directory"),
+            FatError::AlreadyExists => f.write_str("file already exists"),
+            FatError::NotADirectory => f.write_str("not a directory"),
+            FatError::IsADirectory => f.write_str("is a directory"),
+            FatError::DirectoryNotEmpty => f.write_str("directory is not empty"),
+            FatError::InvalidName => f.write_str("invalid file name"),
+            FatError::InvalidSeek => f.write_str("seek past the end of the file"),
+            FatError::DirectoryFull => f.write_str("root directory is full"),
+            FatError::DiskFull => f.write_str("no free clusters left"),
+            FatError::FileTooLarge => f.write_str("file is too large"),
+        }
+    }
+}
+
+impl From<BlockError> for FatError {
+    fn from(value: BlockError) -> Self {
+        FatError::Device(value)
+    }
+}
@@ -0,0 +1,60 @@
+const MIB: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+impl FatType {
+    // The type is decided by the cluster count alone, as the specification requires.
+    pub fn for_clusters(clusters: u32) -> Self {
+        if clusters < 4085 {
+            FatType::Fat12
+        } else if clusters < 65525 {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        }
+    }
+
+    // What `FileSystem::format` is usually asked for: FAT12 on floppies, FAT32 on large disks.
+    pub fn for_size(bytes: usize) -> Self {
+        if bytes < 8 * MIB {
+            FatType::Fat12
+        } else if bytes < 512 * MIB {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        }
+    }
+
+    pub fn bits(&self) -> usize {
+        match self {
+            FatType::Fat12 => 12,
+            FatType::Fat16 => 16,
+            FatType::Fat32 => 32,
+        }
+    }
+
+    pub(crate) fn clusters(&self) -> (u32, u32) {
+        match self {
+            FatType::Fat12 => (1, 4084),
+            FatType::Fat16 => (4085, 65524),
+            FatType::Fat32 => (65525, 0x0FFF_FFF4),
+        }
+    }
+
+    pub(crate) fn end_of_chain(&self) -> u32 {
+        match self {
+            FatType::Fat12 => 0xFFF,
+            FatType::Fat16 => 0xFFFF,
+            FatType::Fat32 => 0x0FFF_FFFF,
+        }
+    }
+
+    pub(crate) fn is_end_of_chain(&self, value: u32) -> bool {
+        value >= self.end_of_chain() - 7
+    }
+}
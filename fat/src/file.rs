@@ -0,0 +1,180 @@
+use block_device::BlockDevice;
+
+use crate::{
+    dir_entry::{set_cluster, set_size, EntryLocation},
+    DirEntry, FatError, FileSystem,
+};
+
+// An open file. Its directory entry is brought up to date after every write.
+pub struct File<'a, D: BlockDevice> {
+    fs: &'a mut FileSystem<D>,
+    location: EntryLocation,
+    first_cluster: u32,
+    size: u32,
+    position: u32,
+    // The last cluster looked up and its index in the chain, so sequential access doesn't
+    // walk the chain from the start every time.
+    current: Option<(u32, u32)>,
+}
+
+impl<'a, D: BlockDevice> File<'a, D> {
+    pub(crate) fn new(fs: &'a mut FileSystem<D>, entry: &DirEntry) -> Self {
+        Self {
+            fs,
+            location: entry.location,
+            first_cluster: entry.cluster,
+            size: entry.size,
+            position: 0,
+            current: None,
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    pub fn seek(&mut self, position: u32) -> Result<(), FatError> {
+        if position > self.size {
+            return Err(FatError::InvalidSeek);
+        }
+
+        self.position = position;
+
+        Ok(())
+    }
+
+    // Returns the number of bytes read, less than asked for only at the end of the file.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, FatError> {
+        let length = buffer.len().min((self.size - self.position) as usize);
+        let cluster_size = self.fs.cluster_size();
+        let mut read = 0;
+
+        while read < length {
+            let within = self.position as usize % cluster_size;
+            let cluster = self
+                .cluster_at(self.position / cluster_size as u32, false)?
+                .ok_or(FatError::Corrupted)?;
+            let chunk = (cluster_size - within).min(length - read);
+
+            self.fs.read_bytes(
+                self.fs.cluster_offset(cluster) + within,
+                &mut buffer[read..read + chunk],
+            )?;
+            read += chunk;
+            self.position += chunk as u32;
+        }
+
+        Ok(read)
+    }
+
+    // Writes at the current position, growing the file as needed. Returns the number of
+    // bytes written, which is short when the disk fills up part way through.
+    pub fn write(&mut self, data: &[u8]) -> Result<usize, FatError> {
+        let room = (u32::MAX - self.position) as usize;
+
+        if room == 0 && !data.is_empty() {
+            return Err(FatError::FileTooLarge);
+        }
+
+        let data = &data[..data.len().min(room)];
+        let cluster_size = self.fs.cluster_size();
+        let mut written = 0;
+
+        while written < data.len() {
+            let within = self.position as usize % cluster_size;
+            let cluster = match self.cluster_at(self.position / cluster_size as u32, true) {
+                Ok(cluster) => cluster.ok_or(FatError::Corrupted)?,
+                Err(FatError::DiskFull) if written > 0 => break,
+                Err(error) => return Err(error),
+            };
+            let chunk = (cluster_size - within).min(data.len() - written);
+
+            self.fs.write_bytes(
+                self.fs.cluster_offset(cluster) + within,
+                &data[written..written + chunk],
+            )?;
+            written += chunk;
+            self.position += chunk as u32;
+        }
+
+        self.size = self.size.max(self.position);
+        self.save()?;
+
+        Ok(written)
+    }
+
+    // Cuts the file off at the current position.
+    pub fn truncate(&mut self) -> Result<(), FatError> {
+        if self.position >= self.size {
+            return Ok(());
+        }
+
+        let cluster_size = self.fs.cluster_size() as u32;
+        let last = match self.position {
+            0 => None,
+            position => Some(
+                self.cluster_at((position - 1) / cluster_size, false)?
+                    .ok_or(FatError::Corrupted)?,
+            ),
+        };
+        let first = self.first_cluster;
+
+        self.size = self.position;
+
+        if last.is_none() {
+            self.first_cluster = 0;
+            self.current = None;
+        }
+
+        // The entry is updated first, so a power cut can't leave it pointing at free clusters.
+        self.save()?;
+
+        match last {
+            Some(last) => self.fs.end_chain(last),
+            None if first != 0 => self.fs.free_chain(first),
+            None => Ok(()),
+        }
+    }
+
+    fn cluster_at(&mut self, index: u32, allocate: bool) -> Result<Option<u32>, FatError> {
+        let (mut current, mut cluster) = match self.current {
+            Some((current, cluster)) if current <= index => (current, cluster),
+            _ if self.first_cluster != 0 => (0, self.first_cluster),
+            _ if allocate => {
+                self.first_cluster = self.fs.allocate(None)?;
+
+                (0, self.first_cluster)
+            }
+            _ => return Ok(None),
+        };
+
+        while current < index {
+            cluster = match self.fs.next_cluster(cluster)? {
+                Some(next) => next,
+                None if allocate => self.fs.allocate(Some(cluster))?,
+                None => return Ok(None),
+            };
+            current += 1;
+        }
+
+        self.current = Some((current, cluster));
+
+        Ok(Some(cluster))
+    }
+
+    fn save(&mut self) -> Result<(), FatError> {
+        let mut raw = self.fs.read_entry(self.location.offset)?;
+
+        set_cluster(&mut raw, self.first_cluster);
+        set_size(&mut raw, self.size);
+        self.fs.write_entry(self.location.offset, &raw)
+    }
+}
@@ -0,0 +1,815 @@
+use block_device::BlockDevice;
+
+use crate::{
+    boot_sector::{
+        fs_info, BootSector, BACKUP_BOOT_SECTOR, BOOT_SECTOR_LENGTH, FS_INFO_SECTOR, MEDIA,
+    },
+    dir_entry::{
+        raw_entry, DirLocation, EntryLocation, RawEntry, ATTR_ARCHIVE, ATTR_DIRECTORY,
+        ATTR_LONG_NAME, ATTR_VOLUME_ID, DELETED, DIR_ENTRY_LENGTH, END_OF_DIRECTORY,
+    },
+    long_name::{self, LongName},
+    short_name::{self, ShortName},
+    Dir, DirEntry, FatError, FatType, File, MAX_NAME_LENGTH,
+};
+
+const FATS: usize = 2;
+const FIRST_CLUSTER: u32 = 2;
+const FAT12_ROOT_ENTRIES: usize = 224;
+const FAT16_ROOT_ENTRIES: usize = 512;
+const FAT32_RESERVED_SECTORS: usize = 32;
+const MAX_CLUSTER_SIZE: usize = 32 * 1024;
+const MAX_DIR_ENTRIES: usize = 65536;
+const MAX_SHORT_NAME_NUMBER: u32 = 999_999;
+const ZEROS: [u8; 512] = [0; 512];
+
+// A position in a directory, following its cluster chain as it moves along.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DirCursor {
+    dir: DirLocation,
+    index: usize,
+    cluster: u32,
+    last: u32,
+}
+
+impl DirCursor {
+    pub fn new(dir: DirLocation) -> Self {
+        let cluster = match dir {
+            DirLocation::Root => 0,
+            DirLocation::Cluster(cluster) => cluster,
+        };
+
+        Self {
+            dir,
+            index: 0,
+            cluster,
+            last: cluster,
+        }
+    }
+}
+
+// A FAT12, FAT16 or FAT32 filesystem on a block device. Paths are separated by '/' and
+// looked up without regard to case, every name gets a long name unless it is a plain 8.3 one.
+//
+// Nothing is cached, each operation goes straight to the device and leaves the filesystem
+// consistent, apart from lost clusters when the power goes in the middle of it.
+pub struct FileSystem<D: BlockDevice> {
+    device: D,
+    fat_type: FatType,
+    cluster_size: usize,
+    fat_offset: usize,
+    fat_length: usize,
+    root_offset: usize,
+    root_entries: usize,
+    root_cluster: u32,
+    data_offset: usize,
+    clusters: u32,
+    next_free: u32,
+}
+
+impl<D: BlockDevice> FileSystem<D> {
+    pub fn mount(mut device: D) -> Result<Self, FatError> {
+        let mut bytes = [0; BOOT_SECTOR_LENGTH];
+
+        device.read_at(0, &mut bytes)?;
+
+        let boot = BootSector::from_bytes(&bytes).ok_or(FatError::NotFat)?;
+
+        Self::new(device, boot)
+    }
+
+    // Lays out an empty filesystem over the whole device, using the smallest clusters the
+    // type allows for its size.
+    pub fn format(device: D, fat_type: FatType) -> Result<Self, FatError> {
+        let sector = device.sector_size();
+
+        if !sector.is_power_of_two() || !(512..=4096).contains(&sector) {
+            return Err(FatError::UnsupportedSize);
+        }
+
+        let total_sectors =
+            u32::try_from(device.size() / sector).map_err(|_| FatError::UnsupportedSize)?;
+        let (reserved_sectors, root_entries) = match fat_type {
+            FatType::Fat12 => (1, FAT12_ROOT_ENTRIES),
+            FatType::Fat16 => (1, FAT16_ROOT_ENTRIES),
+            FatType::Fat32 => (FAT32_RESERVED_SECTORS, 0),
+        };
+        // The root directory takes whole sectors, whatever is left over is usable too.
+        let root_sectors = (root_entries * DIR_ENTRY_LENGTH).div_ceil(sector);
+        let root_entries = root_sectors * sector / DIR_ENTRY_LENGTH;
+        let (min_clusters, max_clusters) = fat_type.clusters();
+        let mut sectors_per_cluster = 1;
+
+        let fat_sectors = loop {
+            if sectors_per_cluster * sector > MAX_CLUSTER_SIZE {
+                return Err(FatError::UnsupportedSize);
+            }
+
+            let (fat_sectors, clusters) = fat_layout(
+                fat_type,
+                total_sectors as usize,
+                reserved_sectors + root_sectors,
+                sectors_per_cluster,
+                sector,
+            )
+            .ok_or(FatError::UnsupportedSize)?;
+
+            // Larger clusters only make for fewer of them, so there is no point going on.
+            if clusters <= max_clusters as usize {
+                if clusters < min_clusters as usize {
+                    return Err(FatError::UnsupportedSize);
+                }
+
+                break fat_sectors;
+            }
+
+            sectors_per_cluster *= 2;
+        };
+
+        let boot = BootSector {
+            bytes_per_sector: sector as u16,
+            sectors_per_cluster: sectors_per_cluster as u8,
+            reserved_sectors: reserved_sectors as u16,
+            fats: FATS as u8,
+            root_entries: root_entries as u16,
+            total_sectors,
+            fat_sectors: fat_sectors as u32,
+            root_cluster: if fat_type == FatType::Fat32 {
+                FIRST_CLUSTER
+            } else {
+                0
+            },
+        };
+        let mut fs = Self::new(device, boot)?;
+        let end_of_chain = fat_type.end_of_chain();
+
+        fs.fill(0, fs.data_offset)?;
+        fs.write_bytes(0, &boot.to_bytes(fat_type))?;
+
+        if fat_type == FatType::Fat32 {
+            let backup = BACKUP_BOOT_SECTOR as usize * sector;
+
+            fs.write_bytes(FS_INFO_SECTOR as usize * sector, &fs_info())?;
+            fs.write_bytes(backup, &boot.to_bytes(fat_type))?;
+            fs.write_bytes(backup + sector, &fs_info())?;
+        }
+
+        fs.write_fat(0, end_of_chain & (0xFFFF_FF00 | MEDIA as u32))?;
+        fs.write_fat(1, end_of_chain)?;
+
+        if fat_type == FatType::Fat32 {
+            fs.write_fat(FIRST_CLUSTER, end_of_chain)?;
+            fs.fill(fs.cluster_offset(FIRST_CLUSTER), fs.cluster_size)?;
+        }
+
+        fs.device.flush()?;
+
+        Ok(fs)
+    }
+
+    fn new(device: D, boot: BootSector) -> Result<Self, FatError> {
+        let sector = boot.bytes_per_sector as usize;
+        let fat_offset = boot.reserved_sectors as usize * sector;
+        let fat_length = boot.fat_sectors as usize * sector;
+        let root_offset = fat_offset + boot.fats as usize * fat_length;
+        let root_entries = boot.root_entries as usize;
+        let data_offset = root_offset + (root_entries * DIR_ENTRY_LENGTH).div_ceil(sector) * sector;
+        let size = boot.total_sectors as usize * sector;
+        let cluster_size = boot.sectors_per_cluster as usize * sector;
+
+        if data_offset >= size || size > device.size() {
+            return Err(FatError::NotFat);
+        }
+
+        let clusters = ((size - data_offset) / cluster_size) as u32;
+        let fat_type = FatType::for_clusters(clusters);
+        let fs = Self {
+            device,
+            fat_type,
+            cluster_size,
+            fat_offset,
+            fat_length,
+            root_offset,
+            root_entries,
+            root_cluster: boot.root_cluster,
+            data_offset,
+            clusters,
+            next_free: FIRST_CLUSTER,
+        };
+
+        // FAT32 keeps its root directory in clusters, the others in a fixed region.
+        let root_valid = match fat_type {
+            FatType::Fat32 => root_entries == 0 && fs.is_cluster(boot.root_cluster),
+            _ => root_entries != 0,
+        };
+
+        if !root_valid || fat_length * 8 / fat_type.bits() < clusters as usize + 2 {
+            return Err(FatError::NotFat);
+        }
+
+        Ok(fs)
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    pub fn fat_type(&self) -> FatType {
+        self.fat_type
+    }
+
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size
+    }
+
+    pub fn cluster_count(&self) -> u32 {
+        self.clusters
+    }
+
+    pub fn free_clusters(&mut self) -> Result<u32, FatError> {
+        let mut free = 0;
+
+        for cluster in FIRST_CLUSTER..self.clusters + FIRST_CLUSTER {
+            if self.read_fat(cluster)? == 0 {
+                free += 1;
+            }
+        }
+
+        Ok(free)
+    }
+
+    pub fn flush(&mut self) -> Result<(), FatError> {
+        Ok(self.device.flush()?)
+    }
+
+    pub fn read_dir(&mut self, path: &str) -> Result<Dir<'_, D>, FatError> {
+        let dir = self.resolve_dir(path)?;
+
+        Ok(Dir::new(self, DirCursor::new(dir)))
+    }
+
+    pub fn metadata(&mut self, path: &str) -> Result<DirEntry, FatError> {
+        let (dir, name) = self.parent(path)?;
+
+        self.find(dir, name)?.ok_or(FatError::NotFound)
+    }
+
+    pub fn open(&mut self, path: &str) -> Result<File<'_, D>, FatError> {
+        let entry = self.metadata(path)?;
+
+        if entry.is_dir() {
+            return Err(FatError::IsADirectory);
+        }
+
+        Ok(File::new(self, &entry))
+    }
+
+    pub fn create_file(&mut self, path: &str) -> Result<File<'_, D>, FatError> {
+        let (dir, name) = self.parent(path)?;
+        let entry = self.create_entry(dir, name, ATTR_ARCHIVE, 0)?;
+
+        Ok(File::new(self, &entry))
+    }
+
+    pub fn create_dir(&mut self, path: &str) -> Result<(), FatError> {
+        let (dir, name) = self.parent(path)?;
+        let cluster = self.allocate(None)?;
+        // ".." points at cluster 0 when the parent is the root, even on FAT32.
+        let parent = match dir {
+            DirLocation::Cluster(parent) if parent != self.root_cluster => parent,
+            _ => 0,
+        };
+        let offset = self.cluster_offset(cluster);
+
+        // The new directory is complete before its entry makes it visible.
+        let result = self.fill(offset, self.cluster_size).and_then(|()| {
+            self.write_entry(
+                offset,
+                &raw_entry(b".          ", 0, ATTR_DIRECTORY, cluster),
+            )?;
+            self.write_entry(
+                offset + DIR_ENTRY_LENGTH,
+                &raw_entry(b"..         ", 0, ATTR_DIRECTORY, parent),
+            )?;
+            self.create_entry(dir, name, ATTR_DIRECTORY, cluster)
+        });
+
+        if let Err(error) = result {
+            self.free_chain(cluster)?;
+
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    // Removes a file or an empty directory.
+    pub fn remove(&mut self, path: &str) -> Result<(), FatError> {
+        let entry = self.metadata(path)?;
+
+        if entry.is_dir() {
+            let mut cursor = DirCursor::new(self.dir_of(&entry));
+
+            if self.next_entry(&mut cursor)?.is_some() {
+                return Err(FatError::DirectoryNotEmpty);
+            }
+        }
+
+        // The entries go first, a power cut then leaves lost clusters behind rather than a
+        // file pointing into free space.
+        self.delete_entries(&entry.location)?;
+
+        if entry.cluster != 0 {
+            self.free_chain(entry.cluster)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read_bytes(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), FatError> {
+        Ok(self.device.read_at(offset, buffer)?)
+    }
+
+    pub(crate) fn write_bytes(&mut self, offset: usize, data: &[u8]) -> Result<(), FatError> {
+        Ok(self.device.write_at(offset, data)?)
+    }
+
+    pub(crate) fn read_entry(&mut self, offset: usize) -> Result<RawEntry, FatError> {
+        let mut raw = [0; DIR_ENTRY_LENGTH];
+
+        self.read_bytes(offset, &mut raw)?;
+
+        Ok(raw)
+    }
+
+    pub(crate) fn write_entry(&mut self, offset: usize, raw: &RawEntry) -> Result<(), FatError> {
+        self.write_bytes(offset, raw)
+    }
+
+    pub(crate) fn cluster_offset(&self, cluster: u32) -> usize {
+        self.data_offset + (cluster - FIRST_CLUSTER) as usize * self.cluster_size
+    }
+
+    pub(crate) fn next_cluster(&mut self, cluster: u32) -> Result<Option<u32>, FatError> {
+        let value = self.read_fat(cluster)?;
+
+        if self.fat_type.is_end_of_chain(value) {
+            return Ok(None);
+        }
+
+        if !self.is_cluster(value) {
+            return Err(FatError::Corrupted);
+        }
+
+        Ok(Some(value))
+    }
+
+    // Takes a free cluster and appends it to the chain ending at `previous`.
+    pub(crate) fn allocate(&mut self, previous: Option<u32>) -> Result<u32, FatError> {
+        for i in 0..self.clusters {
+            let cluster = FIRST_CLUSTER + (self.next_free - FIRST_CLUSTER + i) % self.clusters;
+
+            if self.read_fat(cluster)? != 0 {
+                continue;
+            }
+
+            self.write_fat(cluster, self.fat_type.end_of_chain())?;
+
+            if let Some(previous) = previous {
+                self.write_fat(previous, cluster)?;
+            }
+
+            self.next_free = cluster;
+
+            return Ok(cluster);
+        }
+
+        Err(FatError::DiskFull)
+    }
+
+    pub(crate) fn free_chain(&mut self, first: u32) -> Result<(), FatError> {
+        let mut cluster = first;
+
+        if !self.is_cluster(first) {
+            return Err(FatError::Corrupted);
+        }
+
+        // A chain can't be longer than the disk, anything longer loops.
+        for _ in 0..self.clusters {
+            let next = self.next_cluster(cluster)?;
+
+            self.write_fat(cluster, 0)?;
+            self.next_free = self.next_free.min(cluster);
+
+            match next {
+                Some(next) => cluster = next,
+                None => return Ok(()),
+            }
+        }
+
+        Err(FatError::Corrupted)
+    }
+
+    // Makes `cluster` the last one of its chain and frees the rest.
+    pub(crate) fn end_chain(&mut self, cluster: u32) -> Result<(), FatError> {
+        let next = self.next_cluster(cluster)?;
+
+        self.write_fat(cluster, self.fat_type.end_of_chain())?;
+
+        match next {
+            Some(next) => self.free_chain(next),
+            None => Ok(()),
+        }
+    }
+
+    // The next entry of the directory with its long name, skipping deleted entries, volume
+    // labels and the "." and ".." entries.
+    pub(crate) fn next_entry(
+        &mut self,
+        cursor: &mut DirCursor,
+    ) -> Result<Option<DirEntry>, FatError> {
+        let mut long_name = LongName::new();
+
+        while let Some(offset) = self.entry_offset(cursor) {
+            let raw = self.read_entry(offset)?;
+            let location = EntryLocation {
+                dir: cursor.dir,
+                index: cursor.index,
+                offset,
+                long_entries: 0,
+            };
+
+            self.advance(cursor)?;
+
+            match raw[0] {
+                END_OF_DIRECTORY => return Ok(None),
+                DELETED => long_name.reset(),
+                _ if raw[11] & 0x3F == ATTR_LONG_NAME => long_name.push(&raw),
+                _ if raw[11] & ATTR_VOLUME_ID != 0 => long_name.reset(),
+                _ => {
+                    let entry = DirEntry::from_raw(&raw, &mut long_name, location);
+
+                    if !entry.is_dot() {
+                        return Ok(Some(entry));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn is_cluster(&self, cluster: u32) -> bool {
+        (FIRST_CLUSTER..self.clusters + FIRST_CLUSTER).contains(&cluster)
+    }
+
+    fn read_fat(&mut self, cluster: u32) -> Result<u32, FatError> {
+        let cluster = cluster as usize;
+
+        Ok(match self.fat_type {
+            FatType::Fat12 => {
+                let mut bytes = [0; 2];
+
+                self.read_bytes(self.fat_offset + cluster + cluster / 2, &mut bytes)?;
+
+                let value = u16::from_le_bytes(bytes);
+
+                if cluster % 2 == 1 {
+                    value as u32 >> 4
+                } else {
+                    value as u32 & 0xFFF
+                }
+            }
+            FatType::Fat16 => {
+                let mut bytes = [0; 2];
+
+                self.read_bytes(self.fat_offset + cluster * 2, &mut bytes)?;
+                u16::from_le_bytes(bytes) as u32
+            }
+            FatType::Fat32 => {
+                let mut bytes = [0; 4];
+
+                self.read_bytes(self.fat_offset + cluster * 4, &mut bytes)?;
+                u32::from_le_bytes(bytes) & 0x0FFF_FFFF
+            }
+        })
+    }
+
+    // Updates every copy of the FAT.
+    fn write_fat(&mut self, cluster: u32, value: u32) -> Result<(), FatError> {
+        let cluster = cluster as usize;
+
+        for copy in 0..FATS {
+            let fat = self.fat_offset + copy * self.fat_length;
+
+            match self.fat_type {
+                FatType::Fat12 => {
+                    let offset = fat + cluster + cluster / 2;
+                    let mut bytes = [0; 2];
+
+                    self.read_bytes(offset, &mut bytes)?;
+
+                    let old = u16::from_le_bytes(bytes);
+                    let new = if cluster % 2 == 1 {
+                        (old & 0x000F) | (value as u16) << 4
+                    } else {
+                        (old & 0xF000) | (value as u16 & 0x0FFF)
+                    };
+
+                    self.write_bytes(offset, &new.to_le_bytes())?;
+                }
+                FatType::Fat16 => {
+                    self.write_bytes(fat + cluster * 2, &(value as u16).to_le_bytes())?;
+                }
+                FatType::Fat32 => {
+                    let offset = fat + cluster * 4;
+                    let mut bytes = [0; 4];
+
+                    // The top four bits are reserved and have to be kept.
+                    self.read_bytes(offset, &mut bytes)?;
+
+                    let new = (u32::from_le_bytes(bytes) & 0xF000_0000) | (value & 0x0FFF_FFFF);
+
+                    self.write_bytes(offset, &new.to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill(&mut self, offset: usize, length: usize) -> Result<(), FatError> {
+        let mut done = 0;
+
+        while done < length {
+            let chunk = ZEROS.len().min(length - done);
+
+            self.write_bytes(offset + done, &ZEROS[..chunk])?;
+            done += chunk;
+        }
+
+        Ok(())
+    }
+
+    fn root_dir(&self) -> DirLocation {
+        match self.fat_type {
+            FatType::Fat32 => DirLocation::Cluster(self.root_cluster),
+            _ => DirLocation::Root,
+        }
+    }
+
+    fn dir_of(&self, entry: &DirEntry) -> DirLocation {
+        match entry.cluster {
+            0 => self.root_dir(),
+            cluster => DirLocation::Cluster(cluster),
+        }
+    }
+
+    fn entry_offset(&self, cursor: &DirCursor) -> Option<usize> {
+        match cursor.dir {
+            DirLocation::Root => (cursor.index < self.root_entries)
+                .then(|| self.root_offset + cursor.index * DIR_ENTRY_LENGTH),
+            DirLocation::Cluster(_) => {
+                let index = cursor.index % (self.cluster_size / DIR_ENTRY_LENGTH);
+
+                (cursor.cluster != 0 && cursor.index < MAX_DIR_ENTRIES)
+                    .then(|| self.cluster_offset(cursor.cluster) + index * DIR_ENTRY_LENGTH)
+            }
+        }
+    }
+
+    fn advance(&mut self, cursor: &mut DirCursor) -> Result<(), FatError> {
+        cursor.index += 1;
+
+        if let DirLocation::Cluster(_) = cursor.dir {
+            let at_boundary = cursor
+                .index
+                .is_multiple_of(self.cluster_size / DIR_ENTRY_LENGTH);
+
+            if cursor.cluster != 0 && at_boundary {
+                cursor.last = cursor.cluster;
+                cursor.cluster = self.next_cluster(cursor.cluster)?.unwrap_or(0);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_dir(&mut self, path: &str) -> Result<DirLocation, FatError> {
+        let mut dir = self.root_dir();
+
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let entry = self.find(dir, name)?.ok_or(FatError::NotFound)?;
+
+            if !entry.is_dir() {
+                return Err(FatError::NotADirectory);
+            }
+
+            dir = self.dir_of(&entry);
+        }
+
+        Ok(dir)
+    }
+
+    // The directory a path points into and the last name of it.
+    fn parent<'p>(&mut self, path: &'p str) -> Result<(DirLocation, &'p str), FatError> {
+        let path = path.trim_end_matches('/');
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+
+        if name.is_empty() {
+            return Err(FatError::InvalidName);
+        }
+
+        Ok((self.resolve_dir(dir)?, name))
+    }
+
+    fn find(&mut self, dir: DirLocation, name: &str) -> Result<Option<DirEntry>, FatError> {
+        let mut cursor = DirCursor::new(dir);
+
+        while let Some(entry) = self.next_entry(&mut cursor)? {
+            if entry.matches(name) {
+                return Ok(Some(entry));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn create_entry(
+        &mut self,
+        dir: DirLocation,
+        name: &str,
+        attributes: u8,
+        cluster: u32,
+    ) -> Result<DirEntry, FatError> {
+        validate_name(name)?;
+
+        if self.find(dir, name)?.is_some() {
+            return Err(FatError::AlreadyExists);
+        }
+
+        let (short, flags, long_entries) = match short_name::exact(name) {
+            Some((short, flags)) => (short, flags, 0),
+            None => (
+                self.unique_short_name(dir, name)?,
+                0,
+                long_name::entry_count(name),
+            ),
+        };
+        let start = self.find_free(dir, long_entries + 1)?;
+        let checksum = short_name::checksum(&short);
+        let mut cursor = start;
+
+        // Long name entries come in reverse order, the last part of the name first.
+        for order in (1..=long_entries).rev() {
+            let offset = self.entry_offset(&cursor).ok_or(FatError::Corrupted)?;
+
+            self.write_entry(
+                offset,
+                &long_name::entry(name, order, long_entries, checksum),
+            )?;
+            self.advance(&mut cursor)?;
+        }
+
+        let offset = self.entry_offset(&cursor).ok_or(FatError::Corrupted)?;
+
+        self.write_entry(offset, &raw_entry(&short, flags, attributes, cluster))?;
+        self.next_entry(&mut { start })?.ok_or(FatError::Corrupted)
+    }
+
+    fn unique_short_name(&mut self, dir: DirLocation, name: &str) -> Result<ShortName, FatError> {
+        let basis = short_name::basis(name);
+
+        for number in 1..=MAX_SHORT_NAME_NUMBER {
+            let short = short_name::numbered(&basis, number);
+
+            if !self.short_name_taken(dir, &short)? {
+                return Ok(short);
+            }
+        }
+
+        Err(FatError::DirectoryFull)
+    }
+
+    fn short_name_taken(&mut self, dir: DirLocation, short: &ShortName) -> Result<bool, FatError> {
+        let mut cursor = DirCursor::new(dir);
+
+        while let Some(offset) = self.entry_offset(&cursor) {
+            let raw = self.read_entry(offset)?;
+
+            match raw[0] {
+                END_OF_DIRECTORY => break,
+                DELETED => {}
+                _ if raw[11] & 0x3F != ATTR_LONG_NAME && raw[..short.len()] == short[..] => {
+                    return Ok(true);
+                }
+                _ => {}
+            }
+
+            self.advance(&mut cursor)?;
+        }
+
+        Ok(false)
+    }
+
+    // Finds `count` free entries in a row, growing the directory when it runs out of them.
+    fn find_free(&mut self, dir: DirLocation, count: usize) -> Result<DirCursor, FatError> {
+        let mut cursor = DirCursor::new(dir);
+        let mut start = cursor;
+        let mut run = 0;
+
+        loop {
+            let Some(offset) = self.entry_offset(&cursor) else {
+                if dir == DirLocation::Root || cursor.index >= MAX_DIR_ENTRIES {
+                    return Err(FatError::DirectoryFull);
+                }
+
+                let cluster = self.allocate(Some(cursor.last))?;
+
+                self.fill(self.cluster_offset(cluster), self.cluster_size)?;
+                cursor.cluster = cluster;
+
+                continue;
+            };
+
+            let mut first = [0];
+
+            self.read_bytes(offset, &mut first)?;
+
+            if first[0] == END_OF_DIRECTORY || first[0] == DELETED {
+                if run == 0 {
+                    start = cursor;
+                }
+
+                run += 1;
+
+                if run == count {
+                    return Ok(start);
+                }
+            } else {
+                run = 0;
+            }
+
+            self.advance(&mut cursor)?;
+        }
+    }
+
+    fn delete_entries(&mut self, location: &EntryLocation) -> Result<(), FatError> {
+        let mut cursor = DirCursor::new(location.dir);
+
+        while cursor.index < location.index - location.long_entries {
+            self.advance(&mut cursor)?;
+        }
+
+        while cursor.index <= location.index {
+            let offset = self.entry_offset(&cursor).ok_or(FatError::Corrupted)?;
+
+            self.write_bytes(offset, &[DELETED])?;
+            self.advance(&mut cursor)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Sizes the FAT for a layout, returns its length in sectors and the cluster count it covers.
+fn fat_layout(
+    fat_type: FatType,
+    total_sectors: usize,
+    fixed_sectors: usize,
+    sectors_per_cluster: usize,
+    sector: usize,
+) -> Option<(usize, usize)> {
+    let mut fat_sectors = 1;
+
+    loop {
+        let data_sectors = total_sectors.checked_sub(fixed_sectors + FATS * fat_sectors)?;
+        let clusters = data_sectors / sectors_per_cluster;
+        let needed = ((clusters + 2) * fat_type.bits())
+            .div_ceil(8)
+            .div_ceil(sector);
+
+        if needed <= fat_sectors {
+            return Some((fat_sectors, clusters));
+        }
+
+        fat_sectors = needed;
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), FatError> {
+    let invalid = name.is_empty()
+        || name.ends_with(['.', ' '])
+        || name.encode_utf16().count() > MAX_NAME_LENGTH
+        || name.chars().any(|c| c < ' ' || "\"*/:<>?\\|".contains(c));
+
+    if invalid {
+        Err(FatError::InvalidName)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+#![no_std]
+
+mod boot_sector;
+mod dir;
+mod dir_entry;
+mod fat_error;
+mod fat_type;
+mod file;
+mod file_system;
+mod long_name;
+mod short_name;
+
+pub use block_device;
+pub use dir::Dir;
+pub use dir_entry::DirEntry;
+pub use fat_error::FatError;
+pub use fat_type::FatType;
+pub use file::File;
+pub use file_system::FileSystem;
+
+// In UTF-16 code units, as stored in the long name entries.
+pub const MAX_NAME_LENGTH: usize = 255;
@@ -0,0 +1,120 @@
+use core::iter;
+
+use crate::{dir_entry::ATTR_LONG_NAME, MAX_NAME_LENGTH};
+
+pub(crate) const CHARS_PER_ENTRY: usize = 13;
+// A UTF-16 code unit takes at most three bytes in UTF-8, a surrogate pair four for two units.
+pub(crate) const NAME_BUFFER_LENGTH: usize = MAX_NAME_LENGTH * 3;
+
+const MAX_ENTRIES: usize = MAX_NAME_LENGTH.div_ceil(CHARS_PER_ENTRY);
+const LAST_ENTRY: u8 = 0x40;
+const ORDER_MASK: u8 = 0x1F;
+const CHAR_OFFSETS: [usize; CHARS_PER_ENTRY] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+// Collects a long name from its entries. They are stored in reverse order before the short
+// entry they belong to, each carries the checksum of that short name so orphans left behind
+// by other systems are recognized.
+pub(crate) struct LongName {
+    units: [u16; MAX_ENTRIES * CHARS_PER_ENTRY],
+    entries: u8,
+    expected: u8,
+    checksum: u8,
+}
+
+impl LongName {
+    pub fn new() -> Self {
+        Self {
+            units: [0; MAX_ENTRIES * CHARS_PER_ENTRY],
+            entries: 0,
+            expected: 0,
+            checksum: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.entries = 0;
+        self.expected = 0;
+    }
+
+    pub fn push(&mut self, raw: &[u8; 32]) {
+        let order = raw[0] & ORDER_MASK;
+
+        if raw[0] & LAST_ENTRY != 0 {
+            if order == 0 || order as usize > MAX_ENTRIES {
+                self.reset();
+                return;
+            }
+
+            self.entries = order;
+            self.checksum = raw[13];
+        } else if self.expected == 0 || order != self.expected || raw[13] != self.checksum {
+            self.reset();
+            return;
+        }
+
+        let start = (order as usize - 1) * CHARS_PER_ENTRY;
+
+        for (i, offset) in CHAR_OFFSETS.into_iter().enumerate() {
+            self.units[start + i] = u16::from_le_bytes([raw[offset], raw[offset + 1]]);
+        }
+
+        self.expected = order - 1;
+    }
+
+    // Decodes the collected name into `name` if it belongs to the short name with this
+    // checksum. Returns the length of the name and how many entries it took.
+    pub fn take(
+        &mut self,
+        checksum: u8,
+        name: &mut [u8; NAME_BUFFER_LENGTH],
+    ) -> Option<(usize, usize)> {
+        let entries = self.entries as usize;
+        let complete = entries != 0 && self.expected == 0 && checksum == self.checksum;
+
+        self.reset();
+
+        if !complete {
+            return None;
+        }
+
+        let units = &self.units[..entries * CHARS_PER_ENTRY];
+        let units = &units[..units
+            .iter()
+            .position(|&unit| unit == 0)
+            .unwrap_or(units.len())];
+        let mut length = 0;
+
+        for c in char::decode_utf16(units.iter().take(MAX_NAME_LENGTH).copied()) {
+            length += c
+                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                .encode_utf8(&mut name[length..])
+                .len();
+        }
+
+        (length != 0).then_some((length, entries))
+    }
+}
+
+pub(crate) fn entry_count(name: &str) -> usize {
+    name.encode_utf16().count().div_ceil(CHARS_PER_ENTRY)
+}
+
+// The long name entry with the given order, counting from 1 at the start of the name.
+pub(crate) fn entry(name: &str, order: usize, entries: usize, checksum: u8) -> [u8; 32] {
+    let mut raw = [0; 32];
+    let mut units = name
+        .encode_utf16()
+        .skip((order - 1) * CHARS_PER_ENTRY)
+        .chain(iter::once(0))
+        .chain(iter::repeat(0xFFFF));
+
+    raw[0] = order as u8 | if order == entries { LAST_ENTRY } else { 0 };
+    raw[11] = ATTR_LONG_NAME;
+    raw[13] = checksum;
+
+    for offset in CHAR_OFFSETS {
+        raw[offset..offset + 2].copy_from_slice(&units.next().unwrap_or(0xFFFF).to_le_bytes());
+    }
+
+    raw
+}
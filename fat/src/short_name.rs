@@ -0,0 +1,148 @@
+pub(crate) const SHORT_NAME_LENGTH: usize = 11;
+// Set in the reserved byte of an entry by Windows NT for short names in lower case.
+pub(crate) const LOWERCASE_BASE: u8 = 0x08;
+pub(crate) const LOWERCASE_EXTENSION: u8 = 0x10;
+
+pub(crate) type ShortName = [u8; SHORT_NAME_LENGTH];
+
+fn is_short_char(byte: u8) -> bool {
+    byte.is_ascii_uppercase() || byte.is_ascii_digit() || b"!#$%&'()-@^_`{}~".contains(&byte)
+}
+
+// A name that is a plain 8.3 name with each part in a single case is stored as a short name
+// with case flags and needs no long name entries.
+pub(crate) fn exact(name: &str) -> Option<(ShortName, u8)> {
+    let (base, extension) = name.split_once('.').unwrap_or((name, ""));
+
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 || extension.contains('.') {
+        return None;
+    }
+
+    let mut short = [b' '; SHORT_NAME_LENGTH];
+    let mut flags = 0;
+
+    for (part, start, flag) in [
+        (base, 0, LOWERCASE_BASE),
+        (extension, 8, LOWERCASE_EXTENSION),
+    ] {
+        let has_upper = part.bytes().any(|byte| byte.is_ascii_uppercase());
+        let has_lower = part.bytes().any(|byte| byte.is_ascii_lowercase());
+
+        match (has_upper, has_lower) {
+            (true, true) => return None,
+            (false, true) => flags |= flag,
+            _ => {}
+        }
+
+        for (i, byte) in part.bytes().enumerate() {
+            let byte = byte.to_ascii_uppercase();
+
+            if !is_short_char(byte) {
+                return None;
+            }
+
+            short[start + i] = byte;
+        }
+    }
+
+    Some((short, flags))
+}
+
+// The short name a long name starts from before a "~N" tail makes it unique.
+pub(crate) fn basis(name: &str) -> ShortName {
+    let name = name.trim_start_matches('.');
+    let (base, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    let mut short = [b' '; SHORT_NAME_LENGTH];
+
+    fill(&mut short[..8], base);
+    fill(&mut short[8..], extension);
+
+    if short[0] == b' ' {
+        short[0] = b'_';
+    }
+
+    short
+}
+
+fn fill(target: &mut [u8], part: &str) {
+    let chars = part.chars().filter(|&c| c != ' ' && c != '.');
+
+    for (slot, c) in target.iter_mut().zip(chars) {
+        let byte = if c.is_ascii() {
+            c.to_ascii_uppercase() as u8
+        } else {
+            b'_'
+        };
+
+        *slot = if is_short_char(byte) { byte } else { b'_' };
+    }
+}
+
+pub(crate) fn numbered(basis: &ShortName, number: u32) -> ShortName {
+    let mut digits = [0; 10];
+    let mut count = 0;
+    let mut rest = number;
+
+    loop {
+        digits[count] = b'0' + (rest % 10) as u8;
+        count += 1;
+        rest /= 10;
+
+        if rest == 0 {
+            break;
+        }
+    }
+
+    let length = basis[..8]
+        .iter()
+        .position(|&byte| byte == b' ')
+        .unwrap_or(8)
+        .min(7 - count);
+    let mut short = *basis;
+
+    short[length] = b'~';
+
+    for i in 0..count {
+        short[length + 1 + i] = digits[count - 1 - i];
+    }
+
+    short[length + 1 + count..8].fill(b' ');
+
+    short
+}
+
+pub(crate) fn checksum(short: &ShortName) -> u8 {
+    short
+        .iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+}
+
+// Writes the name as "BASE.EXT", returns its length. Bytes from other code pages show up as
+// '_', there is no way to tell which code page wrote them.
+pub(crate) fn display(short: &ShortName, flags: u8, out: &mut [u8; 12]) -> usize {
+    let mut length = 0;
+    let mut push = |byte: u8, lower: bool| {
+        out[length] = match byte {
+            byte if !byte.is_ascii_graphic() && byte != b' ' => b'_',
+            byte if lower => byte.to_ascii_lowercase(),
+            byte => byte,
+        };
+        length += 1;
+    };
+    let base = short[..8].trim_ascii_end();
+    let extension = short[8..].trim_ascii_end();
+
+    for &byte in base {
+        push(byte, flags & LOWERCASE_BASE != 0);
+    }
+
+    if !extension.is_empty() {
+        push(b'.', false);
+
+        for &byte in extension {
+            push(byte, flags & LOWERCASE_EXTENSION != 0);
+        }
+    }
+
+    length
+}
@@ -0,0 +1,390 @@
+use fat::{
+    block_device::{BlockDevice, MemoryDisk},
+    FatError, FatType, FileSystem,
+};
+
+const FLOPPY: usize = 1474560;
+const MIB: usize = 1024 * 1024;
+
+fn contents(seed: u32, length: usize) -> Vec<u8> {
+    (0..length)
+        .map(|i| (i as u32).wrapping_mul(seed * 2 + 1).rotate_right(3) as u8)
+        .collect()
+}
+
+fn read_all(fs: &mut FileSystem<MemoryDisk>, path: &str) -> Vec<u8> {
+    let mut file = fs.open(path).unwrap();
+    let mut data = vec![0; file.len() as usize];
+
+    assert_eq!(file.read(&mut data).unwrap(), data.len());
+
+    data
+}
+
+fn write_file(fs: &mut FileSystem<MemoryDisk>, path: &str, data: &[u8]) {
+    let mut file = fs.create_file(path).unwrap();
+
+    // Odd pieces, so writes straddle cluster boundaries.
+    for chunk in data.chunks(1531) {
+        assert_eq!(file.write(chunk).unwrap(), chunk.len());
+    }
+}
+
+fn names(fs: &mut FileSystem<MemoryDisk>, path: &str) -> Vec<String> {
+    let mut names: Vec<_> = fs
+        .read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().name().to_string())
+        .collect();
+
+    names.sort();
+
+    names
+}
+
+#[test]
+fn formats_and_mounts_every_type() {
+    for (fat_type, size) in [
+        (FatType::Fat12, FLOPPY),
+        (FatType::Fat16, 16 * MIB),
+        (FatType::Fat32, 40 * MIB),
+    ] {
+        let mut image = vec![0xAA; size];
+        let mut fs = FileSystem::format(MemoryDisk::new(&mut image), fat_type).unwrap();
+        let free = fs.free_clusters().unwrap();
+        let data = contents(1, 20000);
+
+        assert_eq!(fs.fat_type(), fat_type);
+        assert_eq!(FatType::for_clusters(fs.cluster_count()), fat_type);
+        assert_eq!(fs.read_dir("/").unwrap().count(), 0);
+
+        write_file(&mut fs, "/station.log", &data);
+
+        let mut fs = FileSystem::mount(fs.into_inner()).unwrap();
+
+        assert_eq!(fs.fat_type(), fat_type);
+        assert_eq!(names(&mut fs, "/"), ["station.log"]);
+        assert_eq!(read_all(&mut fs, "/station.log"), data);
+
+        fs.remove("/station.log").unwrap();
+        assert_eq!(fs.free_clusters().unwrap(), free);
+    }
+}
+
+#[test]
+fn rejects_devices_without_fat() {
+    let mut image = vec![0; FLOPPY];
+
+    assert_eq!(
+        FileSystem::mount(MemoryDisk::new(&mut image)).err(),
+        Some(FatError::NotFat)
+    );
+    assert_eq!(
+        FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat32).err(),
+        Some(FatError::UnsupportedSize)
+    );
+}
+
+#[test]
+fn keeps_long_names() {
+    let mut image = vec![0; FLOPPY];
+    let mut fs = FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat12).unwrap();
+    let long = "A rather long name that takes several entries of its own.dat";
+
+    for name in [
+        "README.TXT",
+        "notes.md",
+        "Station Log.txt",
+        "журнал.bin",
+        "Station Log.txt.bak",
+        long,
+    ] {
+        fs.create_file(&format!("/{name}")).unwrap();
+    }
+
+    let mut fs = FileSystem::mount(fs.into_inner()).unwrap();
+
+    assert_eq!(
+        names(&mut fs, "/"),
+        [
+            long,
+            "README.TXT",
+            "Station Log.txt",
+            "Station Log.txt.bak",
+            "notes.md",
+            "журнал.bin",
+        ]
+    );
+
+    // Short names are generated for the long ones and both find the file.
+    assert_eq!(
+        fs.metadata("/station log.txt").unwrap().short_name(),
+        "STATIO~1.TXT"
+    );
+    assert_eq!(
+        fs.metadata("/STATIO~1.BAK").unwrap().name(),
+        "Station Log.txt.bak"
+    );
+    assert_eq!(fs.metadata("/notes.md").unwrap().short_name(), "notes.md");
+    assert_eq!(
+        fs.create_file("/Notes.MD").err(),
+        Some(FatError::AlreadyExists)
+    );
+}
+
+#[test]
+fn rejects_invalid_names() {
+    let mut image = vec![0; FLOPPY];
+    let mut fs = FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat12).unwrap();
+    let too_long = "x".repeat(256);
+
+    for name in ["/", "/a:b", "/what?", "/trailing.", "/tab\there", &too_long] {
+        assert_eq!(
+            fs.create_file(name).err(),
+            Some(FatError::InvalidName),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn nested_directories() {
+    let mut image = vec![0; 16 * MIB];
+    let mut fs = FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat16).unwrap();
+    let free = fs.free_clusters().unwrap();
+
+    fs.create_dir("/data").unwrap();
+    fs.create_dir("/data/Sensor readings").unwrap();
+    write_file(
+        &mut fs,
+        "/data/Sensor readings/day 1.csv",
+        &contents(2, 3000),
+    );
+    write_file(&mut fs, "/data/index", b"1");
+
+    assert_eq!(names(&mut fs, "/data"), ["Sensor readings", "index"]);
+    assert_eq!(
+        read_all(&mut fs, "/DATA/sensor readings/DAY 1.CSV"),
+        contents(2, 3000)
+    );
+    assert!(fs.metadata("/data/Sensor readings").unwrap().is_dir());
+    assert_eq!(fs.open("/data").err(), Some(FatError::IsADirectory));
+    assert_eq!(
+        fs.read_dir("/data/index").err(),
+        Some(FatError::NotADirectory)
+    );
+    assert_eq!(fs.open("/data/missing").err(), Some(FatError::NotFound));
+    assert_eq!(fs.remove("/data").err(), Some(FatError::DirectoryNotEmpty));
+
+    fs.remove("/data/Sensor readings/day 1.csv").unwrap();
+    fs.remove("/data/Sensor readings").unwrap();
+    fs.remove("/data/index").unwrap();
+    fs.remove("/data").unwrap();
+
+    assert_eq!(names(&mut fs, "/"), Vec::<String>::new());
+    assert_eq!(fs.free_clusters().unwrap(), free);
+}
+
+#[test]
+fn directories_grow_and_root_fills_up() {
+    let mut image = vec![0; FLOPPY];
+    let mut fs = FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat12).unwrap();
+
+    fs.create_dir("/many").unwrap();
+
+    for i in 0..100 {
+        fs.create_file(&format!("/many/Entry number {i}.txt"))
+            .unwrap();
+    }
+
+    assert_eq!(fs.read_dir("/many").unwrap().count(), 100);
+    assert!(fs.metadata("/many/entry number 99.txt").is_ok());
+
+    // The FAT12 root directory has a fixed size.
+    let mut created = 1;
+
+    loop {
+        match fs.create_file(&format!("/F{created}")) {
+            Ok(_) => created += 1,
+            Err(error) => {
+                assert_eq!(error, FatError::DirectoryFull);
+                break;
+            }
+        }
+    }
+
+    assert_eq!(created, 224);
+}
+
+#[test]
+fn seek_overwrite_and_truncate() {
+    let mut image = vec![0; FLOPPY];
+    let mut fs = FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat12).unwrap();
+    let free = fs.free_clusters().unwrap();
+    let mut data = contents(3, 10000);
+
+    write_file(&mut fs, "/image.bin", &data);
+
+    {
+        let mut file = fs.open("/image.bin").unwrap();
+
+        file.seek(4000).unwrap();
+        file.write(&[0x55; 2000]).unwrap();
+        data[4000..6000].fill(0x55);
+
+        assert_eq!(file.seek(10001), Err(FatError::InvalidSeek));
+
+        // Reading the tail from the middle of a cluster.
+        let mut tail = [0; 100];
+
+        file.seek(9950).unwrap();
+        assert_eq!(file.read(&mut tail).unwrap(), 50);
+        assert_eq!(tail[..50], data[9950..]);
+    }
+
+    assert_eq!(read_all(&mut fs, "/image.bin"), data);
+
+    {
+        let mut file = fs.open("/image.bin").unwrap();
+
+        file.seek(1500).unwrap();
+        file.truncate().unwrap();
+        assert_eq!(file.len(), 1500);
+    }
+
+    assert_eq!(fs.metadata("/image.bin").unwrap().size(), 1500);
+    assert_eq!(read_all(&mut fs, "/image.bin"), data[..1500]);
+    assert_eq!(fs.free_clusters().unwrap(), free - 3);
+
+    fs.open("/image.bin").unwrap().truncate().unwrap();
+    assert_eq!(fs.free_clusters().unwrap(), free);
+    assert_eq!(read_all(&mut fs, "/image.bin"), []);
+}
+
+#[test]
+fn short_write_when_disk_fills_up() {
+    let mut image = vec![0; 64 * 1024];
+    let mut fs = FileSystem::format(MemoryDisk::new(&mut image), FatType::Fat12).unwrap();
+    let capacity = fs.free_clusters().unwrap() as usize * fs.cluster_size();
+    let data = contents(4, capacity + 1000);
+    let mut file = fs.create_file("/big").unwrap();
+
+    assert_eq!(file.write(&data).unwrap(), capacity);
+    assert_eq!(file.write(&data), Err(FatError::DiskFull));
+
+    assert_eq!(read_all(&mut fs, "/big"), data[..capacity]);
+    assert_eq!(fs.create_dir("/dir"), Err(FatError::DiskFull));
+}
+
+// A floppy laid out by hand the way other systems write them: a volume label, a deleted
+// entry, a long name spanning two entries and a lower case short name.
+#[test]
+fn reads_image_made_elsewhere() {
+    let mut image = vec![0; FLOPPY];
+    let boot = &mut image[..512];
+
+    boot[..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+    boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+    boot[13] = 1;
+    boot[14..16].copy_from_slice(&1u16.to_le_bytes());
+    boot[16] = 2;
+    boot[17..19].copy_from_slice(&224u16.to_le_bytes());
+    boot[19..21].copy_from_slice(&2880u16.to_le_bytes());
+    boot[21] = 0xF0;
+    boot[22..24].copy_from_slice(&9u16.to_le_bytes());
+    boot[510..].copy_from_slice(&[0x55, 0xAA]);
+
+    for fat in [512, 512 + 9 * 512] {
+        let entries: [(usize, u16); 5] = [(0, 0xFF0), (1, 0xFFF), (2, 3), (3, 0xFFF), (4, 0xFFF)];
+
+        for (cluster, value) in entries {
+            let offset = fat + cluster * 3 / 2;
+            let old = u16::from_le_bytes([image[offset], image[offset + 1]]);
+            let new = if cluster % 2 == 1 {
+                (old & 0xF) | value << 4
+            } else {
+                (old & 0xF000) | value
+            };
+
+            image[offset..offset + 2].copy_from_slice(&new.to_le_bytes());
+        }
+    }
+
+    let root = 19 * 512;
+    let data = 33 * 512;
+    let short = *b"FLIGHT~1TXT";
+    let checksum = short
+        .iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte));
+    let long: Vec<u16> = "Flight plan.txt".encode_utf16().collect();
+    let long_entry = |order: u8, last: bool| {
+        let mut entry = [0u8; 32];
+        let offsets = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+        entry[0] = order | if last { 0x40 } else { 0 };
+        entry[11] = 0x0F;
+        entry[13] = checksum;
+
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let unit = match (order as usize - 1) * 13 + i {
+                n if n < long.len() => long[n],
+                n if n == long.len() => 0,
+                _ => 0xFFFF,
+            };
+
+            entry[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+
+        entry
+    };
+    let short_entry = |name: &[u8; 11], attributes: u8, case: u8, cluster: u16, size: u32| {
+        let mut entry = [0u8; 32];
+
+        entry[..11].copy_from_slice(name);
+        entry[11] = attributes;
+        entry[12] = case;
+        entry[26..28].copy_from_slice(&cluster.to_le_bytes());
+        entry[28..32].copy_from_slice(&size.to_le_bytes());
+
+        entry
+    };
+    let mut deleted = short_entry(b"OLD     TXT", 0x20, 0, 0, 0);
+
+    deleted[0] = 0xE5;
+
+    let entries = [
+        short_entry(b"STATION 7  ", 0x08, 0, 0, 0),
+        deleted,
+        long_entry(2, true),
+        long_entry(1, false),
+        short_entry(&short, 0x20, 0, 2, 700),
+        short_entry(b"BOOT    CFG", 0x20, 0x18, 4, 10),
+    ];
+
+    for (i, entry) in entries.iter().enumerate() {
+        image[root + i * 32..root + i * 32 + 32].copy_from_slice(entry);
+    }
+
+    let plan = contents(5, 700);
+
+    image[data..data + 700].copy_from_slice(&plan);
+    image[data + 2 * 512..data + 2 * 512 + 10].copy_from_slice(b"timeout=3\n");
+
+    let mut fs = FileSystem::mount(MemoryDisk::new(&mut image)).unwrap();
+
+    assert_eq!(fs.fat_type(), FatType::Fat12);
+    assert_eq!(names(&mut fs, "/"), ["Flight plan.txt", "boot.cfg"]);
+    assert_eq!(read_all(&mut fs, "/flight plan.txt"), plan);
+    assert_eq!(read_all(&mut fs, "/BOOT.CFG"), b"timeout=3\n");
+    assert_eq!(fs.free_clusters().unwrap(), fs.cluster_count() - 3);
+
+    // Changes go into the same layout.
+    write_file(&mut fs, "/Flight plan (copy).txt", &plan);
+    fs.remove("/Flight plan.txt").unwrap();
+
+    let mut fs = FileSystem::mount(fs.into_inner()).unwrap();
+
+    assert_eq!(names(&mut fs, "/"), ["Flight plan (copy).txt", "boot.cfg"]);
+    assert_eq!(read_all(&mut fs, "/flight plan (copy).txt"), plan);
+    assert_eq!(fs.device().size(), FLOPPY);
+}
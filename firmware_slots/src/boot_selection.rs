@@ -0,0 +1,10 @@
+use crate::{ImageHeader, Slot};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootSelection {
+    pub slot: Slot,
+    pub header: ImageHeader,
+    // The image hasn't confirmed itself yet and has to call `FirmwareSlots::confirm` once
+    // it is up, otherwise the previous image boots again after its attempts run out.
+    pub trial: bool,
+}
@@ -0,0 +1,36 @@
+use core::fmt::Display;
+
+use flash::FlashError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FirmwareError {
+    Flash(FlashError),
+    InvalidLayout,
+    ImageTooLarge,
+    LengthMismatch,
+    DigestMismatch,
+    EmptySlot,
+    NoBootableImage,
+}
+
+impl Display for FirmwareError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FirmwareError::Flash(error) => write!(f, "flash error: {error}"),
+            FirmwareError::InvalidLayout => f.write_str("invalid firmware slot layout"),
+            FirmwareError::ImageTooLarge => f.write_str("firmware image does not fit the slot"),
+            FirmwareError::LengthMismatch => {
+                f.write_str("firmware image length does not match the header")
+            }
+            FirmwareError::DigestMismatch => f.write_str("firmware image digest does not match"),
+            FirmwareError::EmptySlot => f.write_str("firmware slot is empty"),
+            FirmwareError::NoBootableImage => f.write_str("no bootable firmware image"),
+        }
+    }
+}
+
+impl From<FlashError> for FirmwareError {
+    fn from(value: FlashError) -> Self {
+        FirmwareError::Flash(value)
+    }
+}
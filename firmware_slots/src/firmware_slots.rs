@@ -0,0 +1,262 @@
+use core::ops::Range;
+
+use crypto::{Sha256, SHA256_LENGTH};
+use flash::{FlashStorage, SECTOR_SIZE};
+
+use crate::{
+    image_header::{ATTEMPTS_OFFSET, CONFIRMED_OFFSET, IMAGE_HEADER_LENGTH, REJECTED_OFFSET},
+    BootSelection, FirmwareError, ImageHeader, Slot, SlotState, Update,
+};
+
+const CHUNK: usize = 256;
+const SLOTS: [Slot; 2] = [Slot::A, Slot::B];
+
+// Two firmware slots for A/B updates. A new image is installed into the slot that isn't
+// running and boots on trial, the previous image stays around as the fallback until the new
+// one confirms itself. Among confirmed images the most recently installed one boots.
+pub struct FirmwareSlots<F: FlashStorage> {
+    pub(crate) flash: F,
+    sectors: [Range<usize>; 2],
+}
+
+impl<F: FlashStorage> FirmwareSlots<F> {
+    pub fn new(flash: F, a: Range<usize>, b: Range<usize>) -> Result<Self, FirmwareError> {
+        let valid =
+            |sectors: &Range<usize>| !sectors.is_empty() && sectors.end <= flash.sector_count();
+
+        if !valid(&a) || !valid(&b) || (a.start < b.end && b.start < a.end) {
+            return Err(FirmwareError::InvalidLayout);
+        }
+
+        Ok(Self {
+            flash,
+            sectors: [a, b],
+        })
+    }
+
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    pub fn capacity(&self, slot: Slot) -> usize {
+        self.sectors[slot.index()].len() * SECTOR_SIZE - IMAGE_HEADER_LENGTH
+    }
+
+    pub fn header(&self, slot: Slot) -> Result<Option<ImageHeader>, FirmwareError> {
+        let mut bytes = [0; IMAGE_HEADER_LENGTH];
+
+        self.flash.read(self.slot_address(slot), &mut bytes)?;
+
+        Ok(ImageHeader::from_bytes(&bytes))
+    }
+
+    pub fn state(&self, slot: Slot) -> Result<SlotState, FirmwareError> {
+        Ok(match self.header(slot)? {
+            None => SlotState::Empty,
+            Some(header) if header.rejected() => SlotState::Rejected,
+            Some(header) if header.confirmed() => SlotState::Confirmed,
+            Some(header) => SlotState::Pending {
+                attempts_left: header.attempts_left(),
+            },
+        })
+    }
+
+    pub fn verify(&self, slot: Slot) -> Result<ImageHeader, FirmwareError> {
+        let header = self.header(slot)?.ok_or(FirmwareError::EmptySlot)?;
+
+        if header.length as usize > self.capacity(slot) {
+            return Err(FirmwareError::ImageTooLarge);
+        }
+
+        if self.image_digest(slot, header.length as usize)? != header.digest {
+            return Err(FirmwareError::DigestMismatch);
+        }
+
+        Ok(header)
+    }
+
+    pub fn read_image(
+        &self,
+        slot: Slot,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<(), FirmwareError> {
+        let header = self.header(slot)?.ok_or(FirmwareError::EmptySlot)?;
+
+        match offset.checked_add(buffer.len()) {
+            Some(end) if end <= header.length as usize => {
+                Ok(self.flash.read(self.image_address(slot) + offset, buffer)?)
+            }
+            _ => Err(FirmwareError::LengthMismatch),
+        }
+    }
+
+    // Picks the image to boot. A pending image uses up one attempt before it is handed out,
+    // so an image that hangs or crashes before confirming itself eventually gets rejected.
+    // Images that fail verification are rejected on the spot.
+    pub fn select(&mut self) -> Result<BootSelection, FirmwareError> {
+        for _ in 0..=SLOTS.len() {
+            let fallback = self.fallback()?;
+            let mut trial = None;
+
+            for slot in SLOTS {
+                let Some(header) = self.header(slot)? else {
+                    continue;
+                };
+
+                if header.rejected() || header.confirmed() {
+                    continue;
+                }
+
+                if header.attempts_left() == 0 {
+                    self.reject(slot)?;
+                } else if fallback.is_none_or(|(_, fallback)| header.sequence > fallback.sequence) {
+                    trial = Some((slot, header));
+                }
+            }
+
+            let (slot, header, is_trial) = match (trial, fallback) {
+                (Some((slot, header)), _) => (slot, header, true),
+                (None, Some((slot, header))) => (slot, header, false),
+                (None, None) => return Err(FirmwareError::NoBootableImage),
+            };
+
+            if self.verify(slot).is_err() {
+                self.reject(slot)?;
+                continue;
+            }
+
+            if is_trial {
+                self.program(slot, ATTEMPTS_OFFSET, header.next_attempts())?;
+            }
+
+            return Ok(BootSelection {
+                slot,
+                header: self.header(slot)?.ok_or(FirmwareError::EmptySlot)?,
+                trial: is_trial,
+            });
+        }
+
+        Err(FirmwareError::NoBootableImage)
+    }
+
+    pub fn confirm(&mut self, slot: Slot) -> Result<(), FirmwareError> {
+        self.header(slot)?.ok_or(FirmwareError::EmptySlot)?;
+        self.program(slot, CONFIRMED_OFFSET, 0)
+    }
+
+    pub fn reject(&mut self, slot: Slot) -> Result<(), FirmwareError> {
+        self.header(slot)?.ok_or(FirmwareError::EmptySlot)?;
+        self.program(slot, REJECTED_OFFSET, 0)
+    }
+
+    // The slot a new image goes into: never the one the station falls back to.
+    pub fn update_target(&self) -> Result<Slot, FirmwareError> {
+        if let Some((slot, _)) = self.fallback()? {
+            return Ok(slot.other());
+        }
+
+        let a = self.header(Slot::A)?;
+        let b = self.header(Slot::B)?;
+
+        Ok(match (a, b) {
+            (None, _) => Slot::A,
+            (_, None) => Slot::B,
+            (Some(a), Some(b)) if a.rejected() || (!b.rejected() && a.sequence < b.sequence) => {
+                Slot::A
+            }
+            _ => Slot::B,
+        })
+    }
+
+    pub fn begin_update(
+        &mut self,
+        version: u32,
+        length: usize,
+    ) -> Result<Update<'_, F>, FirmwareError> {
+        let slot = self.update_target()?;
+
+        if length > self.capacity(slot) {
+            return Err(FirmwareError::ImageTooLarge);
+        }
+
+        let mut sequence = 0;
+
+        for slot in SLOTS {
+            if let Some(header) = self.header(slot)? {
+                sequence = sequence.max(header.sequence.wrapping_add(1));
+            }
+        }
+
+        // The header sector is erased first, so the slot reads as empty from here on.
+        for sector in self.sectors[slot.index()].clone() {
+            self.flash.erase_sector(sector)?;
+        }
+
+        Ok(Update {
+            slots: self,
+            slot,
+            version,
+            sequence,
+            length,
+            written: 0,
+        })
+    }
+
+    pub(crate) fn slot_address(&self, slot: Slot) -> usize {
+        self.sectors[slot.index()].start * SECTOR_SIZE
+    }
+
+    pub(crate) fn image_address(&self, slot: Slot) -> usize {
+        self.slot_address(slot) + IMAGE_HEADER_LENGTH
+    }
+
+    pub(crate) fn image_digest(
+        &self,
+        slot: Slot,
+        length: usize,
+    ) -> Result<[u8; SHA256_LENGTH], FirmwareError> {
+        let mut hasher = Sha256::new();
+        let mut buffer = [0; CHUNK];
+        let address = self.image_address(slot);
+        let mut offset = 0;
+
+        while offset < length {
+            let chunk = &mut buffer[..CHUNK.min(length - offset)];
+
+            self.flash.read(address + offset, chunk)?;
+            hasher.update(chunk);
+            offset += chunk.len();
+        }
+
+        Ok(hasher.finalize())
+    }
+
+    // The most recently installed confirmed image that hasn't been rejected.
+    fn fallback(&self) -> Result<Option<(Slot, ImageHeader)>, FirmwareError> {
+        let mut fallback: Option<(Slot, ImageHeader)> = None;
+
+        for slot in SLOTS {
+            if let Some(header) = self.header(slot)? {
+                if header.confirmed()
+                    && !header.rejected()
+                    && fallback.is_none_or(|(_, other)| header.sequence > other.sequence)
+                {
+                    fallback = Some((slot, header));
+                }
+            }
+        }
+
+        Ok(fallback)
+    }
+
+    fn program(&mut self, slot: Slot, offset: usize, value: u8) -> Result<(), FirmwareError> {
+        Ok(self
+            .flash
+            .write(self.slot_address(slot) + offset, &[value])?)
+    }
+}
@@ -0,0 +1,116 @@
+use crc32::crc32;
+use crypto::SHA256_LENGTH;
+use flash::ERASED_BYTE;
+
+use crate::MAX_BOOT_ATTEMPTS;
+
+pub const IMAGE_HEADER_LENGTH: usize = 64;
+
+pub(crate) const ATTEMPTS_OFFSET: usize = 52;
+pub(crate) const CONFIRMED_OFFSET: usize = 53;
+pub(crate) const REJECTED_OFFSET: usize = 54;
+
+const MAGIC: [u8; 4] = *b"FWIM";
+const CRC_OFFSET: usize = 48;
+const PROGRAMMED_BYTE: u8 = 0x00;
+
+// Sits at the start of a slot, the image follows it. The first part is written once when
+// the image is installed and is covered by a CRC. The boot state bytes after it start out
+// erased and are only ever programmed towards zero, so the boot selector can update them
+// without erasing the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub version: u32,
+    pub sequence: u32,
+    pub length: u32,
+    pub digest: [u8; SHA256_LENGTH],
+    pub(crate) attempts: u8,
+    pub(crate) confirmed: bool,
+    pub(crate) rejected: bool,
+}
+
+impl ImageHeader {
+    pub(crate) fn new(
+        version: u32,
+        sequence: u32,
+        length: u32,
+        digest: [u8; SHA256_LENGTH],
+    ) -> Self {
+        Self {
+            version,
+            sequence,
+            length,
+            digest,
+            attempts: ERASED_BYTE,
+            confirmed: false,
+            rejected: false,
+        }
+    }
+
+    pub fn confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    pub fn rejected(&self) -> bool {
+        self.rejected
+    }
+
+    pub fn attempts_used(&self) -> u32 {
+        (!self.attempts).count_ones()
+    }
+
+    pub fn attempts_left(&self) -> u32 {
+        MAX_BOOT_ATTEMPTS.saturating_sub(self.attempts_used())
+    }
+
+    // Each boot attempt clears the lowest bit that is still set.
+    pub(crate) fn next_attempts(&self) -> u8 {
+        self.attempts & self.attempts.wrapping_sub(1)
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; IMAGE_HEADER_LENGTH] {
+        let mut bytes = [ERASED_BYTE; IMAGE_HEADER_LENGTH];
+
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&self.version.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.length.to_le_bytes());
+        bytes[16..CRC_OFFSET].copy_from_slice(&self.digest);
+
+        let crc = crc32(&bytes[..CRC_OFFSET]);
+
+        bytes[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        bytes[ATTEMPTS_OFFSET] = self.attempts;
+        bytes[CONFIRMED_OFFSET] = state_byte(self.confirmed);
+        bytes[REJECTED_OFFSET] = state_byte(self.rejected);
+
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8; IMAGE_HEADER_LENGTH]) -> Option<Self> {
+        let crc = u32::from_le_bytes(bytes[CRC_OFFSET..CRC_OFFSET + 4].try_into().unwrap());
+
+        if bytes[..4] != MAGIC || crc32(&bytes[..CRC_OFFSET]) != crc {
+            return None;
+        }
+
+        Some(Self {
+            version: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            sequence: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            length: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            digest: bytes[16..CRC_OFFSET].try_into().unwrap(),
+            attempts: bytes[ATTEMPTS_OFFSET],
+            // A flag byte torn half way through still counts as set.
+            confirmed: bytes[CONFIRMED_OFFSET] != ERASED_BYTE,
+            rejected: bytes[REJECTED_OFFSET] != ERASED_BYTE,
+        })
+    }
+}
+
+fn state_byte(set: bool) -> u8 {
+    if set {
+        PROGRAMMED_BYTE
+    } else {
+        ERASED_BYTE
+    }
+}
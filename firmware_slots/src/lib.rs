@@ -0,0 +1,18 @@
+#![no_std]
+
+mod boot_selection;
+mod firmware_error;
+mod firmware_slots;
+mod image_header;
+mod slot;
+mod update;
+
+pub use boot_selection::BootSelection;
+pub use firmware_error::FirmwareError;
+pub use firmware_slots::FirmwareSlots;
+pub use flash;
+pub use image_header::{ImageHeader, IMAGE_HEADER_LENGTH};
+pub use slot::{Slot, SlotState};
+pub use update::Update;
+
+pub const MAX_BOOT_ATTEMPTS: u32 = 3;
@@ -0,0 +1,59 @@
+use crypto::SHA256_LENGTH;
+use flash::FlashStorage;
+
+use crate::{FirmwareError, FirmwareSlots, ImageHeader, Slot};
+
+// An image being written into the slot that isn't running. Images arrive in pieces from a
+// floppy or the network, so they are written as they come and checked against the expected
+// digest at the end. The header goes in last, until then the slot stays empty and a power
+// cut leaves the running image untouched.
+pub struct Update<'s, F: FlashStorage> {
+    pub(crate) slots: &'s mut FirmwareSlots<F>,
+    pub(crate) slot: Slot,
+    pub(crate) version: u32,
+    pub(crate) sequence: u32,
+    pub(crate) length: usize,
+    pub(crate) written: usize,
+}
+
+impl<F: FlashStorage> Update<'_, F> {
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<(), FirmwareError> {
+        if self.written + data.len() > self.length {
+            return Err(FirmwareError::LengthMismatch);
+        }
+
+        let address = self.slots.image_address(self.slot) + self.written;
+
+        self.slots.flash.write(address, data)?;
+        self.written += data.len();
+
+        Ok(())
+    }
+
+    // The digest is computed over what actually landed on flash, not over what was sent.
+    pub fn finish(self, digest: &[u8; SHA256_LENGTH]) -> Result<Slot, FirmwareError> {
+        if self.written != self.length {
+            return Err(FirmwareError::LengthMismatch);
+        }
+
+        if self.slots.image_digest(self.slot, self.length)? != *digest {
+            return Err(FirmwareError::DigestMismatch);
+        }
+
+        let header = ImageHeader::new(self.version, self.sequence, self.length as u32, *digest);
+
+        self.slots
+            .flash
+            .write(self.slots.slot_address(self.slot), &header.to_bytes())?;
+
+        Ok(self.slot)
+    }
+}
@@ -0,0 +1,223 @@
+use crypto::sha256;
+use firmware_slots::{
+    flash::{FlashStorage, MemoryFlash, SECTOR_SIZE},
+    FirmwareError, FirmwareSlots, Slot, SlotState, IMAGE_HEADER_LENGTH, MAX_BOOT_ATTEMPTS,
+};
+
+type Flash = MemoryFlash<8>;
+
+fn open(flash: &mut Flash) -> FirmwareSlots<&mut Flash> {
+    FirmwareSlots::new(flash, 0..4, 4..8).unwrap()
+}
+
+fn image(version: u32, length: usize) -> Vec<u8> {
+    (0..length)
+        .map(|i| (i as u32).wrapping_mul(version + 13) as u8)
+        .collect()
+}
+
+// Writes the image in pieces, the way it arrives from a floppy or the network.
+fn install(
+    slots: &mut FirmwareSlots<impl FlashStorage>,
+    version: u32,
+    image: &[u8],
+) -> Result<Slot, FirmwareError> {
+    let mut update = slots.begin_update(version, image.len())?;
+
+    for chunk in image.chunks(1000) {
+        update.write(chunk)?;
+    }
+
+    update.finish(&sha256(image))
+}
+
+#[test]
+fn rejects_invalid_layout() {
+    let mut flash = Flash::new();
+
+    assert!(FirmwareSlots::new(&mut flash, 0..4, 3..6).is_err());
+    assert!(FirmwareSlots::new(&mut flash, 0..0, 4..8).is_err());
+    assert!(FirmwareSlots::new(&mut flash, 0..4, 4..9).is_err());
+}
+
+#[test]
+fn nothing_to_boot_on_empty_flash() {
+    let mut flash = Flash::new();
+    let mut slots = open(&mut flash);
+
+    assert_eq!(slots.state(Slot::A).unwrap(), SlotState::Empty);
+    assert_eq!(slots.select(), Err(FirmwareError::NoBootableImage));
+}
+
+#[test]
+fn new_image_boots_on_trial_until_confirmed() {
+    let mut flash = Flash::new();
+    let mut slots = open(&mut flash);
+    let v1 = image(1, 5000);
+
+    assert_eq!(install(&mut slots, 1, &v1).unwrap(), Slot::A);
+    assert_eq!(
+        slots.state(Slot::A).unwrap(),
+        SlotState::Pending {
+            attempts_left: MAX_BOOT_ATTEMPTS
+        }
+    );
+
+    let selection = slots.select().unwrap();
+
+    assert_eq!(selection.slot, Slot::A);
+    assert_eq!(selection.header.version, 1);
+    assert!(selection.trial);
+    assert_eq!(selection.header.attempts_left(), MAX_BOOT_ATTEMPTS - 1);
+
+    slots.confirm(Slot::A).unwrap();
+
+    let selection = slots.select().unwrap();
+
+    assert_eq!(selection.slot, Slot::A);
+    assert!(!selection.trial);
+    assert_eq!(slots.state(Slot::A).unwrap(), SlotState::Confirmed);
+
+    let mut loaded = vec![0; v1.len()];
+
+    slots.read_image(Slot::A, 0, &mut loaded).unwrap();
+    assert_eq!(loaded, v1);
+    assert_eq!(
+        slots.read_image(Slot::A, 1, &mut loaded),
+        Err(FirmwareError::LengthMismatch)
+    );
+}
+
+#[test]
+fn falls_back_when_new_image_never_confirms() {
+    let mut flash = Flash::new();
+    let mut slots = open(&mut flash);
+
+    install(&mut slots, 1, &image(1, 3000)).unwrap();
+    slots.select().unwrap();
+    slots.confirm(Slot::A).unwrap();
+
+    assert_eq!(install(&mut slots, 2, &image(2, 7000)).unwrap(), Slot::B);
+
+    for _ in 0..MAX_BOOT_ATTEMPTS {
+        let selection = slots.select().unwrap();
+
+        assert_eq!(selection.slot, Slot::B);
+        assert!(selection.trial);
+    }
+
+    let selection = slots.select().unwrap();
+
+    assert_eq!(selection.slot, Slot::A);
+    assert_eq!(selection.header.version, 1);
+    assert!(!selection.trial);
+    assert_eq!(slots.state(Slot::B).unwrap(), SlotState::Rejected);
+
+    // The rejected slot is the one that gets the next update.
+    assert_eq!(install(&mut slots, 3, &image(3, 100)).unwrap(), Slot::B);
+}
+
+#[test]
+fn confirmed_update_becomes_the_fallback() {
+    let mut flash = Flash::new();
+    let mut slots = open(&mut flash);
+
+    install(&mut slots, 1, &image(1, 3000)).unwrap();
+    slots.confirm(Slot::A).unwrap();
+    install(&mut slots, 2, &image(2, 3000)).unwrap();
+    slots.select().unwrap();
+    slots.confirm(Slot::B).unwrap();
+
+    assert_eq!(slots.select().unwrap().slot, Slot::B);
+    assert_eq!(slots.update_target().unwrap(), Slot::A);
+
+    // Images are ordered by installation, so a downgrade boots like any other update.
+    install(&mut slots, 1, &image(1, 3000)).unwrap();
+    slots.confirm(Slot::A).unwrap();
+    assert_eq!(slots.select().unwrap().slot, Slot::A);
+}
+
+#[test]
+fn corrupted_image_is_rejected() {
+    let mut flash = Flash::new();
+    let mut slots = open(&mut flash);
+
+    install(&mut slots, 1, &image(1, 3000)).unwrap();
+    slots.confirm(Slot::A).unwrap();
+    install(&mut slots, 2, &image(2, 3000)).unwrap();
+    slots.confirm(Slot::B).unwrap();
+
+    flash
+        .write(4 * SECTOR_SIZE + IMAGE_HEADER_LENGTH + 100, &[0x00])
+        .unwrap();
+
+    let mut slots = open(&mut flash);
+
+    assert_eq!(slots.verify(Slot::B), Err(FirmwareError::DigestMismatch));
+    assert_eq!(slots.select().unwrap().slot, Slot::A);
+    assert_eq!(slots.state(Slot::B).unwrap(), SlotState::Rejected);
+}
+
+#[test]
+fn update_checks_length_and_digest() {
+    let mut flash = Flash::new();
+    let mut slots = open(&mut flash);
+    let v1 = image(1, 3000);
+
+    assert_eq!(
+        slots.begin_update(1, 4 * SECTOR_SIZE).err(),
+        Some(FirmwareError::ImageTooLarge)
+    );
+
+    let mut update = slots.begin_update(1, v1.len()).unwrap();
+
+    update.write(&v1[..1000]).unwrap();
+    assert_eq!(
+        update.finish(&sha256(&v1)),
+        Err(FirmwareError::LengthMismatch)
+    );
+
+    let mut update = slots.begin_update(1, v1.len()).unwrap();
+
+    update.write(&v1).unwrap();
+    assert_eq!(update.write(&[0]), Err(FirmwareError::LengthMismatch));
+    assert_eq!(
+        update.finish(&sha256(b"another image")),
+        Err(FirmwareError::DigestMismatch)
+    );
+    assert_eq!(slots.state(Slot::A).unwrap(), SlotState::Empty);
+}
+
+#[test]
+fn power_cut_during_update_keeps_running_image() {
+    let v1 = image(1, 3000);
+    let v2 = image(2, 6000);
+    let mut cut = 0;
+
+    loop {
+        let mut flash = Flash::new();
+        let mut slots = open(&mut flash);
+
+        install(&mut slots, 1, &v1).unwrap();
+        slots.confirm(Slot::A).unwrap();
+
+        flash.cut_power_after(cut);
+        let _ = install(&mut open(&mut flash), 2, &v2);
+
+        let finished = flash.is_powered();
+
+        flash.restore_power();
+
+        let mut slots = open(&mut flash);
+        let selection = slots.select().unwrap();
+
+        if finished {
+            assert_eq!(selection.slot, Slot::B);
+            break;
+        }
+
+        assert_eq!(selection.slot, Slot::A, "power cut at byte {cut}");
+        assert_eq!(selection.header.version, 1);
+        cut += 509;
+    }
+}
@@ -0,0 +1,16 @@
+use core::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FlashError {
+    OutOfRange,
+    Misaligned,
+}
+
+impl Display for FlashError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FlashError::OutOfRange => f.write_str("flash access out of range"),
+            FlashError::Misaligned => f.write_str("flash erase is not sector aligned"),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{Flash, FlashError, SECTOR_SIZE};
+
+// Anything that behaves like flash: bytes can be programmed and whole sectors erased. Stores
+// built on this run against `Flash` on the station and against `MemoryFlash` on the host.
+pub trait FlashStorage {
+    fn size(&self) -> usize;
+
+    fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), FlashError>;
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), FlashError>;
+
+    fn erase_sector(&mut self, sector: usize) -> Result<(), FlashError>;
+
+    fn sector_count(&self) -> usize {
+        self.size() / SECTOR_SIZE
+    }
+}
+
+impl FlashStorage for Flash {
+    fn size(&self) -> usize {
+        Flash::size(self) as usize
+    }
+
+    fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), FlashError> {
+        unsafe { Flash::read(self, offset, buffer) }
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        unsafe { Flash::write(self, offset, data) }
+    }
+
+    fn erase_sector(&mut self, sector: usize) -> Result<(), FlashError> {
+        unsafe { Flash::erase_sector(self, sector) }
+    }
+}
+
+impl<F: FlashStorage + ?Sized> FlashStorage for &mut F {
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), FlashError> {
+        (**self).read(offset, buffer)
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        (**self).write(offset, data)
+    }
+
+    fn erase_sector(&mut self, sector: usize) -> Result<(), FlashError> {
+        (**self).erase_sector(sector)
+    }
+}
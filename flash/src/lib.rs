@@ -1,8 +1,21 @@
+#![allow(clippy::missing_safety_doc)]
 #![no_std]
 
+mod flash_error;
+mod flash_storage;
+mod memory_flash;
+
+use core::ops::Range;
+
+pub use flash_error::FlashError;
+pub use flash_storage::FlashStorage;
+pub use memory_flash::MemoryFlash;
 use mmio::Mmio;
 
 pub const MMIO_ADDRESS: usize = 0x1FF80000;
+pub const SECTOR_SIZE: usize = 4096;
+pub const ERASED_BYTE: u8 = 0xFF;
+const DATA_OFFSET: usize = core::mem::size_of::<u32>();
 
 pub struct Flash {
     mmio: Mmio,
@@ -14,8 +27,73 @@ impl Flash {
         self.size
     }
 
+    // A trailing partial sector can't be erased on its own, so it isn't counted.
+    pub fn sector_count(&self) -> usize {
+        self.size as usize / SECTOR_SIZE
+    }
+
     pub fn mmio(&self) -> Mmio {
-        Mmio::new(self.mmio.address + core::mem::size_of::<u32>())
+        Mmio::new(self.mmio.address + DATA_OFFSET)
+    }
+
+    pub unsafe fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), FlashError> {
+        let range = self.range(offset, buffer.len())?;
+        let mmio = self.mmio();
+
+        for (byte, offset) in buffer.iter_mut().zip(range) {
+            *byte = mmio.read_u8(offset);
+        }
+
+        Ok(())
+    }
+
+    pub unsafe fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        let range = self.range(offset, data.len())?;
+        let mmio = self.mmio();
+
+        for (byte, offset) in data.iter().zip(range) {
+            mmio.write_u8(*byte, offset);
+        }
+
+        Ok(())
+    }
+
+    // Erased sectors read back as `ERASED_BYTE`.
+    pub unsafe fn erase_sector(&mut self, sector: usize) -> Result<(), FlashError> {
+        if sector >= self.sector_count() {
+            return Err(FlashError::OutOfRange);
+        }
+
+        let mmio = self.mmio();
+
+        for offset in sector * SECTOR_SIZE..(sector + 1) * SECTOR_SIZE {
+            mmio.write_u8(ERASED_BYTE, offset);
+        }
+
+        Ok(())
+    }
+
+    pub unsafe fn erase(&mut self, range: Range<usize>) -> Result<(), FlashError> {
+        if !range.start.is_multiple_of(SECTOR_SIZE) || !range.end.is_multiple_of(SECTOR_SIZE) {
+            return Err(FlashError::Misaligned);
+        }
+
+        if range.end > self.sector_count() * SECTOR_SIZE {
+            return Err(FlashError::OutOfRange);
+        }
+
+        for sector in range.start / SECTOR_SIZE..range.end / SECTOR_SIZE {
+            self.erase_sector(sector)?;
+        }
+
+        Ok(())
+    }
+
+    fn range(&self, offset: usize, len: usize) -> Result<Range<usize>, FlashError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size as usize => Ok(offset..end),
+            _ => Err(FlashError::OutOfRange),
+        }
     }
 }
 
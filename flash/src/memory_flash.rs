@@ -0,0 +1,115 @@
+use core::ops::Range;
+
+use crate::{FlashError, FlashStorage, ERASED_BYTE, SECTOR_SIZE};
+
+// Flash image kept in memory, for running flash stores on the host. Writes behave like NOR
+// flash and can only clear bits, so code that forgets to erase fails here as well.
+// After a power cut, an erase can stop partway through a sector and leave it half erased.
+pub struct MemoryFlash<const SECTORS: usize> {
+    sectors: [[u8; SECTOR_SIZE]; SECTORS],
+    erase_counts: [u32; SECTORS],
+    budget: Option<usize>,
+}
+
+impl<const SECTORS: usize> MemoryFlash<SECTORS> {
+    pub const fn new() -> Self {
+        Self {
+            sectors: [[ERASED_BYTE; SECTOR_SIZE]; SECTORS],
+            erase_counts: [0; SECTORS],
+            budget: None,
+        }
+    }
+
+    pub fn sector(&self, sector: usize) -> &[u8; SECTOR_SIZE] {
+        &self.sectors[sector]
+    }
+
+    pub fn erase_counts(&self) -> &[u32; SECTORS] {
+        &self.erase_counts
+    }
+
+    // Power goes out after `bytes` more bytes are written or erased.
+    pub fn cut_power_after(&mut self, bytes: usize) {
+        self.budget = Some(bytes);
+    }
+
+    pub fn restore_power(&mut self) {
+        self.budget = None;
+    }
+
+    pub fn is_powered(&self) -> bool {
+        self.budget != Some(0)
+    }
+
+    fn range(&self, offset: usize, len: usize) -> Result<Range<usize>, FlashError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= SECTORS * SECTOR_SIZE => Ok(offset..end),
+            _ => Err(FlashError::OutOfRange),
+        }
+    }
+
+    fn spend(&mut self) -> bool {
+        match &mut self.budget {
+            Some(0) => false,
+            Some(budget) => {
+                *budget -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+impl<const SECTORS: usize> FlashStorage for MemoryFlash<SECTORS> {
+    fn size(&self) -> usize {
+        SECTORS * SECTOR_SIZE
+    }
+
+    fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), FlashError> {
+        let range = self.range(offset, buffer.len())?;
+
+        buffer.copy_from_slice(&self.sectors.as_flattened()[range]);
+
+        Ok(())
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        let range = self.range(offset, data.len())?;
+
+        for (byte, offset) in data.iter().zip(range) {
+            if !self.spend() {
+                break;
+            }
+
+            self.sectors.as_flattened_mut()[offset] &= byte;
+        }
+
+        Ok(())
+    }
+
+    fn erase_sector(&mut self, sector: usize) -> Result<(), FlashError> {
+        if sector >= SECTORS {
+            return Err(FlashError::OutOfRange);
+        }
+
+        if self.is_powered() {
+            self.erase_counts[sector] += 1;
+        }
+
+        for i in 0..SECTOR_SIZE {
+            if !self.spend() {
+                break;
+            }
+
+            self.sectors[sector][i] = ERASED_BYTE;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const SECTORS: usize> Default for MemoryFlash<SECTORS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
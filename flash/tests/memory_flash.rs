@@ -0,0 +1,51 @@
+use flash::{FlashError, FlashStorage, MemoryFlash, ERASED_BYTE, SECTOR_SIZE};
+
+#[test]
+fn programs_and_erases_like_nor() {
+    let mut flash = MemoryFlash::<2>::new();
+    let mut buffer = [0; 4];
+
+    flash
+        .write(SECTOR_SIZE - 2, &[0xF0, 0x0F, 0x3C, 0xC3])
+        .unwrap();
+    flash
+        .write(SECTOR_SIZE - 2, &[0x0F, 0xFF, 0xFF, 0xFF])
+        .unwrap();
+    flash.read(SECTOR_SIZE - 2, &mut buffer).unwrap();
+    assert_eq!(buffer, [0x00, 0x0F, 0x3C, 0xC3]);
+
+    flash.erase_sector(1).unwrap();
+    flash.read(SECTOR_SIZE - 2, &mut buffer).unwrap();
+    assert_eq!(buffer, [0x00, 0x0F, ERASED_BYTE, ERASED_BYTE]);
+    assert_eq!(flash.erase_counts(), &[0, 1]);
+}
+
+#[test]
+fn checks_bounds() {
+    let mut flash = MemoryFlash::<1>::new();
+
+    assert_eq!(flash.sector_count(), 1);
+    assert_eq!(
+        flash.read(SECTOR_SIZE - 1, &mut [0; 2]),
+        Err(FlashError::OutOfRange)
+    );
+    assert_eq!(flash.write(usize::MAX, &[0]), Err(FlashError::OutOfRange));
+    assert_eq!(flash.erase_sector(1), Err(FlashError::OutOfRange));
+}
+
+#[test]
+fn power_cut_freezes_image() {
+    let mut flash = MemoryFlash::<1>::new();
+
+    flash.cut_power_after(3);
+    flash.write(0, &[0; 5]).unwrap();
+    assert!(!flash.is_powered());
+
+    flash.erase_sector(0).unwrap();
+    assert_eq!(flash.sector(0)[..5], [0, 0, 0, ERASED_BYTE, ERASED_BYTE]);
+    assert_eq!(flash.erase_counts(), &[0]);
+
+    flash.restore_power();
+    flash.erase_sector(0).unwrap();
+    assert!(flash.sector(0).iter().all(|&byte| byte == ERASED_BYTE));
+}
@@ -0,0 +1,70 @@
+use core::ops::Range;
+
+use block_device::SECTOR_SIZE;
+
+// The boot sector, both FATs and the root directory of a 1.44 MB FAT12 disk.
+pub const FINGERPRINT_SECTORS: usize = 33;
+
+// Tells disks apart by their size and first `FINGERPRINT_SECTORS` sectors. Disks formatted
+// alike share a boot sector, their FATs and root directories are where they first differ. Two
+// disks identical that far still look like one. Every sector is hashed on its own, so the
+// driver's own writes can be folded in by reading back just the sectors they touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    size: usize,
+    sectors: [u64; FINGERPRINT_SECTORS],
+}
+
+impl Fingerprint {
+    // `read` fills the buffer from the given disk offset, false if the drive fails. None for
+    // an empty drive or a failed read.
+    pub fn read(size: usize, mut read: impl FnMut(usize, &mut [u8]) -> bool) -> Option<Self> {
+        if size == 0 {
+            return None;
+        }
+
+        let mut fingerprint = Self {
+            size,
+            sectors: [0; FINGERPRINT_SECTORS],
+        };
+
+        (0..fingerprint.sector_count())
+            .all(|sector| fingerprint.read_sector(sector, &mut read))
+            .then_some(fingerprint)
+    }
+
+    // Reads back the sectors of `range` that are part of the fingerprint. One that can't be
+    // read keeps its old hash, the next poll sorts it out.
+    pub fn update(&mut self, range: Range<usize>, mut read: impl FnMut(usize, &mut [u8]) -> bool) {
+        let end = range.end.div_ceil(SECTOR_SIZE).min(self.sector_count());
+
+        for sector in range.start / SECTOR_SIZE..end {
+            self.read_sector(sector, &mut read);
+        }
+    }
+
+    fn sector_count(&self) -> usize {
+        self.size.div_ceil(SECTOR_SIZE).min(FINGERPRINT_SECTORS)
+    }
+
+    fn read_sector(
+        &mut self,
+        sector: usize,
+        read: &mut impl FnMut(usize, &mut [u8]) -> bool,
+    ) -> bool {
+        let address = sector * SECTOR_SIZE;
+        let mut buffer = [0; SECTOR_SIZE];
+        let buffer = &mut buffer[..SECTOR_SIZE.min(self.size - address)];
+
+        if !read(address, buffer) {
+            return false;
+        }
+
+        // FNV-1a.
+        self.sectors[sector] = buffer.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+        });
+
+        true
+    }
+}
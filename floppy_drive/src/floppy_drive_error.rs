@@ -1,18 +1,61 @@
-#[repr(i64)]
+use core::fmt::Display;
+
+use block_device::BlockError;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FloppyDriveError {
-    InvalidAddress = -1,
-    InvalidSize = -2,
-    FloppyDriveIsEmpty = -3,
-    Unknown = 0xFFFFFF,
+    // Reported by the drive.
+    InvalidAddress,
+    InvalidSize,
+    FloppyDriveIsEmpty,
+    // Caught by the driver before anything reached the drive.
+    OutOfRange,
+    // A code this driver doesn't know, kept as the drive reported it.
+    Unknown(i64),
+}
+
+impl FloppyDriveError {
+    // The code the drive reported, None for errors the driver raised itself.
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            FloppyDriveError::InvalidAddress => Some(-1),
+            FloppyDriveError::InvalidSize => Some(-2),
+            FloppyDriveError::FloppyDriveIsEmpty => Some(-3),
+            FloppyDriveError::OutOfRange => None,
+            FloppyDriveError::Unknown(code) => Some(*code),
+        }
+    }
 }
 
 impl From<i64> for FloppyDriveError {
     fn from(value: i64) -> Self {
-        if value >= Self::InvalidAddress as i64 && value <= Self::FloppyDriveIsEmpty as i64 {
-            unsafe { core::mem::transmute(value) }
-        } else {
-            Self::Unknown
+        match value {
+            -1 => Self::InvalidAddress,
+            -2 => Self::InvalidSize,
+            -3 => Self::FloppyDriveIsEmpty,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl Display for FloppyDriveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FloppyDriveError::InvalidAddress => f.write_str("drive rejected the address"),
+            FloppyDriveError::InvalidSize => f.write_str("drive rejected the transfer size"),
+            FloppyDriveError::FloppyDriveIsEmpty => f.write_str("no disk in the drive"),
+            FloppyDriveError::OutOfRange => f.write_str("access beyond the end of the disk"),
+            FloppyDriveError::Unknown(code) => write!(f, "drive error {code}"),
+        }
+    }
+}
+
+impl From<FloppyDriveError> for BlockError {
+    fn from(value: FloppyDriveError) -> Self {
+        match value {
+            FloppyDriveError::FloppyDriveIsEmpty => BlockError::NoMedia,
+            FloppyDriveError::OutOfRange => BlockError::OutOfRange,
+            error => BlockError::Device(error.code().unwrap_or_default()),
         }
     }
 }
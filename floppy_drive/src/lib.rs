@@ -1,23 +1,39 @@
+#![allow(clippy::missing_safety_doc)]
 #![no_std]
 
-mod floppy_drive_argument;
+mod fingerprint;
 mod floppy_drive_error;
-mod floppy_drive_op;
+mod media_state;
+mod media_tracker;
 
-pub use floppy_drive_argument::FloppyDriveArgument;
+pub use block_device;
+use block_device::{
+    check_range, check_transferred, transfer, BlockDevice, BlockError, BulkOp, DeviceArgument,
+};
+pub use block_device::{MAX_READ_WRITE_SIZE, SECTOR_SIZE};
+pub use fingerprint::{Fingerprint, FINGERPRINT_SECTORS};
 pub use floppy_drive_error::FloppyDriveError;
-pub use floppy_drive_op::FloppyDriveOp;
+pub use media_state::MediaState;
+pub use media_tracker::MediaTracker;
 use pci::PciDevice;
 
+pub type FloppyDriveArgument = DeviceArgument;
+pub type FloppyDriveOp = BulkOp;
+
 pub const DEVICE_ID: u16 = 0x6D;
-pub const MAX_READ_WRITE_SIZE: usize = 65536;
 const GET_SIZE: usize = 0x1;
-const CALL_OP: usize = 0x0;
 const EJECT_DISK: usize = 0x1;
-const OP_RESULT: usize = 0x0;
 
+// The drive doesn't report media changes, so the driver keeps track itself: `poll_media` tells
+// disks apart by their `Fingerprint`, ejecting or finding the drive empty counts as a removal.
+// Writes through `write` and `write_at` carry the fingerprint along, raw ops from `call_op`
+// don't, so the next poll takes a disk changed that way for a new one. Each change bumps the
+// media generation, which is how a `BlockCache` on top knows to drop what it holds. The
+// write-protect tab isn't reported either, a write to a protected disk fails with whatever
+// code the drive returns.
 pub struct FloppyDrive {
     pub device: PciDevice,
+    media: MediaTracker,
 }
 
 impl FloppyDrive {
@@ -35,39 +51,150 @@ impl FloppyDrive {
 
     pub unsafe fn eject_disk(&mut self) {
         self.device.mmio.write_u32(1, EJECT_DISK);
+        self.media.removed();
     }
 
-    pub unsafe fn call_op(&mut self, op: FloppyDriveOp) -> Result<f64, FloppyDriveError> {
-        match op {
-            FloppyDriveOp::BulkRead {
-                address,
-                size,
-                dst_address,
-            }
-            | FloppyDriveOp::BulkWrite {
+    // The state as of the last poll or disk access.
+    pub fn media_state(&self) -> MediaState {
+        self.media.state()
+    }
+
+    // Looks at the drive and reports a different disk as `MediaState::Changed`. The first
+    // disk seen after the driver starts counts as a change too.
+    pub fn poll_media(&mut self) -> MediaState {
+        let fingerprint = self.fingerprint();
+
+        self.media.observe(fingerprint)
+    }
+
+    pub fn acknowledge_media_change(&mut self) {
+        self.media.acknowledge();
+    }
+
+    // Called with the new state whenever the media changes.
+    pub fn set_media_listener(&mut self, listener: Option<fn(MediaState)>) {
+        self.media.set_listener(listener);
+    }
+
+    fn fingerprint(&self) -> Option<Fingerprint> {
+        let size = unsafe { FloppyDrive::size(self) as usize };
+
+        Fingerprint::read(size, self.raw_reader())
+    }
+
+    // Reads for the fingerprint straight from the drive, leaving the media state alone.
+    fn raw_reader(&self) -> impl FnMut(usize, &mut [u8]) -> bool {
+        let mmio = self.device.mmio;
+
+        move |address, buffer| {
+            let op = FloppyDriveOp::BulkRead {
                 address,
-                size,
-                src_address: dst_address,
-            } => {
-                self.set_arg(FloppyDriveArgument::Arg0, address as f64);
-                self.set_arg(FloppyDriveArgument::Arg1, size as f64);
-                self.set_arg(FloppyDriveArgument::Arg2, dst_address as f64);
-            }
+                size: buffer.len(),
+                dst_address: buffer.as_mut_ptr() as usize,
+            };
+            let ret = unsafe { op.call(&mmio) };
+
+            ret >= 0.0 && ret as usize >= buffer.len()
         }
+    }
+
+    // Reads in chunks of at most `MAX_READ_WRITE_SIZE`, stopping early if the drive transfers
+    // less than asked. Returns the number of bytes read.
+    pub fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<usize, FloppyDriveError> {
+        self.check_range(offset, buffer.len())?;
+
+        transfer(offset, buffer.len(), |address, range| {
+            let chunk = &mut buffer[range];
+            let op = FloppyDriveOp::BulkRead {
+                address,
+                size: chunk.len(),
+                dst_address: chunk.as_mut_ptr() as usize,
+            };
+
+            unsafe { self.call_op(op) }
+        })
+    }
+
+    // Writes in chunks of at most `MAX_READ_WRITE_SIZE`, stopping early if the drive transfers
+    // less than asked. Returns the number of bytes written.
+    pub fn write(&mut self, offset: usize, data: &[u8]) -> Result<usize, FloppyDriveError> {
+        self.check_range(offset, data.len())?;
+
+        let written = transfer(offset, data.len(), |address, range| {
+            let chunk = &data[range];
+            let op = FloppyDriveOp::BulkWrite {
+                address,
+                size: chunk.len(),
+                src_address: chunk.as_ptr() as usize,
+            };
+
+            unsafe { self.call_op(op) }
+        });
 
-        self.device.mmio.write_u32(op.id(), CALL_OP);
-        let ret = self.device.mmio.read_f64(OP_RESULT);
+        // Even a failed write may have changed part of the range.
+        let reader = self.raw_reader();
 
-        if (ret as i64) < 0 {
-            Err(FloppyDriveError::from(ret as i64))
+        self.media.written(offset..offset + data.len(), reader);
+
+        written
+    }
+
+    fn check_range(&self, offset: usize, length: usize) -> Result<(), FloppyDriveError> {
+        let size = unsafe { FloppyDrive::size(self) as usize };
+
+        check_range(size, offset, length).map_err(|_| FloppyDriveError::OutOfRange)
+    }
+
+    // Returns the number of bytes the drive transferred.
+    pub unsafe fn call_op(&mut self, op: FloppyDriveOp) -> Result<usize, FloppyDriveError> {
+        let ret = op.call(&self.device.mmio);
+
+        if ret < 0.0 {
+            let error = FloppyDriveError::from(ret as i64);
+
+            if error == FloppyDriveError::FloppyDriveIsEmpty {
+                self.media.removed();
+            }
+
+            Err(error)
         } else {
-            Ok(ret)
+            Ok(ret as usize)
         }
     }
 }
 
+impl BlockDevice for FloppyDrive {
+    fn size(&self) -> usize {
+        unsafe { FloppyDrive::size(self) as usize }
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        let read = self.read(offset, buffer)?;
+
+        check_transferred(buffer.len(), read)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        let written = self.write(offset, data)?;
+
+        check_transferred(data.len(), written)
+    }
+
+    // Writes reach the disk before the op returns, there is nothing to flush.
+    fn flush(&mut self) -> Result<(), BlockError> {
+        Ok(())
+    }
+
+    fn media_generation(&self) -> u64 {
+        self.media.generation()
+    }
+}
+
 impl From<PciDevice> for FloppyDrive {
     fn from(device: PciDevice) -> Self {
-        Self { device }
+        Self {
+            device,
+            media: MediaTracker::new(),
+        }
     }
 }
@@ -0,0 +1,7 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MediaState {
+    Absent,
+    Present,
+    // A disk other than the last one seen is in the drive, until the change is acknowledged.
+    Changed,
+}
@@ -0,0 +1,89 @@
+use core::ops::Range;
+
+use crate::{Fingerprint, MediaState};
+
+// What the driver knows about the disk in the drive, built from what it sees the drive do.
+// Kept apart from the MMIO so the bookkeeping runs on the host.
+#[derive(Debug, Clone, Copy)]
+pub struct MediaTracker {
+    state: MediaState,
+    fingerprint: Option<Fingerprint>,
+    generation: u64,
+    listener: Option<fn(MediaState)>,
+}
+
+impl MediaTracker {
+    pub const fn new() -> Self {
+        Self {
+            state: MediaState::Absent,
+            fingerprint: None,
+            generation: 0,
+            listener: None,
+        }
+    }
+
+    pub fn state(&self) -> MediaState {
+        self.state
+    }
+
+    // Goes up on every removal and every new disk.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Takes the fingerprint of the disk a poll found, None for an empty drive. A different
+    // fingerprint than last time is a new disk, the first disk seen counts as one too.
+    pub fn observe(&mut self, fingerprint: Option<Fingerprint>) -> MediaState {
+        if fingerprint != self.fingerprint {
+            self.fingerprint = fingerprint;
+            self.set_state(match fingerprint {
+                Some(_) => MediaState::Changed,
+                None => MediaState::Absent,
+            });
+        }
+
+        self.state
+    }
+
+    // The disk was ejected or the drive reported itself empty.
+    pub fn removed(&mut self) {
+        if self.state != MediaState::Absent {
+            self.fingerprint = None;
+            self.set_state(MediaState::Absent);
+        }
+    }
+
+    // The driver wrote `range` of the disk. Its fingerprint follows the write, so the next
+    // poll doesn't take the same disk for another one.
+    pub fn written(&mut self, range: Range<usize>, read: impl FnMut(usize, &mut [u8]) -> bool) {
+        if let Some(fingerprint) = &mut self.fingerprint {
+            fingerprint.update(range, read);
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        if self.state == MediaState::Changed {
+            self.state = MediaState::Present;
+        }
+    }
+
+    // Called with the new state whenever the media changes.
+    pub fn set_listener(&mut self, listener: Option<fn(MediaState)>) {
+        self.listener = listener;
+    }
+
+    fn set_state(&mut self, state: MediaState) {
+        self.state = state;
+        self.generation += 1;
+
+        if let Some(listener) = self.listener {
+            listener(state);
+        }
+    }
+}
+
+impl Default for MediaTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,35 @@
+use floppy_drive::{block_device::BlockError, FloppyDriveError};
+
+#[test]
+fn keeps_device_codes() {
+    assert_eq!(FloppyDriveError::from(-1), FloppyDriveError::InvalidAddress);
+    assert_eq!(FloppyDriveError::from(-2), FloppyDriveError::InvalidSize);
+    assert_eq!(
+        FloppyDriveError::from(-3),
+        FloppyDriveError::FloppyDriveIsEmpty
+    );
+    assert_eq!(FloppyDriveError::from(-4), FloppyDriveError::Unknown(-4));
+    assert_eq!(FloppyDriveError::from(-9), FloppyDriveError::Unknown(-9));
+    assert_eq!(FloppyDriveError::from(-9).code(), Some(-9));
+    assert_eq!(FloppyDriveError::OutOfRange.code(), None);
+}
+
+#[test]
+fn converts_to_block_errors() {
+    assert_eq!(
+        BlockError::from(FloppyDriveError::FloppyDriveIsEmpty),
+        BlockError::NoMedia
+    );
+    assert_eq!(
+        BlockError::from(FloppyDriveError::OutOfRange),
+        BlockError::OutOfRange
+    );
+    assert_eq!(
+        BlockError::from(FloppyDriveError::Unknown(-9)),
+        BlockError::Device(-9)
+    );
+    assert_eq!(
+        FloppyDriveError::FloppyDriveIsEmpty.to_string(),
+        "no disk in the drive"
+    );
+}
@@ -0,0 +1,132 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use floppy_drive::{Fingerprint, MediaState, MediaTracker, FINGERPRINT_SECTORS, SECTOR_SIZE};
+
+const DISK_SIZE: usize = 2 * FINGERPRINT_SECTORS * SECTOR_SIZE;
+
+static CHANGES: AtomicUsize = AtomicUsize::new(0);
+
+fn count_change(_: MediaState) {
+    CHANGES.fetch_add(1, Ordering::SeqCst);
+}
+
+fn reader(disk: &[u8]) -> impl FnMut(usize, &mut [u8]) -> bool + '_ {
+    |address, buffer| {
+        buffer.copy_from_slice(&disk[address..][..buffer.len()]);
+        true
+    }
+}
+
+fn fingerprint(disk: &[u8]) -> Option<Fingerprint> {
+    Fingerprint::read(disk.len(), reader(disk))
+}
+
+fn with_disk(disk: &[u8]) -> MediaTracker {
+    let mut media = MediaTracker::new();
+
+    media.observe(fingerprint(disk));
+    media.acknowledge();
+    media
+}
+
+#[test]
+fn first_disk_counts_as_a_change() {
+    let disk = vec![1; DISK_SIZE];
+    let mut media = MediaTracker::new();
+
+    assert_eq!(media.state(), MediaState::Absent);
+    assert_eq!(media.observe(None), MediaState::Absent);
+    assert_eq!(media.generation(), 0);
+
+    assert_eq!(media.observe(fingerprint(&disk)), MediaState::Changed);
+    assert_eq!(media.generation(), 1);
+
+    // Stays changed until acknowledged, later polls of the same disk change nothing.
+    assert_eq!(media.observe(fingerprint(&disk)), MediaState::Changed);
+    media.acknowledge();
+    assert_eq!(media.observe(fingerprint(&disk)), MediaState::Present);
+    assert_eq!(media.generation(), 1);
+}
+
+#[test]
+fn eject_and_reinsert() {
+    let disk = vec![1; DISK_SIZE];
+    let mut media = with_disk(&disk);
+
+    media.removed();
+    assert_eq!(media.state(), MediaState::Absent);
+    assert_eq!(media.generation(), 2);
+
+    // A drive found empty after the eject is no news.
+    media.removed();
+    assert_eq!(media.observe(None), MediaState::Absent);
+    assert_eq!(media.generation(), 2);
+
+    // The same disk back in may have been written elsewhere, so it is a change all the same.
+    assert_eq!(media.observe(fingerprint(&disk)), MediaState::Changed);
+    assert_eq!(media.generation(), 3);
+}
+
+#[test]
+fn swap_between_polls() {
+    let first = vec![1; DISK_SIZE];
+    let second = vec![2; DISK_SIZE];
+    let mut media = with_disk(&first);
+
+    assert_eq!(media.observe(fingerprint(&second)), MediaState::Changed);
+    assert_eq!(media.generation(), 2);
+    media.acknowledge();
+    assert_eq!(media.observe(fingerprint(&first)), MediaState::Changed);
+    assert_eq!(media.generation(), 3);
+}
+
+#[test]
+fn disks_with_the_same_boot_sector_differ() {
+    let first = vec![1; DISK_SIZE];
+    let mut second = first.clone();
+
+    // Same boot sector, different FAT.
+    second[SECTOR_SIZE + 3] = 0;
+
+    let mut media = with_disk(&first);
+
+    assert_eq!(media.observe(fingerprint(&second)), MediaState::Changed);
+    assert_eq!(fingerprint(&[]), None);
+    assert_ne!(fingerprint(&first[..DISK_SIZE - 1]), fingerprint(&first));
+}
+
+#[test]
+fn own_writes_keep_the_disk() {
+    let mut disk = vec![1; DISK_SIZE];
+    let mut media = with_disk(&disk);
+
+    // Straddles two fingerprinted sectors, then one past them.
+    disk[SECTOR_SIZE - 2..SECTOR_SIZE + 2].fill(7);
+    media.written(SECTOR_SIZE - 2..SECTOR_SIZE + 2, reader(&disk));
+    disk[DISK_SIZE - 1] = 7;
+    media.written(DISK_SIZE - 1..DISK_SIZE, reader(&disk));
+
+    assert_eq!(media.observe(fingerprint(&disk)), MediaState::Present);
+    assert_eq!(media.generation(), 1);
+
+    // A write the tracker never heard of looks like another disk.
+    disk[0] = 0;
+    assert_eq!(media.observe(fingerprint(&disk)), MediaState::Changed);
+}
+
+#[test]
+fn listener_hears_every_change() {
+    let first = vec![1; DISK_SIZE];
+    let second = vec![2; DISK_SIZE];
+    let mut media = MediaTracker::new();
+
+    media.set_listener(Some(count_change));
+    media.observe(fingerprint(&first));
+    media.observe(fingerprint(&first));
+    media.acknowledge();
+    media.observe(fingerprint(&second));
+    media.removed();
+    media.removed();
+
+    assert_eq!(CHANGES.load(Ordering::SeqCst), 3);
+}
@@ -1,17 +1,56 @@
-#[repr(i64)]
+use core::fmt::Display;
+
+use block_device::BlockError;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HddError {
-    InvalidAddress = -1,
-    InvalidSize = -2,
-    Unknown = 0xFFFFFF,
+    // Reported by the disk.
+    InvalidAddress,
+    InvalidSize,
+    // Caught by the driver before anything reached the disk.
+    OutOfRange,
+    // A code this driver doesn't know, kept as the disk reported it.
+    Unknown(i64),
+}
+
+impl HddError {
+    // The code the disk reported, None for errors the driver raised itself.
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            HddError::InvalidAddress => Some(-1),
+            HddError::InvalidSize => Some(-2),
+            HddError::OutOfRange => None,
+            HddError::Unknown(code) => Some(*code),
+        }
+    }
 }
 
 impl From<i64> for HddError {
     fn from(value: i64) -> Self {
-        if value >= Self::InvalidAddress as i64 && value <= Self::InvalidSize as i64 {
-            unsafe { core::mem::transmute(value) }
-        } else {
-            Self::Unknown
+        match value {
+            -1 => Self::InvalidAddress,
+            -2 => Self::InvalidSize,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl Display for HddError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HddError::InvalidAddress => f.write_str("disk rejected the address"),
+            HddError::InvalidSize => f.write_str("disk rejected the transfer size"),
+            HddError::OutOfRange => f.write_str("access beyond the end of the disk"),
+            HddError::Unknown(code) => write!(f, "disk error {code}"),
+        }
+    }
+}
+
+impl From<HddError> for BlockError {
+    fn from(value: HddError) -> Self {
+        match value.code() {
+            Some(code) => BlockError::Device(code),
+            None => BlockError::OutOfRange,
         }
     }
 }
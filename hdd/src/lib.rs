@@ -1,20 +1,21 @@
 #![allow(clippy::missing_safety_doc)]
 #![no_std]
 
-mod hdd_argument;
 mod hdd_error;
-mod hdd_op;
 
-pub use hdd_argument::HddArgument;
+pub use block_device;
+use block_device::{
+    check_range, check_transferred, transfer, BlockDevice, BlockError, BulkOp, DeviceArgument,
+};
+pub use block_device::{MAX_READ_WRITE_SIZE, SECTOR_SIZE};
 pub use hdd_error::HddError;
-pub use hdd_op::HddOp;
 use pci::PciDevice;
 
+pub type HddArgument = DeviceArgument;
+pub type HddOp = BulkOp;
+
 pub const DEVICE_ID: u16 = 0x6C;
-pub const MAX_READ_WRITE_SIZE: usize = 65536;
 const GET_SIZE: usize = 0x1;
-const CALL_OP: usize = 0x0;
-const OP_RESULT: usize = 0x0;
 
 pub struct Hdd {
     pub device: PciDevice,
@@ -33,33 +34,79 @@ impl Hdd {
         self.device.mmio.read_u32(GET_SIZE)
     }
 
-    pub unsafe fn call_op(&mut self, op: HddOp) -> Result<f64, HddError> {
-        match op {
-            HddOp::BulkRead {
-                address,
-                size,
-                dst_address,
-            }
-            | HddOp::BulkWrite {
-                address,
-                size,
-                src_address: dst_address,
-            } => {
-                self.set_arg(HddArgument::Arg0, address as f64);
-                self.set_arg(HddArgument::Arg1, size as f64);
-                self.set_arg(HddArgument::Arg2, dst_address as f64);
-            }
-        }
-
-        self.device.mmio.write_u32(op.id(), CALL_OP);
-        let ret = self.device.mmio.read_f64(OP_RESULT);
+    // Returns the number of bytes the disk transferred.
+    pub unsafe fn call_op(&mut self, op: HddOp) -> Result<usize, HddError> {
+        let ret = op.call(&self.device.mmio);
 
-        if (ret as i64) < 0 {
+        if ret < 0.0 {
             Err(HddError::from(ret as i64))
         } else {
-            Ok(ret)
+            Ok(ret as usize)
         }
     }
+
+    // Reads in chunks of at most `MAX_READ_WRITE_SIZE`, stopping early if the disk transfers
+    // less than asked. Returns the number of bytes read.
+    pub fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<usize, HddError> {
+        self.check_range(offset, buffer.len())?;
+
+        transfer(offset, buffer.len(), |address, range| {
+            let chunk = &mut buffer[range];
+            let op = HddOp::BulkRead {
+                address,
+                size: chunk.len(),
+                dst_address: chunk.as_mut_ptr() as usize,
+            };
+
+            unsafe { self.call_op(op) }
+        })
+    }
+
+    // Writes in chunks of at most `MAX_READ_WRITE_SIZE`, stopping early if the disk transfers
+    // less than asked. Returns the number of bytes written.
+    pub fn write(&mut self, offset: usize, data: &[u8]) -> Result<usize, HddError> {
+        self.check_range(offset, data.len())?;
+
+        transfer(offset, data.len(), |address, range| {
+            let chunk = &data[range];
+            let op = HddOp::BulkWrite {
+                address,
+                size: chunk.len(),
+                src_address: chunk.as_ptr() as usize,
+            };
+
+            unsafe { self.call_op(op) }
+        })
+    }
+
+    fn check_range(&self, offset: usize, length: usize) -> Result<(), HddError> {
+        let size = unsafe { Hdd::size(self) as usize };
+
+        check_range(size, offset, length).map_err(|_| HddError::OutOfRange)
+    }
+}
+
+impl BlockDevice for Hdd {
+    fn size(&self) -> usize {
+        unsafe { Hdd::size(self) as usize }
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        let read = self.read(offset, buffer)?;
+
+        check_transferred(buffer.len(), read)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        let written = self.write(offset, data)?;
+
+        check_transferred(data.len(), written)
+    }
+
+    // Writes reach the disk before the op returns, there is nothing to flush.
+    fn flush(&mut self) -> Result<(), BlockError> {
+        Ok(())
+    }
 }
 
 impl From<PciDevice> for Hdd {
@@ -0,0 +1,28 @@
+use hdd::{block_device::BlockError, HddError};
+
+#[test]
+fn keeps_device_codes() {
+    assert_eq!(HddError::from(-1), HddError::InvalidAddress);
+    assert_eq!(HddError::from(-2), HddError::InvalidSize);
+    assert_eq!(HddError::from(-42), HddError::Unknown(-42));
+    assert_eq!(HddError::from(-42).code(), Some(-42));
+    assert_eq!(HddError::InvalidSize.code(), Some(-2));
+    assert_eq!(HddError::OutOfRange.code(), None);
+}
+
+#[test]
+fn converts_to_block_errors() {
+    assert_eq!(
+        BlockError::from(HddError::InvalidAddress),
+        BlockError::Device(-1)
+    );
+    assert_eq!(
+        BlockError::from(HddError::Unknown(-9)),
+        BlockError::Device(-9)
+    );
+    assert_eq!(
+        BlockError::from(HddError::OutOfRange),
+        BlockError::OutOfRange
+    );
+    assert_eq!(HddError::Unknown(-9).to_string(), "disk error -9");
+}
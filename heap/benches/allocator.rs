@@ -0,0 +1,95 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use heap::Heap;
+
+const HEAP_SIZE: usize = 1024 * 1024;
+const LIVE_OBJECTS: usize = 1024;
+const ITERATIONS: usize = 200_000;
+// GPU object handles, small protocol structs and `net_hub::MAX_MESSAGE_SIZE` frames.
+const SIZES: [usize; 6] = [16, 24, 48, 96, 512, 1500];
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+
+        (self.0 >> 33) as usize
+    }
+}
+
+fn run(name: &str, heap: &Heap) {
+    let memory = Box::leak(vec![0u8; HEAP_SIZE].into_boxed_slice());
+
+    unsafe { heap.init(memory.as_mut_ptr(), memory.len()) };
+
+    let mut rng = Rng(0x0B5E55ED);
+    let mut live: Vec<Option<(*mut u8, Layout)>> = vec![None; LIVE_OBJECTS];
+    let mut alloc_time = Duration::ZERO;
+    let mut dealloc_time = Duration::ZERO;
+    let mut allocs = 0u32;
+    let mut deallocs = 0u32;
+
+    for _ in 0..ITERATIONS {
+        let slot = &mut live[rng.next() % LIVE_OBJECTS];
+
+        match slot.take() {
+            Some((ptr, layout)) => {
+                let start = Instant::now();
+
+                unsafe { heap.dealloc(black_box(ptr), layout) };
+
+                dealloc_time += start.elapsed();
+                deallocs += 1;
+            }
+            None => {
+                let layout = Layout::from_size_align(SIZES[rng.next() % SIZES.len()], 8).unwrap();
+                let start = Instant::now();
+                let ptr = unsafe { heap.alloc(black_box(layout)) };
+
+                alloc_time += start.elapsed();
+                allocs += 1;
+
+                assert!(!ptr.is_null(), "{name}: out of memory");
+
+                *slot = Some((ptr, layout));
+            }
+        }
+    }
+
+    let stats = heap.stats();
+    let free = stats.size - heap_used(&stats);
+    let largest_free_block = heap.largest_free_block();
+    let fragmentation = 100.0 - largest_free_block as f64 * 100.0 / free as f64;
+
+    println!("{name}:");
+    println!("  alloc:   {:?}/op", alloc_time / allocs);
+    println!("  dealloc: {:?}/op", dealloc_time / deallocs);
+    println!(
+        "  live: {} bytes in {} allocations",
+        stats.allocated, stats.allocations
+    );
+    println!(
+        "  free: {} bytes, largest free block: {} bytes, fragmentation: {:.1}%",
+        free, largest_free_block, fragmentation
+    );
+    println!("  cached in slabs: {} bytes", stats.cached);
+}
+
+// Slab blocks are carved from the linked list heap, so memory sitting in slab free lists is
+// neither allocated nor available for other sizes.
+fn heap_used(stats: &heap::HeapStats) -> usize {
+    stats.allocated + stats.cached
+}
+
+fn main() {
+    run("linked list", &Heap::empty());
+    run("slabs", &Heap::with_slabs());
+}
@@ -0,0 +1,111 @@
+use core::{alloc::Layout, cell::RefCell, fmt::Write, panic::Location};
+
+pub const TRACKED_ALLOCATIONS: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrackedAllocation {
+    pub ptr: *mut u8,
+    pub size: usize,
+    pub align: usize,
+    pub sequence: usize,
+    pub location: Option<&'static Location<'static>>,
+}
+
+pub(crate) struct AllocationTracker {
+    allocations: [Option<TrackedAllocation>; TRACKED_ALLOCATIONS],
+    location: Option<&'static Location<'static>>,
+    sequence: usize,
+    untracked: usize,
+}
+
+impl AllocationTracker {
+    pub const fn new() -> Self {
+        Self {
+            allocations: [None; TRACKED_ALLOCATIONS],
+            location: None,
+            sequence: 0,
+            untracked: 0,
+        }
+    }
+
+    pub fn set_location(
+        &mut self,
+        location: Option<&'static Location<'static>>,
+    ) -> Option<&'static Location<'static>> {
+        core::mem::replace(&mut self.location, location)
+    }
+
+    pub fn track(&mut self, ptr: *mut u8, layout: Layout) {
+        let allocation = TrackedAllocation {
+            ptr,
+            size: layout.size(),
+            align: layout.align(),
+            sequence: self.sequence,
+            location: self.location,
+        };
+
+        self.sequence += 1;
+
+        match self.allocations.iter_mut().find(|a| a.is_none()) {
+            Some(slot) => *slot = Some(allocation),
+            None => self.untracked += 1,
+        }
+    }
+
+    pub fn untrack(&mut self, ptr: *mut u8) {
+        match self
+            .allocations
+            .iter_mut()
+            .find(|a| matches!(a, Some(a) if a.ptr == ptr))
+        {
+            Some(slot) => *slot = None,
+            None => self.untracked = self.untracked.saturating_sub(1),
+        }
+    }
+
+    // Copies each entry out before writing it, `out` may allocate from the heap being dumped
+    // and that has to track its allocations. Those show up too if they land in a later slot.
+    pub fn dump(tracker: &RefCell<Self>, out: &mut impl Write) -> core::fmt::Result {
+        let mut outstanding = 0;
+
+        for index in 0..TRACKED_ALLOCATIONS {
+            let Some(allocation) = tracker.borrow().allocations[index] else {
+                continue;
+            };
+
+            outstanding += 1;
+
+            write!(
+                out,
+                "#{} {:#016X} size: {}, align: {}",
+                allocation.sequence, allocation.ptr as usize, allocation.size, allocation.align
+            )?;
+
+            match allocation.location {
+                Some(location) => writeln!(
+                    out,
+                    ", at: {}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )?,
+                None => writeln!(out)?,
+            }
+        }
+
+        let untracked = tracker.borrow().untracked;
+
+        writeln!(out, "outstanding: {outstanding}, untracked: {untracked}")
+    }
+}
+
+pub struct TagGuard<'h> {
+    pub(crate) tracker: &'h RefCell<AllocationTracker>,
+    pub(crate) previous: Option<&'static Location<'static>>,
+}
+
+impl Drop for TagGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.borrow_mut().set_location(self.previous);
+    }
+}
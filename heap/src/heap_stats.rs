@@ -0,0 +1,74 @@
+use core::fmt::Display;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    pub size: usize,
+    pub allocated: usize,
+    pub peak: usize,
+    pub allocations: usize,
+    pub total_allocations: usize,
+    pub failed_allocations: usize,
+    pub cached: usize,
+    pub leaked: usize,
+}
+
+impl HeapStats {
+    pub const fn empty() -> Self {
+        Self {
+            size: 0,
+            allocated: 0,
+            peak: 0,
+            allocations: 0,
+            total_allocations: 0,
+            failed_allocations: 0,
+            cached: 0,
+            leaked: 0,
+        }
+    }
+
+    pub fn free(&self) -> usize {
+        self.size.saturating_sub(self.allocated + self.leaked)
+    }
+
+    pub(crate) fn record_alloc(&mut self, size: usize) {
+        self.allocated += size;
+        self.allocations += 1;
+        self.total_allocations += 1;
+
+        if self.allocated > self.peak {
+            self.peak = self.allocated;
+        }
+    }
+
+    pub(crate) fn record_dealloc(&mut self, size: usize) {
+        self.allocated -= size;
+        self.allocations -= 1;
+    }
+
+    // Corrupted blocks are no longer live, but never become free again either.
+    pub(crate) fn record_leak(&mut self, size: usize) {
+        self.record_dealloc(size);
+        self.leaked += size;
+    }
+
+    pub(crate) fn record_failure(&mut self) {
+        self.failed_allocations += 1;
+    }
+}
+
+impl Display for HeapStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "size: {}, allocated: {}, peak: {}, allocations: {}, total: {}, failed: {}, cached: {}, leaked: {}",
+            self.size,
+            self.allocated,
+            self.peak,
+            self.allocations,
+            self.total_allocations,
+            self.failed_allocations,
+            self.cached,
+            self.leaked
+        )
+    }
+}
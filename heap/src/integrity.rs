@@ -0,0 +1,212 @@
+use core::{alloc::Layout, fmt::Display, ptr::null_mut};
+
+pub const CANARY: u8 = 0xAB;
+pub const CANARY_SIZE: usize = 8;
+pub const ALLOC_POISON: u8 = 0xCD;
+pub const FREE_POISON: u8 = 0xDD;
+
+const ALLOCATED_MAGIC: usize = 0xA110_CA7E;
+const FREED_MAGIC: usize = 0xF4EE_D0DE;
+const HEADER_SIZE: usize = core::mem::size_of::<AllocationHeader>();
+
+pub type IntegrityHandler = fn(error: &IntegrityError);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CorruptionKind {
+    DoubleFree,
+    InvalidHeader,
+    SizeMismatch,
+    FrontCanary,
+    BackCanary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegrityError {
+    pub ptr: usize,
+    pub size: usize,
+    pub kind: CorruptionKind,
+}
+
+impl Display for IntegrityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let kind = match self.kind {
+            CorruptionKind::DoubleFree => "double free",
+            CorruptionKind::InvalidHeader => "invalid header",
+            CorruptionKind::SizeMismatch => "size mismatch",
+            CorruptionKind::FrontCanary => "buffer underrun",
+            CorruptionKind::BackCanary => "buffer overrun",
+        };
+
+        write!(
+            f,
+            "heap corruption: {} at {:#016X}, size: {}",
+            kind, self.ptr, self.size
+        )
+    }
+}
+
+#[repr(C)]
+struct AllocationHeader {
+    next: *mut AllocationHeader,
+    prev: *mut AllocationHeader,
+    size: usize,
+    align: usize,
+    magic: usize,
+}
+
+// Every allocation is laid out as `[padding][header][front canary][data][back canary]`,
+// with the header linked into a list of live allocations so `check` can walk them.
+pub(crate) struct Integrity {
+    head: *mut AllocationHeader,
+    pub handler: Option<IntegrityHandler>,
+}
+
+impl Integrity {
+    pub const fn new() -> Self {
+        Self {
+            head: null_mut(),
+            handler: None,
+        }
+    }
+
+    pub fn raw_layout(layout: Layout) -> Option<Layout> {
+        let align = layout
+            .align()
+            .max(core::mem::align_of::<AllocationHeader>());
+        let size = Self::prefix(layout)
+            .checked_add(layout.size())?
+            .checked_add(CANARY_SIZE)?;
+
+        Layout::from_size_align(size, align).ok()
+    }
+
+    fn prefix(layout: Layout) -> usize {
+        (HEADER_SIZE + CANARY_SIZE).next_multiple_of(layout.align())
+    }
+
+    pub unsafe fn register(&mut self, raw: *mut u8, layout: Layout) -> *mut u8 {
+        let ptr = raw.add(Self::prefix(layout));
+        let header = Self::header(ptr);
+
+        header.write(AllocationHeader {
+            next: self.head,
+            prev: null_mut(),
+            size: layout.size(),
+            align: layout.align(),
+            magic: ALLOCATED_MAGIC,
+        });
+
+        if let Some(next) = self.head.as_mut() {
+            next.prev = header;
+        }
+
+        self.head = header;
+
+        ptr.sub(CANARY_SIZE).write_bytes(CANARY, CANARY_SIZE);
+        ptr.write_bytes(ALLOC_POISON, layout.size());
+        ptr.add(layout.size()).write_bytes(CANARY, CANARY_SIZE);
+
+        ptr
+    }
+
+    pub unsafe fn release(
+        &mut self,
+        ptr: *mut u8,
+        layout: Layout,
+    ) -> Result<*mut u8, IntegrityError> {
+        let header = Self::header(ptr);
+        let mut result = Self::validate(header, ptr);
+
+        if result.is_ok() && ((*header).size != layout.size() || (*header).align != layout.align())
+        {
+            result = Err(IntegrityError {
+                ptr: ptr as usize,
+                size: layout.size(),
+                kind: CorruptionKind::SizeMismatch,
+            });
+        }
+
+        match result {
+            Ok(()) => self.unlink(header),
+            // The header is intact, so the block leaves the list and is never checked again.
+            Err(error)
+                if !matches!(
+                    error.kind,
+                    CorruptionKind::DoubleFree | CorruptionKind::InvalidHeader
+                ) =>
+            {
+                self.unlink(header);
+                (*header).magic = FREED_MAGIC;
+
+                return Err(error);
+            }
+            Err(error) => return Err(error),
+        }
+
+        let raw = ptr.sub(Self::prefix(layout));
+        let raw_size = Self::prefix(layout) + layout.size() + CANARY_SIZE;
+
+        raw.write_bytes(FREE_POISON, raw_size);
+        (*header).magic = FREED_MAGIC;
+
+        Ok(raw)
+    }
+
+    pub unsafe fn check(&self) -> Result<(), IntegrityError> {
+        let mut header = self.head;
+
+        while !header.is_null() {
+            let ptr = (header as *mut u8).add(HEADER_SIZE + CANARY_SIZE);
+
+            Self::validate(header, ptr)?;
+            header = (*header).next;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn unlink(&mut self, header: *mut AllocationHeader) {
+        let AllocationHeader { next, prev, .. } = header.read();
+
+        match prev.as_mut() {
+            Some(prev) => prev.next = next,
+            None => self.head = next,
+        }
+
+        if let Some(next) = next.as_mut() {
+            next.prev = prev;
+        }
+    }
+
+    unsafe fn validate(header: *mut AllocationHeader, ptr: *mut u8) -> Result<(), IntegrityError> {
+        let error = |size, kind| IntegrityError {
+            ptr: ptr as usize,
+            size,
+            kind,
+        };
+
+        match (*header).magic {
+            ALLOCATED_MAGIC => {}
+            FREED_MAGIC => return Err(error(0, CorruptionKind::DoubleFree)),
+            _ => return Err(error(0, CorruptionKind::InvalidHeader)),
+        }
+
+        let size = (*header).size;
+        let front = core::slice::from_raw_parts(ptr.sub(CANARY_SIZE), CANARY_SIZE);
+        let back = core::slice::from_raw_parts(ptr.add(size), CANARY_SIZE);
+
+        if front.iter().any(|&b| b != CANARY) {
+            return Err(error(size, CorruptionKind::FrontCanary));
+        }
+
+        if back.iter().any(|&b| b != CANARY) {
+            return Err(error(size, CorruptionKind::BackCanary));
+        }
+
+        Ok(())
+    }
+
+    unsafe fn header(ptr: *mut u8) -> *mut AllocationHeader {
+        ptr.sub(CANARY_SIZE + HEADER_SIZE).cast()
+    }
+}
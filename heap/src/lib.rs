@@ -1,43 +1,362 @@
 #![allow(clippy::missing_safety_doc)]
 #![no_std]
 
-use core::{alloc::GlobalAlloc, cell::RefCell};
+#[cfg(feature = "tracking")]
+mod allocation_tracker;
+mod heap_error;
+mod heap_stats;
+#[cfg(feature = "integrity")]
+mod integrity;
+mod oom;
+mod regions;
+mod slab;
 
-use linked_list_allocator::Heap as LLHeap;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::RefCell,
+    ptr::NonNull,
+};
+
+#[cfg(feature = "tracking")]
+pub use allocation_tracker::{TagGuard, TrackedAllocation, TRACKED_ALLOCATIONS};
+pub use heap_error::HeapError;
+pub use heap_stats::HeapStats;
+#[cfg(feature = "integrity")]
+pub use integrity::{
+    CorruptionKind, IntegrityError, IntegrityHandler, ALLOC_POISON, CANARY, CANARY_SIZE,
+    FREE_POISON,
+};
+pub use oom::{OomHandler, Reclaimer, MAX_RECLAIMERS};
+pub use regions::{RegionStats, MAX_REGIONS};
+pub use slab::{SlabStats, SIZE_CLASSES, SLAB_SIZE};
+
+use oom::OomHooks;
+use regions::Regions;
+use slab::SlabCaches;
+
+#[cfg(feature = "tracking")]
+use allocation_tracker::AllocationTracker;
+#[cfg(feature = "integrity")]
+use integrity::Integrity;
+
+// What became of a block handed back to `dealloc`.
+#[cfg_attr(not(feature = "integrity"), allow(dead_code))]
+enum Release {
+    Freed,
+    Leaked,
+    AlreadyFreed,
+}
 
 pub struct Heap {
-    heap: RefCell<LLHeap>,
+    regions: RefCell<Regions>,
+    slabs: Option<RefCell<SlabCaches>>,
+    stats: RefCell<HeapStats>,
+    oom: RefCell<OomHooks>,
+    #[cfg(feature = "tracking")]
+    tracker: RefCell<AllocationTracker>,
+    #[cfg(feature = "integrity")]
+    integrity: RefCell<Integrity>,
 }
 
 impl Heap {
     pub unsafe fn init(&self, start: *mut u8, size: usize) {
-        self.heap.borrow_mut().init(start, size);
+        assert_eq!(
+            self.regions.borrow().len(),
+            0,
+            "heap is already initialized"
+        );
+
+        self.add_region(start, size).unwrap();
+    }
+
+    pub unsafe fn add_region(&self, start: *mut u8, size: usize) -> Result<usize, HeapError> {
+        let mut regions = self.regions.borrow_mut();
+        let region = regions.add(start, size)?;
+
+        self.stats.borrow_mut().size = regions.size();
+
+        Ok(region)
+    }
+
+    pub unsafe fn extend_region(&self, region: usize, by: usize) -> Result<(), HeapError> {
+        let mut regions = self.regions.borrow_mut();
+
+        regions.extend(region, by)?;
+        self.stats.borrow_mut().size = regions.size();
+
+        Ok(())
+    }
+
+    pub fn regions(&self) -> usize {
+        self.regions.borrow().len()
+    }
+
+    pub fn region_stats(&self, region: usize) -> Option<RegionStats> {
+        self.regions.borrow().stats(region)
+    }
+
+    // Expensive, the allocator has no way to list its holes, so this probes each region with
+    // a binary search of real allocations. Not part of `stats`, which the OOM path relies on.
+    pub fn largest_free_block(&self) -> usize {
+        self.regions.borrow_mut().largest_free_block()
     }
 
     pub const fn empty() -> Self {
-        let heap = LLHeap::empty();
+        Self::new(None)
+    }
+
+    pub const fn with_slabs() -> Self {
+        Self::new(Some(RefCell::new(SlabCaches::new())))
+    }
 
+    const fn new(slabs: Option<RefCell<SlabCaches>>) -> Self {
         Self {
-            heap: RefCell::new(heap),
+            regions: RefCell::new(Regions::new()),
+            slabs,
+            stats: RefCell::new(HeapStats::empty()),
+            oom: RefCell::new(OomHooks::new()),
+            #[cfg(feature = "tracking")]
+            tracker: RefCell::new(AllocationTracker::new()),
+            #[cfg(feature = "integrity")]
+            integrity: RefCell::new(Integrity::new()),
+        }
+    }
+
+    pub fn set_oom_handler(&self, handler: OomHandler) {
+        self.oom.borrow_mut().handler = Some(handler);
+    }
+
+    pub fn register_reclaimer(&self, reclaimer: Reclaimer) -> Result<(), HeapError> {
+        self.oom.borrow_mut().register(reclaimer)
+    }
+
+    pub fn slab_stats(&self) -> Option<[SlabStats; SIZE_CLASSES.len()]> {
+        self.slabs.as_ref().map(|slabs| slabs.borrow().stats())
+    }
+
+    // Gives cached slab blocks back to the regions. Runs on its own before the registered
+    // reclaimers when an allocation fails. Returns the number of bytes released.
+    pub fn trim_slabs(&self) -> usize {
+        let Some(slabs) = &self.slabs else {
+            return 0;
+        };
+
+        slabs.borrow_mut().trim(&mut self.regions.borrow_mut())
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let mut stats = *self.stats.borrow();
+
+        stats.cached = self
+            .slabs
+            .as_ref()
+            .map_or(0, |slabs| slabs.borrow().cached());
+
+        stats
+    }
+
+    #[cfg(feature = "tracking")]
+    #[track_caller]
+    pub fn tag(&self) -> TagGuard<'_> {
+        let previous = self
+            .tracker
+            .borrow_mut()
+            .set_location(Some(core::panic::Location::caller()));
+
+        TagGuard {
+            tracker: &self.tracker,
+            previous,
+        }
+    }
+
+    #[cfg(feature = "tracking")]
+    pub fn dump_allocations(&self, out: &mut impl core::fmt::Write) -> core::fmt::Result {
+        AllocationTracker::dump(&self.tracker, out)
+    }
+
+    #[cfg(feature = "integrity")]
+    pub fn set_integrity_handler(&self, handler: IntegrityHandler) {
+        self.integrity.borrow_mut().handler = Some(handler);
+    }
+
+    #[cfg(feature = "integrity")]
+    pub fn check(&self) -> Result<(), IntegrityError> {
+        unsafe { self.integrity.borrow().check() }
+    }
+
+    #[cfg(feature = "integrity")]
+    fn raw_layout(layout: Layout) -> Option<Layout> {
+        Integrity::raw_layout(layout)
+    }
+
+    #[cfg(not(feature = "integrity"))]
+    fn raw_layout(layout: Layout) -> Option<Layout> {
+        Some(layout)
+    }
+
+    #[cfg(feature = "integrity")]
+    unsafe fn register(&self, raw: *mut u8, layout: Layout) -> *mut u8 {
+        self.integrity.borrow_mut().register(raw, layout)
+    }
+
+    #[cfg(not(feature = "integrity"))]
+    unsafe fn register(&self, raw: *mut u8, _layout: Layout) -> *mut u8 {
+        raw
+    }
+
+    // A corrupted allocation is reported and leaked, returning it to the allocator would spread
+    // the damage into the free list.
+    #[cfg(feature = "integrity")]
+    unsafe fn release(&self, ptr: *mut u8, layout: Layout) -> Release {
+        let released = self.integrity.borrow_mut().release(ptr, layout);
+
+        match released {
+            Ok(raw) => {
+                self.deallocate(
+                    NonNull::new_unchecked(raw),
+                    Integrity::raw_layout(layout).unwrap(),
+                );
+
+                Release::Freed
+            }
+            Err(error) => {
+                let handler = self.integrity.borrow().handler;
+
+                match handler {
+                    Some(handler) => handler(&error),
+                    None => panic!("{error}"),
+                }
+
+                match error.kind {
+                    CorruptionKind::DoubleFree => Release::AlreadyFreed,
+                    _ => Release::Leaked,
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "integrity"))]
+    unsafe fn release(&self, ptr: *mut u8, layout: Layout) -> Release {
+        self.deallocate(NonNull::new_unchecked(ptr), layout);
+
+        Release::Freed
+    }
+
+    fn slab_class(&self, layout: Layout) -> Option<(&RefCell<SlabCaches>, usize)> {
+        let slabs = self.slabs.as_ref()?;
+
+        SlabCaches::class(layout).map(|class| (slabs, class))
+    }
+
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        let mut regions = self.regions.borrow_mut();
+
+        let ptr = match self.slab_class(layout) {
+            Some((slabs, class)) => slabs.borrow_mut().allocate(class, &mut regions),
+            None => regions.allocate_first_fit(layout),
+        };
+
+        ptr.map_or(core::ptr::null_mut(), |all| all.as_ptr())
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self.slab_class(layout) {
+            Some((slabs, class)) => slabs.borrow_mut().deallocate(class, ptr),
+            None => self.regions.borrow_mut().deallocate(ptr, layout),
+        }
+    }
+
+    // Trimming the slab caches is the built-in first reclaimer. Reclaimers may free memory back
+    // into this heap, so no borrows are held while they run. Allocation failures inside a
+    // reclaimer skip straight to the handler.
+    fn handle_oom(&self, layout: Layout, raw_layout: Layout) -> *mut u8 {
+        let (reclaimers, reclaiming) = {
+            let mut oom = self.oom.borrow_mut();
+
+            (
+                oom.reclaimers,
+                core::mem::replace(&mut oom.reclaiming, true),
+            )
+        };
+
+        if !reclaiming {
+            let released = core::iter::once(self.trim_slabs()).chain(
+                reclaimers
+                    .iter()
+                    .flatten()
+                    .map(|reclaimer| reclaimer(layout)),
+            );
+
+            for released in released {
+                if released == 0 {
+                    continue;
+                }
+
+                let ptr = self.allocate(raw_layout);
+
+                if !ptr.is_null() {
+                    self.oom.borrow_mut().reclaiming = false;
+
+                    return ptr;
+                }
+            }
+
+            self.oom.borrow_mut().reclaiming = false;
         }
+
+        self.stats.borrow_mut().record_failure();
+
+        let handler = self.oom.borrow().handler;
+
+        if let Some(handler) = handler {
+            handler(layout, &self.stats());
+        }
+
+        core::ptr::null_mut()
     }
 }
 
 unsafe impl GlobalAlloc for Heap {
-    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        self.heap
-            .borrow_mut()
-            .allocate_first_fit(layout)
-            .ok()
-            .map_or(core::ptr::null_mut(), |all| all.as_ptr())
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(raw_layout) = Self::raw_layout(layout) else {
+            self.stats.borrow_mut().record_failure();
+
+            return core::ptr::null_mut();
+        };
+
+        let mut ptr = self.allocate(raw_layout);
+
+        if ptr.is_null() {
+            ptr = self.handle_oom(layout, raw_layout);
+        }
+
+        if ptr.is_null() {
+            return ptr;
+        }
+
+        let ptr = self.register(ptr, layout);
+
+        self.stats.borrow_mut().record_alloc(layout.size());
+
+        #[cfg(feature = "tracking")]
+        self.tracker.borrow_mut().track(ptr, layout);
+
+        ptr
     }
 
-    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         assert_ne!(ptr, core::ptr::null_mut());
 
-        self.heap
-            .borrow_mut()
-            .deallocate(core::ptr::NonNull::new_unchecked(ptr), layout)
+        let record = match self.release(ptr, layout) {
+            Release::Freed => HeapStats::record_dealloc,
+            Release::Leaked => HeapStats::record_leak,
+            Release::AlreadyFreed => return,
+        };
+
+        record(&mut self.stats.borrow_mut(), layout.size());
+
+        #[cfg(feature = "tracking")]
+        self.tracker.borrow_mut().untrack(ptr);
     }
 }
 
@@ -0,0 +1,36 @@
+use core::alloc::Layout;
+
+use crate::{HeapError, HeapStats};
+
+pub const MAX_RECLAIMERS: usize = 8;
+
+pub type OomHandler = fn(layout: Layout, stats: &HeapStats);
+pub type Reclaimer = fn(layout: Layout) -> usize;
+
+pub(crate) struct OomHooks {
+    pub handler: Option<OomHandler>,
+    pub reclaimers: [Option<Reclaimer>; MAX_RECLAIMERS],
+    pub reclaiming: bool,
+}
+
+impl OomHooks {
+    pub const fn new() -> Self {
+        Self {
+            handler: None,
+            reclaimers: [None; MAX_RECLAIMERS],
+            reclaiming: false,
+        }
+    }
+
+    pub fn register(&mut self, reclaimer: Reclaimer) -> Result<(), HeapError> {
+        let slot = self
+            .reclaimers
+            .iter_mut()
+            .find(|r| r.is_none())
+            .ok_or(HeapError::TooManyReclaimers)?;
+
+        *slot = Some(reclaimer);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+use linked_list_allocator::Heap as LLHeap;
+
+use crate::HeapError;
+
+pub const MAX_REGIONS: usize = 4;
+const PROBE_ALIGN: usize = core::mem::size_of::<usize>();
+// Size of the header `linked_list_allocator` writes into every hole.
+const HOLE_HEADER: usize = 2 * core::mem::size_of::<usize>();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionStats {
+    pub start: usize,
+    pub size: usize,
+    pub used: usize,
+    pub free: usize,
+}
+
+pub(crate) struct Regions {
+    heaps: [LLHeap; MAX_REGIONS],
+    count: usize,
+}
+
+impl Regions {
+    pub const fn new() -> Self {
+        Self {
+            heaps: [const { LLHeap::empty() }; MAX_REGIONS],
+            count: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn size(&self) -> usize {
+        self.iter().map(|heap| heap.size()).sum()
+    }
+
+    pub unsafe fn add(&mut self, start: *mut u8, size: usize) -> Result<usize, HeapError> {
+        if self.count == MAX_REGIONS {
+            return Err(HeapError::TooManyRegions);
+        }
+
+        let end = (start as usize)
+            .checked_add(size)
+            .ok_or(HeapError::InvalidRegion)?;
+
+        if start.is_null() || size < core::mem::size_of::<usize>() * 2 {
+            return Err(HeapError::InvalidRegion);
+        }
+
+        if self
+            .iter()
+            .any(|heap| (start as usize) < heap.top() as usize && end > heap.bottom() as usize)
+        {
+            return Err(HeapError::OverlappingRegion);
+        }
+
+        self.heaps[self.count].init(start, size);
+        self.count += 1;
+
+        Ok(self.count - 1)
+    }
+
+    pub unsafe fn extend(&mut self, region: usize, by: usize) -> Result<(), HeapError> {
+        let heap = self.heaps[..self.count]
+            .get(region)
+            .ok_or(HeapError::InvalidRegion)?;
+        let top = heap.top() as usize;
+        let end = top.checked_add(by).ok_or(HeapError::InvalidRegion)?;
+
+        if self
+            .iter()
+            .any(|other| top < other.top() as usize && end > other.bottom() as usize)
+        {
+            return Err(HeapError::OverlappingRegion);
+        }
+
+        self.heaps[region].extend(by);
+
+        Ok(())
+    }
+
+    pub fn allocate_first_fit(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        self.heaps[..self.count]
+            .iter_mut()
+            .find_map(|heap| heap.allocate_first_fit(layout).ok())
+    }
+
+    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        let address = ptr.as_ptr() as usize;
+        let heap = self.heaps[..self.count]
+            .iter_mut()
+            .find(|heap| address >= heap.bottom() as usize && address < heap.top() as usize)
+            .expect("pointer does not belong to any heap region");
+
+        heap.deallocate(ptr, layout);
+    }
+
+    pub fn stats(&self, region: usize) -> Option<RegionStats> {
+        let heap = self.heaps[..self.count].get(region)?;
+
+        Some(RegionStats {
+            start: heap.bottom() as usize,
+            size: heap.size(),
+            used: heap.used(),
+            free: heap.free(),
+        })
+    }
+
+    pub fn largest_free_block(&mut self) -> usize {
+        self.heaps[..self.count]
+            .iter_mut()
+            .map(largest_free_block)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &LLHeap> {
+        self.heaps[..self.count].iter()
+    }
+}
+
+// `linked_list_allocator` does not expose its hole list, so the largest block is found by
+// probing allocations. Freeing a probe merges the hole back, leaving the list unchanged.
+fn largest_free_block(heap: &mut LLHeap) -> usize {
+    let mut low = 0;
+    let mut high = heap.free() / PROBE_ALIGN;
+
+    while low < high {
+        let middle = (low + high).div_ceil(2);
+
+        if probe(heap, middle * PROBE_ALIGN) {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+
+    // A hole is only split if the remainder fits a hole header, so a hole fits whole while
+    // anything less than a header smaller does not. The search above stops up to a header
+    // short of the largest hole, the sizes in between are probed from the top.
+    (1..=HOLE_HEADER / PROBE_ALIGN)
+        .rev()
+        .map(|extra| (low + extra) * PROBE_ALIGN)
+        .find(|&size| probe(heap, size))
+        .unwrap_or(low * PROBE_ALIGN)
+}
+
+fn probe(heap: &mut LLHeap, size: usize) -> bool {
+    let layout = Layout::from_size_align(size, PROBE_ALIGN).unwrap();
+
+    match heap.allocate_first_fit(layout) {
+        Ok(ptr) => {
+            unsafe { heap.deallocate(ptr, layout) };
+            true
+        }
+        Err(_) => false,
+    }
+}
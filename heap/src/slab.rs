@@ -0,0 +1,136 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+use crate::regions::Regions;
+
+pub const SIZE_CLASSES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+pub const SLAB_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlabStats {
+    pub block_size: usize,
+    pub blocks: usize,
+    pub free_blocks: usize,
+}
+
+#[derive(Clone, Copy)]
+struct SlabCache {
+    free_list: Option<NonNull<FreeBlock>>,
+    stats: SlabStats,
+}
+
+struct FreeBlock {
+    next: Option<NonNull<FreeBlock>>,
+}
+
+pub(crate) struct SlabCaches {
+    caches: [SlabCache; SIZE_CLASSES.len()],
+}
+
+impl SlabCaches {
+    pub const fn new() -> Self {
+        let mut caches = [SlabCache {
+            free_list: None,
+            stats: SlabStats {
+                block_size: 0,
+                blocks: 0,
+                free_blocks: 0,
+            },
+        }; SIZE_CLASSES.len()];
+        let mut i = 0;
+
+        while i < SIZE_CLASSES.len() {
+            caches[i].stats.block_size = SIZE_CLASSES[i];
+            i += 1;
+        }
+
+        Self { caches }
+    }
+
+    pub fn class(layout: Layout) -> Option<usize> {
+        SIZE_CLASSES
+            .iter()
+            .position(|&size| layout.size() <= size && layout.align() <= size)
+    }
+
+    pub fn allocate(&mut self, class: usize, regions: &mut Regions) -> Option<NonNull<u8>> {
+        let cache = &mut self.caches[class];
+
+        // When a whole slab doesn't fit anymore, a single block still may.
+        if cache.free_list.is_none() && cache.refill(regions, SLAB_SIZE).is_none() {
+            cache.refill(regions, cache.stats.block_size)?;
+        }
+
+        let block = cache.free_list?;
+
+        cache.free_list = unsafe { block.as_ref().next };
+        cache.stats.free_blocks -= 1;
+
+        Some(block.cast())
+    }
+
+    pub unsafe fn deallocate(&mut self, class: usize, ptr: NonNull<u8>) {
+        self.caches[class].push(ptr.cast());
+    }
+
+    // Every cached block goes back to its region on its own, the allocator merges neighbours so
+    // slabs with no live blocks become whole pages again. Returns the number of bytes released.
+    pub fn trim(&mut self, regions: &mut Regions) -> usize {
+        let mut released = 0;
+
+        for cache in &mut self.caches {
+            let block_size = cache.stats.block_size;
+            let layout = Layout::from_size_align(block_size, block_size).unwrap();
+
+            while let Some(block) = cache.free_list {
+                unsafe {
+                    cache.free_list = block.as_ref().next;
+                    regions.deallocate(block.cast(), layout);
+                }
+
+                released += block_size;
+            }
+
+            cache.stats.blocks -= cache.stats.free_blocks;
+            cache.stats.free_blocks = 0;
+        }
+
+        released
+    }
+
+    pub fn stats(&self) -> [SlabStats; SIZE_CLASSES.len()] {
+        self.caches.map(|cache| cache.stats)
+    }
+
+    pub fn cached(&self) -> usize {
+        self.caches
+            .iter()
+            .map(|cache| cache.stats.free_blocks * cache.stats.block_size)
+            .sum()
+    }
+}
+
+impl SlabCache {
+    fn refill(&mut self, regions: &mut Regions, size: usize) -> Option<()> {
+        let block_size = self.stats.block_size;
+        let layout = Layout::from_size_align(size.max(block_size), block_size).unwrap();
+        let slab = regions.allocate_first_fit(layout)?;
+
+        for i in 0..layout.size() / block_size {
+            unsafe {
+                self.push(NonNull::new_unchecked(
+                    slab.as_ptr().add(i * block_size).cast(),
+                ))
+            };
+        }
+
+        self.stats.blocks += layout.size() / block_size;
+
+        Some(())
+    }
+
+    unsafe fn push(&mut self, mut block: NonNull<FreeBlock>) {
+        block.as_mut().next = self.free_list;
+        self.free_list = Some(block);
+        self.stats.free_blocks += 1;
+    }
+}
@@ -0,0 +1,35 @@
+// Shared by the heap tests, not every test file uses all of it.
+#![allow(dead_code)]
+
+use std::alloc::Layout;
+
+use heap::Heap;
+
+// Page aligned, like the memory the kernel gives the heap.
+#[repr(align(4096))]
+pub struct Memory<const SIZE: usize>(pub [u8; SIZE]);
+
+impl<const SIZE: usize> Memory<SIZE> {
+    pub const fn new() -> Self {
+        Self([0; SIZE])
+    }
+
+    pub fn at(&mut self, offset: usize) -> *mut u8 {
+        self.0[offset..].as_mut_ptr()
+    }
+}
+
+// An empty heap over all of `memory`.
+pub fn heap<const SIZE: usize>(memory: &mut Memory<SIZE>) -> Heap {
+    init(Heap::empty(), memory)
+}
+
+pub fn init<const SIZE: usize>(heap: Heap, memory: &mut Memory<SIZE>) -> Heap {
+    unsafe { heap.init(memory.at(0), SIZE) };
+
+    heap
+}
+
+pub fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 8).unwrap()
+}
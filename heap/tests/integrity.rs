@@ -0,0 +1,130 @@
+mod common;
+
+use std::{alloc::GlobalAlloc, cell::Cell};
+
+use common::layout;
+use heap::{CorruptionKind, Heap, IntegrityError, ALLOC_POISON, CANARY_SIZE, FREE_POISON};
+
+const HEAP_SIZE: usize = 16 * 1024;
+
+type Memory = common::Memory<HEAP_SIZE>;
+
+thread_local! {
+    static REPORTED: Cell<Option<IntegrityError>> = const { Cell::new(None) };
+}
+
+fn report(error: &IntegrityError) {
+    REPORTED.with(|reported| reported.set(Some(*error)));
+}
+
+fn reported() -> Option<CorruptionKind> {
+    REPORTED
+        .with(|reported| reported.take())
+        .map(|error| error.kind)
+}
+
+fn heap(memory: &mut Memory) -> Heap {
+    let heap = common::heap(memory);
+
+    heap.set_integrity_handler(report);
+
+    heap
+}
+
+#[test]
+fn poisons_allocated_and_freed_memory() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(64)) };
+    let data = unsafe { core::slice::from_raw_parts(ptr, 64) };
+
+    assert!(data.iter().all(|&b| b == ALLOC_POISON));
+
+    unsafe { heap.dealloc(ptr, layout(64)) };
+
+    let data = unsafe { core::slice::from_raw_parts(ptr, 64) };
+
+    assert!(data.iter().all(|&b| b == FREE_POISON));
+    assert_eq!(reported(), None);
+    assert_eq!(heap.stats().allocated, 0);
+    assert_eq!(heap.stats().leaked, 0);
+}
+
+#[test]
+fn overrun_is_reported_and_leaked() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(32)) };
+    let other = unsafe { heap.alloc(layout(32)) };
+
+    unsafe { ptr.add(32).write(0) };
+
+    assert_eq!(
+        heap.check().map_err(|error| error.kind),
+        Err(CorruptionKind::BackCanary)
+    );
+
+    unsafe { heap.dealloc(ptr, layout(32)) };
+
+    let stats = heap.stats();
+
+    assert_eq!(reported(), Some(CorruptionKind::BackCanary));
+    assert_eq!(stats.allocated, 32);
+    assert_eq!(stats.allocations, 1);
+    assert_eq!(stats.leaked, 32);
+    assert_eq!(stats.free(), HEAP_SIZE - 64);
+
+    // The leaked block left the live list, the heap is consistent again.
+    assert_eq!(heap.check(), Ok(()));
+
+    unsafe { heap.dealloc(other, layout(32)) };
+
+    assert_eq!(reported(), None);
+    assert_eq!(heap.stats().allocated, 0);
+    assert_eq!(heap.stats().leaked, 32);
+}
+
+#[test]
+fn underrun_is_reported() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(16)) };
+
+    unsafe { ptr.sub(CANARY_SIZE).write(0) };
+    unsafe { heap.dealloc(ptr, layout(16)) };
+
+    assert_eq!(reported(), Some(CorruptionKind::FrontCanary));
+    assert_eq!(heap.stats().leaked, 16);
+}
+
+#[test]
+fn size_mismatch_is_reported() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(48)) };
+
+    unsafe { heap.dealloc(ptr, layout(40)) };
+
+    assert_eq!(reported(), Some(CorruptionKind::SizeMismatch));
+    assert_eq!(heap.stats().leaked, 40);
+}
+
+#[test]
+fn double_free_is_reported_and_not_counted() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(64)) };
+    let live = unsafe { heap.alloc(layout(64)) };
+
+    unsafe { heap.dealloc(ptr, layout(64)) };
+
+    let stats = heap.stats();
+
+    unsafe { heap.dealloc(ptr, layout(64)) };
+
+    assert_eq!(reported(), Some(CorruptionKind::DoubleFree));
+    assert_eq!(heap.stats(), stats);
+    assert_eq!(heap.stats().leaked, 0);
+
+    unsafe { heap.dealloc(live, layout(64)) };
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+use common::layout;
+use heap::{Heap, HeapError, HeapStats, MAX_RECLAIMERS};
+
+const HEAP_SIZE: usize = 16 * 1024;
+const BALLAST: usize = 12 * 1024;
+
+type Memory = common::Memory<HEAP_SIZE>;
+
+// Handlers and reclaimers are plain functions, so every test gets its own heap and counters.
+static RECLAIM_HEAP: Heap = Heap::empty();
+static mut RECLAIM_MEMORY: Memory = Memory::new();
+static BALLAST_PTR: AtomicPtr<u8> = AtomicPtr::new(null_mut());
+static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+static RECLAIM_HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+static FAIL_HEAP: Heap = Heap::empty();
+static mut FAIL_MEMORY: Memory = Memory::new();
+static EMPTY_RECLAIMS: AtomicUsize = AtomicUsize::new(0);
+static FAILED_SIZE: AtomicUsize = AtomicUsize::new(0);
+static FAILED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn free_ballast(_: Layout) -> usize {
+    let ptr = BALLAST_PTR.swap(null_mut(), Ordering::SeqCst);
+
+    if ptr.is_null() {
+        return 0;
+    }
+
+    unsafe { RECLAIM_HEAP.dealloc(ptr, layout(BALLAST)) };
+    RECLAIMED.fetch_add(1, Ordering::SeqCst);
+
+    BALLAST
+}
+
+fn count_reclaim_handled(_: Layout, _: &HeapStats) {
+    RECLAIM_HANDLED.fetch_add(1, Ordering::SeqCst);
+}
+
+fn reclaim_nothing(_: Layout) -> usize {
+    EMPTY_RECLAIMS.fetch_add(1, Ordering::SeqCst);
+
+    0
+}
+
+fn record_failure(layout: Layout, stats: &HeapStats) {
+    FAILED_SIZE.store(layout.size(), Ordering::SeqCst);
+    FAILED_COUNT.store(stats.failed_allocations, Ordering::SeqCst);
+}
+
+#[test]
+fn reclaimer_frees_memory_and_allocation_is_retried() {
+    unsafe { RECLAIM_HEAP.init((&raw mut RECLAIM_MEMORY.0).cast(), HEAP_SIZE) };
+    RECLAIM_HEAP.set_oom_handler(count_reclaim_handled);
+    RECLAIM_HEAP.register_reclaimer(free_ballast).unwrap();
+
+    let ballast = unsafe { RECLAIM_HEAP.alloc(layout(BALLAST)) };
+
+    assert!(!ballast.is_null());
+    BALLAST_PTR.store(ballast, Ordering::SeqCst);
+
+    let ptr = unsafe { RECLAIM_HEAP.alloc(layout(8 * 1024)) };
+
+    assert!(!ptr.is_null());
+    assert_eq!(RECLAIMED.load(Ordering::SeqCst), 1);
+    assert_eq!(RECLAIM_HANDLED.load(Ordering::SeqCst), 0);
+    assert_eq!(RECLAIM_HEAP.stats().failed_allocations, 0);
+    assert_eq!(RECLAIM_HEAP.stats().allocated, 8 * 1024);
+
+    // Nothing is left to reclaim, so the next failure reaches the handler.
+    assert!(unsafe { RECLAIM_HEAP.alloc(layout(12 * 1024)) }.is_null());
+    assert_eq!(RECLAIMED.load(Ordering::SeqCst), 1);
+    assert_eq!(RECLAIM_HANDLED.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn handler_sees_failure_after_reclaimers_give_up() {
+    unsafe { FAIL_HEAP.init((&raw mut FAIL_MEMORY.0).cast(), HEAP_SIZE) };
+    FAIL_HEAP.set_oom_handler(record_failure);
+    FAIL_HEAP.register_reclaimer(reclaim_nothing).unwrap();
+
+    assert!(unsafe { FAIL_HEAP.alloc(layout(HEAP_SIZE * 2)) }.is_null());
+    assert_eq!(EMPTY_RECLAIMS.load(Ordering::SeqCst), 1);
+    assert_eq!(FAILED_SIZE.load(Ordering::SeqCst), HEAP_SIZE * 2);
+    assert_eq!(FAILED_COUNT.load(Ordering::SeqCst), 1);
+
+    let ptr = unsafe { FAIL_HEAP.alloc(layout(64)) };
+
+    assert!(!ptr.is_null());
+    assert_eq!(EMPTY_RECLAIMS.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn reclaimer_slots_are_limited() {
+    let heap = Heap::empty();
+
+    for _ in 0..MAX_RECLAIMERS {
+        heap.register_reclaimer(reclaim_nothing).unwrap();
+    }
+
+    assert_eq!(
+        heap.register_reclaimer(reclaim_nothing),
+        Err(HeapError::TooManyReclaimers)
+    );
+}
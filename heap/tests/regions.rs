@@ -0,0 +1,140 @@
+mod common;
+
+use std::alloc::GlobalAlloc;
+
+use common::layout;
+use heap::{Heap, HeapError, MAX_REGIONS};
+
+const MEMORY_SIZE: usize = 32 * 1024;
+
+type Memory = common::Memory<MEMORY_SIZE>;
+
+#[test]
+fn allocates_across_regions() {
+    let mut memory = Memory::new();
+    let heap = Heap::empty();
+
+    unsafe {
+        heap.init(memory.at(0), 4096);
+        assert_eq!(heap.add_region(memory.at(8192), 8192), Ok(1));
+    }
+
+    assert_eq!(heap.regions(), 2);
+    assert_eq!(heap.stats().size, 4096 + 8192);
+
+    // Too big for the first region, so it has to come from the second one.
+    let ptr = unsafe { heap.alloc(layout(6000)) };
+    let region = heap.region_stats(1).unwrap();
+
+    assert!(!ptr.is_null());
+    assert_eq!(region.start, memory.at(8192) as usize);
+    assert!(region.used >= 6000);
+    assert_eq!(heap.region_stats(0).unwrap().used, 0);
+    assert_eq!(heap.region_stats(2), None);
+
+    unsafe { heap.dealloc(ptr, layout(6000)) };
+
+    assert_eq!(heap.region_stats(1).unwrap().used, 0);
+}
+
+#[test]
+fn extends_region() {
+    let mut memory = Memory::new();
+    let heap = Heap::empty();
+
+    unsafe { heap.init(memory.at(0), 8192) };
+
+    assert!(unsafe { heap.alloc(layout(12 * 1024)) }.is_null());
+
+    unsafe { heap.extend_region(0, 8192).unwrap() };
+
+    assert_eq!(heap.stats().size, 16 * 1024);
+    assert!(!unsafe { heap.alloc(layout(12 * 1024)) }.is_null());
+}
+
+#[test]
+fn rejects_overlapping_and_invalid_regions() {
+    let mut memory = Memory::new();
+    let heap = Heap::empty();
+
+    unsafe {
+        heap.init(memory.at(4096), 4096);
+
+        assert_eq!(
+            heap.add_region(memory.at(0), 8192),
+            Err(HeapError::OverlappingRegion)
+        );
+        assert_eq!(
+            heap.add_region(memory.at(6144), 4096),
+            Err(HeapError::OverlappingRegion)
+        );
+        assert_eq!(
+            heap.add_region(core::ptr::null_mut(), 4096),
+            Err(HeapError::InvalidRegion)
+        );
+        assert_eq!(
+            heap.add_region(memory.at(0), 4),
+            Err(HeapError::InvalidRegion)
+        );
+
+        assert_eq!(heap.add_region(memory.at(8192), 4096), Ok(1));
+        assert_eq!(
+            heap.extend_region(0, 1024),
+            Err(HeapError::OverlappingRegion)
+        );
+        assert_eq!(heap.extend_region(2, 1024), Err(HeapError::InvalidRegion));
+        assert_eq!(heap.extend_region(1, 4096), Ok(()));
+    }
+
+    assert_eq!(heap.regions(), 2);
+    assert_eq!(heap.stats().size, 4096 * 3);
+}
+
+#[test]
+fn limits_number_of_regions() {
+    let mut memory = Memory::new();
+    let heap = Heap::empty();
+
+    for region in 0..MAX_REGIONS {
+        assert_eq!(
+            unsafe { heap.add_region(memory.at(region * 1024), 1024) },
+            Ok(region)
+        );
+    }
+
+    assert_eq!(
+        unsafe { heap.add_region(memory.at(MAX_REGIONS * 1024), 1024) },
+        Err(HeapError::TooManyRegions)
+    );
+}
+
+#[test]
+fn finds_largest_free_block() {
+    let mut memory = Memory::new();
+    let heap = Heap::empty();
+
+    unsafe {
+        heap.init(memory.at(0), 8192);
+        heap.add_region(memory.at(16 * 1024), 4096).unwrap();
+    }
+
+    assert_eq!(heap.largest_free_block(), 8192);
+
+    let first = unsafe { heap.alloc(layout(1024)) };
+    let second = unsafe { heap.alloc(layout(1024)) };
+    let third = unsafe { heap.alloc(layout(1024)) };
+    let tail = heap.region_stats(0).unwrap().free;
+
+    assert!(!first.is_null() && !second.is_null() && !third.is_null());
+    assert_eq!(heap.largest_free_block(), tail);
+
+    // The freed block in the middle is smaller than the tail, the probes leave it untouched.
+    unsafe { heap.dealloc(second, layout(1024)) };
+
+    let stats = heap.stats();
+
+    assert_eq!(heap.largest_free_block(), tail);
+    assert_eq!(heap.largest_free_block(), tail);
+    assert_eq!(heap.stats(), stats);
+    assert!(heap.region_stats(0).unwrap().free > tail);
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::alloc::GlobalAlloc;
+
+use common::layout;
+use heap::{Heap, SlabStats, SIZE_CLASSES, SLAB_SIZE};
+
+const HEAP_SIZE: usize = 16 * 1024;
+
+type Memory = common::Memory<HEAP_SIZE>;
+
+fn heap(memory: &mut Memory) -> Heap {
+    common::init(Heap::with_slabs(), memory)
+}
+
+fn used_classes(heap: &Heap) -> Vec<SlabStats> {
+    heap.slab_stats()
+        .unwrap()
+        .into_iter()
+        .filter(|stats| stats.blocks > 0)
+        .collect()
+}
+
+#[test]
+fn small_allocations_come_from_one_slab() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let first = unsafe { heap.alloc(layout(24)) };
+    let second = unsafe { heap.alloc(layout(24)) };
+    let classes = used_classes(&heap);
+
+    assert!(!first.is_null() && !second.is_null());
+    assert_eq!(classes.len(), 1);
+    assert!(classes[0].block_size >= 24);
+    assert_eq!(classes[0].blocks, SLAB_SIZE / classes[0].block_size);
+    assert_eq!(classes[0].free_blocks, classes[0].blocks - 2);
+
+    unsafe { heap.dealloc(first, layout(24)) };
+
+    assert_eq!(used_classes(&heap)[0].free_blocks, classes[0].blocks - 1);
+    assert_eq!(heap.stats().allocated, 24);
+    assert_eq!(
+        heap.stats().cached,
+        (classes[0].blocks - 1) * classes[0].block_size
+    );
+
+    unsafe { heap.dealloc(second, layout(24)) };
+}
+
+#[test]
+fn large_allocations_bypass_slabs() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let size = SIZE_CLASSES[SIZE_CLASSES.len() - 1] + 1;
+    let ptr = unsafe { heap.alloc(layout(size)) };
+
+    assert!(!ptr.is_null());
+    assert!(used_classes(&heap).is_empty());
+    assert_eq!(heap.stats().allocated, size);
+
+    unsafe { heap.dealloc(ptr, layout(size)) };
+
+    assert_eq!(heap.stats().cached, 0);
+}
+
+#[test]
+fn falls_back_to_single_block_when_slab_does_not_fit() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let filler = unsafe { heap.alloc(layout(HEAP_SIZE - SLAB_SIZE + 1024)) };
+    let ptr = unsafe { heap.alloc(layout(16)) };
+    let classes = used_classes(&heap);
+
+    assert!(!filler.is_null() && !ptr.is_null());
+    assert_eq!(classes.len(), 1);
+    assert_eq!(classes[0].blocks, 1);
+    assert_eq!(classes[0].free_blocks, 0);
+}
+
+#[test]
+fn cached_blocks_are_trimmed_on_oom() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let blocks: Vec<_> = (0..20).map(|_| unsafe { heap.alloc(layout(16)) }).collect();
+
+    for &block in &blocks {
+        assert!(!block.is_null());
+        unsafe { heap.dealloc(block, layout(16)) };
+    }
+
+    assert_eq!(heap.stats().cached, SLAB_SIZE);
+
+    // Only fits once the slab page is back in the region.
+    let large = layout(HEAP_SIZE - SLAB_SIZE + 1024);
+    let ptr = unsafe { heap.alloc(large) };
+
+    assert!(!ptr.is_null());
+    assert_eq!(heap.stats().cached, 0);
+    assert_eq!(heap.stats().failed_allocations, 0);
+    assert!(used_classes(&heap).is_empty());
+
+    unsafe { heap.dealloc(ptr, large) };
+}
+
+#[test]
+fn trimming_keeps_live_blocks() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let live = unsafe { heap.alloc(layout(16)) };
+    let freed = unsafe { heap.alloc(layout(16)) };
+
+    unsafe { heap.dealloc(freed, layout(16)) };
+
+    let class = used_classes(&heap)[0];
+
+    assert_eq!(heap.trim_slabs(), (class.blocks - 1) * class.block_size);
+    assert_eq!(used_classes(&heap)[0].blocks, 1);
+    assert_eq!(used_classes(&heap)[0].free_blocks, 0);
+    assert_eq!(heap.trim_slabs(), 0);
+
+    unsafe { heap.dealloc(live, layout(16)) };
+
+    assert_eq!(used_classes(&heap)[0].free_blocks, 1);
+    assert_eq!(heap.trim_slabs(), class.block_size);
+    assert!(used_classes(&heap).is_empty());
+}
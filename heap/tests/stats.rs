@@ -0,0 +1,66 @@
+mod common;
+
+use std::alloc::GlobalAlloc;
+
+use common::{heap, layout};
+
+const HEAP_SIZE: usize = 64 * 1024;
+
+type Memory = common::Memory<HEAP_SIZE>;
+
+#[test]
+fn counts_allocations_and_peak() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+
+    assert_eq!(heap.stats().size, HEAP_SIZE);
+    assert_eq!(heap.stats().allocated, 0);
+
+    let small = unsafe { heap.alloc(layout(100)) };
+    let large = unsafe { heap.alloc(layout(200)) };
+    let stats = heap.stats();
+
+    assert!(!small.is_null() && !large.is_null());
+    assert_eq!(stats.allocated, 300);
+    assert_eq!(stats.allocations, 2);
+    assert_eq!(stats.total_allocations, 2);
+    assert_eq!(stats.peak, 300);
+    assert_eq!(stats.free(), HEAP_SIZE - 300);
+
+    unsafe { heap.dealloc(large, layout(200)) };
+
+    let other = unsafe { heap.alloc(layout(50)) };
+    let stats = heap.stats();
+
+    assert_eq!(stats.allocated, 150);
+    assert_eq!(stats.allocations, 2);
+    assert_eq!(stats.total_allocations, 3);
+    assert_eq!(stats.peak, 300);
+
+    unsafe {
+        heap.dealloc(small, layout(100));
+        heap.dealloc(other, layout(50));
+    }
+
+    assert_eq!(heap.stats().allocated, 0);
+    assert_eq!(heap.stats().allocations, 0);
+}
+
+#[test]
+fn counts_failed_allocations() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(100)) };
+
+    assert!(unsafe { heap.alloc(layout(HEAP_SIZE)) }.is_null());
+    assert!(unsafe { heap.alloc(layout(HEAP_SIZE * 2)) }.is_null());
+
+    let stats = heap.stats();
+
+    assert_eq!(stats.failed_allocations, 2);
+    assert_eq!(stats.allocated, 100);
+    assert_eq!(stats.total_allocations, 1);
+    assert!(stats.to_string().contains("failed: 2"));
+
+    unsafe { heap.dealloc(ptr, layout(100)) };
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::{alloc::GlobalAlloc, fmt::Write};
+
+use common::{heap, layout};
+use heap::{Heap, TRACKED_ALLOCATIONS};
+
+const HEAP_SIZE: usize = 64 * 1024;
+
+type Memory = common::Memory<HEAP_SIZE>;
+
+fn dump(heap: &Heap) -> String {
+    let mut out = String::new();
+
+    heap.dump_allocations(&mut out).unwrap();
+
+    out
+}
+
+// Allocates from the heap it is writing the dump of, a few times so it doesn't feed itself.
+struct AllocatingSink<'h> {
+    heap: &'h Heap,
+    lines: Vec<String>,
+    ptrs: Vec<*mut u8>,
+}
+
+impl Write for AllocatingSink<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        if self.ptrs.len() < 4 {
+            self.ptrs.push(unsafe { self.heap.alloc(layout(16)) });
+        }
+
+        self.lines.push(s.to_string());
+
+        Ok(())
+    }
+}
+
+#[test]
+fn tags_allocations_with_the_caller() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let untagged = unsafe { heap.alloc(layout(100)) };
+    let (tagged, line) = {
+        let (_tag, line) = (heap.tag(), line!());
+
+        (unsafe { heap.alloc(layout(200)) }, line)
+    };
+    let after = unsafe { heap.alloc(layout(300)) };
+    let out = dump(&heap);
+    let lines: Vec<&str> = out.lines().collect();
+    let at = format!(", at: {}:{line}:", file!());
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        format!("#0 {:#016X} size: 100, align: 8", untagged as usize)
+    );
+    assert!(lines[1].starts_with(&format!("#1 {:#016X} size: 200, align: 8", tagged as usize)));
+    assert!(lines[1].contains(&at));
+    // The tag ends with its guard.
+    assert!(lines[2].starts_with("#2 ") && !lines[2].contains("at:"));
+    assert_eq!(lines[3], "outstanding: 3, untracked: 0");
+
+    unsafe {
+        heap.dealloc(untagged, layout(100));
+        heap.dealloc(tagged, layout(200));
+        heap.dealloc(after, layout(300));
+    }
+}
+
+#[test]
+fn nested_tags_restore_the_outer_one() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let (_outer, outer) = (heap.tag(), line!());
+
+    {
+        let (_inner, inner) = (heap.tag(), line!());
+        let ptr = unsafe { heap.alloc(layout(8)) };
+
+        assert!(dump(&heap).contains(&format!("{}:{inner}:", file!())));
+        unsafe { heap.dealloc(ptr, layout(8)) };
+    }
+
+    let ptr = unsafe { heap.alloc(layout(8)) };
+
+    assert!(dump(&heap).contains(&format!("{}:{outer}:", file!())));
+    unsafe { heap.dealloc(ptr, layout(8)) };
+}
+
+#[test]
+fn frees_are_untracked() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let first = unsafe { heap.alloc(layout(64)) };
+    let second = unsafe { heap.alloc(layout(64)) };
+
+    unsafe { heap.dealloc(first, layout(64)) };
+
+    let out = dump(&heap);
+
+    assert!(!out.contains("#0 "));
+    assert!(out.starts_with(&format!("#1 {:#016X}", second as usize)));
+    assert!(out.ends_with("outstanding: 1, untracked: 0\n"));
+
+    unsafe { heap.dealloc(second, layout(64)) };
+    assert_eq!(dump(&heap), "outstanding: 0, untracked: 0\n");
+}
+
+#[test]
+fn counts_what_does_not_fit() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptrs: Vec<*mut u8> = (0..TRACKED_ALLOCATIONS + 2)
+        .map(|_| unsafe { heap.alloc(layout(8)) })
+        .collect();
+
+    assert!(ptrs.iter().all(|ptr| !ptr.is_null()));
+    assert!(dump(&heap).ends_with(&format!(
+        "outstanding: {TRACKED_ALLOCATIONS}, untracked: 2\n"
+    )));
+
+    for ptr in ptrs {
+        unsafe { heap.dealloc(ptr, layout(8)) };
+    }
+
+    assert_eq!(dump(&heap), "outstanding: 0, untracked: 0\n");
+}
+
+#[test]
+fn dump_to_an_allocating_sink() {
+    let mut memory = Memory::new();
+    let heap = heap(&mut memory);
+    let ptr = unsafe { heap.alloc(layout(32)) };
+    let mut sink = AllocatingSink {
+        heap: &heap,
+        lines: Vec::new(),
+        ptrs: Vec::new(),
+    };
+
+    heap.dump_allocations(&mut sink).unwrap();
+
+    let out = sink.lines.concat();
+
+    assert!(out.starts_with(&format!("#0 {:#016X} size: 32, align: 8\n", ptr as usize)));
+    // Its own allocations take later slots and make it into the dump.
+    assert_eq!(out.matches("size: 16, align: 8\n").count(), 4);
+    assert!(out.ends_with("outstanding: 5, untracked: 0\n"));
+    assert!(sink.ptrs.iter().all(|ptr| !ptr.is_null()));
+
+    for ptr in sink.ptrs {
+        unsafe { heap.dealloc(ptr, layout(16)) };
+    }
+
+    unsafe { heap.dealloc(ptr, layout(32)) };
+    assert_eq!(dump(&heap), "outstanding: 0, untracked: 0\n");
+}
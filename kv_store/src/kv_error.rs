@@ -0,0 +1,32 @@
+use core::fmt::Display;
+
+use flash::FlashError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KvError {
+    Flash(FlashError),
+    TooFewSectors,
+    InvalidKey,
+    ValueTooLarge,
+    BufferTooSmall,
+    Full,
+}
+
+impl Display for KvError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            KvError::Flash(error) => write!(f, "flash error: {error}"),
+            KvError::TooFewSectors => f.write_str("key-value store needs at least two sectors"),
+            KvError::InvalidKey => f.write_str("invalid key"),
+            KvError::ValueTooLarge => f.write_str("value is too large"),
+            KvError::BufferTooSmall => f.write_str("buffer is too small for the value"),
+            KvError::Full => f.write_str("key-value store is full"),
+        }
+    }
+}
+
+impl From<FlashError> for KvError {
+    fn from(value: FlashError) -> Self {
+        KvError::Flash(value)
+    }
+}
@@ -0,0 +1,12 @@
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KvStats {
+    pub sectors: usize,
+    pub free_sectors: usize,
+    // Bytes taken by records, including overwritten ones that GC hasn't reclaimed yet.
+    pub used: usize,
+    pub live: usize,
+    pub keys: usize,
+    // Bumped on every sector switch. A switch erases the sector it opens and, once the ring has
+    // wrapped, the collected one as well, so there are up to two erases per step.
+    pub sequence: u32,
+}
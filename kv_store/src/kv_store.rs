@@ -0,0 +1,556 @@
+use core::ops::Range;
+
+use crc32::crc32;
+use flash::{FlashError, FlashStorage, ERASED_BYTE, SECTOR_SIZE};
+
+use crate::{
+    record_header::{RecordHeader, RECORD_HEADER_LENGTH},
+    sector_header::{SectorHeader, SECTOR_HEADER_LENGTH},
+    KvError, KvStats, MAX_KEY_LENGTH, MAX_VALUE_LENGTH,
+};
+
+const CHUNK: usize = 64;
+// Even a sector full of the smallest records fits.
+const MAX_SECTOR_RECORDS: usize = (SECTOR_SIZE - SECTOR_HEADER_LENGTH) / (RECORD_HEADER_LENGTH + 1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Location {
+    sector: usize,
+    offset: usize,
+    header: RecordHeader,
+}
+
+// A record of the sector being collected, `live` until a newer record of its key turns up.
+#[derive(Debug, Clone, Copy, Default)]
+struct Candidate {
+    offset: u16,
+    key_length: u8,
+    live: bool,
+    hash: u32,
+}
+
+enum Slot {
+    Record(RecordHeader),
+    Free,
+    // A torn record or the end of the sector, nothing more can be appended here.
+    Closed,
+}
+
+// A log-structured key-value store. Records are appended to the active sector and sectors are
+// used in ring order, so every sector gets erased once per lap around the ring. Whenever a new
+// sector is opened the oldest one is garbage collected into it: its live records are copied
+// over and it is erased, which keeps one sector free at all times.
+//
+// Every record carries a CRC, so a record torn by a power cut is ignored and the previous
+// value of its key stays visible. A GC interrupted by a power cut is redone on mount.
+pub struct KvStore<F: FlashStorage> {
+    flash: F,
+    first: usize,
+    count: usize,
+    active: usize,
+    offset: usize,
+    sequence: u32,
+}
+
+impl<F: FlashStorage> KvStore<F> {
+    pub fn mount(flash: F, sectors: Range<usize>) -> Result<Self, KvError> {
+        if sectors.len() < 2 {
+            return Err(KvError::TooFewSectors);
+        }
+
+        if sectors.end > flash.sector_count() {
+            return Err(KvError::Flash(FlashError::OutOfRange));
+        }
+
+        let mut store = Self {
+            flash,
+            first: sectors.start,
+            count: sectors.len(),
+            active: 0,
+            offset: SECTOR_HEADER_LENGTH,
+            sequence: 0,
+        };
+        let mut newest: Option<(usize, u32)> = None;
+
+        for sector in 0..store.count {
+            if let Some(header) = store.sector_header(sector)? {
+                if newest.is_none_or(|(_, sequence)| header.sequence > sequence) {
+                    newest = Some((sector, header.sequence));
+                }
+            }
+        }
+
+        match newest {
+            None => store.open(0, 0)?,
+            Some((sector, sequence)) => {
+                store.active = sector;
+                store.sequence = sequence;
+
+                let victim = store.next(sector);
+
+                // The sector after the active one is only in use while it is being collected,
+                // and so far the active sector holds nothing but copies made by the GC.
+                if store.sector_header(victim)?.is_some() {
+                    store.open(sector, sequence)?;
+                    store.collect(victim)?;
+                }
+
+                store.offset = store.end_of_log(store.active)?;
+            }
+        }
+
+        Ok(store)
+    }
+
+    pub fn format(mut flash: F, sectors: Range<usize>) -> Result<Self, KvError> {
+        if sectors.end > flash.sector_count() {
+            return Err(KvError::Flash(FlashError::OutOfRange));
+        }
+
+        for sector in sectors.clone() {
+            flash.erase_sector(sector)?;
+        }
+
+        Self::mount(flash, sectors)
+    }
+
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    // Returns the length of the value, or `None` if the key isn't set.
+    pub fn get(&self, key: &str, buffer: &mut [u8]) -> Result<Option<usize>, KvError> {
+        let key = Self::validate_key(key)?;
+
+        let Some(location) = self
+            .find(key)?
+            .filter(|location| !location.header.tombstone)
+        else {
+            return Ok(None);
+        };
+
+        let length = location.header.value_length as usize;
+
+        if buffer.len() < length {
+            return Err(KvError::BufferTooSmall);
+        }
+
+        self.flash
+            .read(self.value_address(location), &mut buffer[..length])?;
+
+        Ok(Some(length))
+    }
+
+    pub fn contains(&self, key: &str) -> Result<bool, KvError> {
+        let key = Self::validate_key(key)?;
+
+        Ok(self
+            .find(key)?
+            .is_some_and(|location| !location.header.tombstone))
+    }
+
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), KvError> {
+        let key = Self::validate_key(key)?;
+
+        if value.len() > MAX_VALUE_LENGTH {
+            return Err(KvError::ValueTooLarge);
+        }
+
+        // Rewriting a setting with the value it already has shouldn't wear the flash.
+        if let Some(location) = self.find(key)? {
+            if !location.header.tombstone && self.value_equals(location, value)? {
+                return Ok(());
+            }
+        }
+
+        self.append(key, value, false)
+    }
+
+    // Returns whether the key was set.
+    pub fn remove(&mut self, key: &str) -> Result<bool, KvError> {
+        let key = Self::validate_key(key)?;
+
+        match self.find(key)? {
+            Some(location) if !location.header.tombstone => {
+                self.append(key, &[], true)?;
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn stats(&self) -> Result<KvStats, KvError> {
+        let mut stats = KvStats {
+            sectors: self.count,
+            sequence: self.sequence,
+            ..Default::default()
+        };
+
+        for sector in 0..self.count {
+            if self.sector_header(sector)?.is_none() {
+                stats.free_sectors += 1;
+            }
+        }
+
+        self.for_each_record(|location| {
+            stats.used += location.header.len();
+
+            if !location.header.tombstone && self.is_latest(location)? {
+                stats.live += location.header.len();
+                stats.keys += 1;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(stats)
+    }
+
+    fn validate_key(key: &str) -> Result<&[u8], KvError> {
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            Err(KvError::InvalidKey)
+        } else {
+            Ok(key.as_bytes())
+        }
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8], tombstone: bool) -> Result<(), KvError> {
+        let header = RecordHeader::new(key, value, tombstone);
+
+        for _ in 0..self.count {
+            if self.offset + header.len() <= SECTOR_SIZE {
+                let address = self.address(self.active, self.offset);
+
+                // The CRC is written together with the header, so the record only becomes
+                // valid once its last byte is on flash.
+                self.flash.write(address, &header.to_bytes())?;
+                self.flash.write(address + RECORD_HEADER_LENGTH, key)?;
+                self.flash
+                    .write(address + RECORD_HEADER_LENGTH + key.len(), value)?;
+                self.offset += header.len();
+
+                return Ok(());
+            }
+
+            self.open_next()?;
+        }
+
+        Err(KvError::Full)
+    }
+
+    fn open_next(&mut self) -> Result<(), KvError> {
+        let next = self.next(self.active);
+
+        self.open(next, self.sequence.wrapping_add(1))?;
+
+        let victim = self.next(next);
+
+        if self.sector_header(victim)?.is_some() {
+            self.collect(victim)?;
+        }
+
+        Ok(())
+    }
+
+    fn open(&mut self, sector: usize, sequence: u32) -> Result<(), KvError> {
+        self.flash.erase_sector(self.first + sector)?;
+        self.flash.write(
+            self.address(sector, 0),
+            &SectorHeader { sequence }.to_bytes(),
+        )?;
+
+        self.active = sector;
+        self.sequence = sequence;
+        self.offset = SECTOR_HEADER_LENGTH;
+
+        Ok(())
+    }
+
+    // The victim is always the oldest sector, so its tombstones have nothing left to hide
+    // and are dropped along with overwritten records.
+    fn collect(&mut self, victim: usize) -> Result<(), KvError> {
+        let mut candidates = [Candidate::default(); MAX_SECTOR_RECORDS];
+        let count = self.live_records(victim, &mut candidates)?;
+
+        for candidate in candidates[..count]
+            .iter()
+            .filter(|candidate| candidate.live)
+        {
+            let offset = candidate.offset as usize;
+
+            if let Slot::Record(header) = self.slot(victim, offset)? {
+                self.copy(Location {
+                    sector: victim,
+                    offset,
+                    header,
+                })?;
+            }
+        }
+
+        self.flash.erase_sector(self.first + victim)?;
+
+        Ok(())
+    }
+
+    fn copy(&mut self, location: Location) -> Result<(), KvError> {
+        let length = location.header.len();
+
+        if self.offset + length > SECTOR_SIZE {
+            return Err(KvError::Full);
+        }
+
+        let source = self.address(location.sector, location.offset);
+        let destination = self.address(self.active, self.offset);
+        let mut buffer = [0; CHUNK];
+        let mut copied = 0;
+
+        while copied < length {
+            let chunk = &mut buffer[..CHUNK.min(length - copied)];
+
+            self.flash.read(source + copied, chunk)?;
+            self.flash.write(destination + copied, chunk)?;
+            copied += chunk.len();
+        }
+
+        self.offset += length;
+
+        Ok(())
+    }
+
+    // Marks which records of the victim are still the latest of their key, in a single pass
+    // over the log. Keys are compared by hash first and only read back again when it matches.
+    fn live_records(
+        &self,
+        victim: usize,
+        candidates: &mut [Candidate; MAX_SECTOR_RECORDS],
+    ) -> Result<usize, KvError> {
+        let mut count = 0;
+        let mut key = [0; MAX_KEY_LENGTH];
+        let mut other = [0; MAX_KEY_LENGTH];
+
+        self.for_each_record(|location| {
+            let key = self.read_key(location, &mut key)?;
+            let hash = crc32(key);
+
+            for candidate in &mut candidates[..count] {
+                if candidate.live
+                    && candidate.hash == hash
+                    && candidate.key_length as usize == key.len()
+                {
+                    let offset = candidate.offset as usize + RECORD_HEADER_LENGTH;
+                    let other = &mut other[..key.len()];
+
+                    self.flash.read(self.address(victim, offset), other)?;
+                    candidate.live = other != key;
+                }
+            }
+
+            if location.sector == victim {
+                candidates[count] = Candidate {
+                    offset: location.offset as u16,
+                    key_length: location.header.key_length,
+                    live: !location.header.tombstone,
+                    hash,
+                };
+                count += 1;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(count)
+    }
+
+    fn find(&self, key: &[u8]) -> Result<Option<Location>, KvError> {
+        let mut latest = None;
+
+        self.for_each_record(|location| {
+            if self.key_equals(location, key)? {
+                latest = Some(location);
+            }
+
+            Ok(())
+        })?;
+
+        Ok(latest)
+    }
+
+    fn is_latest(&self, location: Location) -> Result<bool, KvError> {
+        let mut key = [0; MAX_KEY_LENGTH];
+        let key = self.read_key(location, &mut key)?;
+
+        Ok(self.find(key)? == Some(location))
+    }
+
+    // Visits records from the oldest to the newest.
+    fn for_each_record(
+        &self,
+        mut f: impl FnMut(Location) -> Result<(), KvError>,
+    ) -> Result<(), KvError> {
+        for i in 1..=self.count {
+            let sector = (self.active + i) % self.count;
+
+            if self.sector_header(sector)?.is_none() {
+                continue;
+            }
+
+            let mut offset = SECTOR_HEADER_LENGTH;
+
+            while let Slot::Record(header) = self.slot(sector, offset)? {
+                f(Location {
+                    sector,
+                    offset,
+                    header,
+                })?;
+                offset += header.len();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn end_of_log(&self, sector: usize) -> Result<usize, KvError> {
+        let mut offset = SECTOR_HEADER_LENGTH;
+
+        loop {
+            match self.slot(sector, offset)? {
+                Slot::Record(header) => offset += header.len(),
+                Slot::Free => return Ok(offset),
+                Slot::Closed => return Ok(SECTOR_SIZE),
+            }
+        }
+    }
+
+    fn slot(&self, sector: usize, offset: usize) -> Result<Slot, KvError> {
+        if offset + RECORD_HEADER_LENGTH > SECTOR_SIZE {
+            return Ok(Slot::Closed);
+        }
+
+        let address = self.address(sector, offset);
+        let mut bytes = [0; RECORD_HEADER_LENGTH];
+
+        self.flash.read(address, &mut bytes)?;
+
+        if bytes.iter().all(|&byte| byte == ERASED_BYTE) {
+            return Ok(Slot::Free);
+        }
+
+        let Some(header) = RecordHeader::from_bytes(&bytes) else {
+            return Ok(Slot::Closed);
+        };
+
+        if offset + header.len() > SECTOR_SIZE {
+            return Ok(Slot::Closed);
+        }
+
+        // Records are written one after another, so a power cut can only tear the last one in
+        // a sector. Any record with something written after it is complete.
+        if self.is_used(sector, offset + header.len())? {
+            return Ok(Slot::Record(header));
+        }
+
+        let mut crc = header.crc();
+        let mut buffer = [0; CHUNK];
+        let mut position = address + RECORD_HEADER_LENGTH;
+        let end = address + header.len();
+
+        while position < end {
+            let chunk = &mut buffer[..CHUNK.min(end - position)];
+
+            self.flash.read(position, chunk)?;
+            crc.update(chunk);
+            position += chunk.len();
+        }
+
+        if crc.finalize() == header.crc {
+            Ok(Slot::Record(header))
+        } else {
+            Ok(Slot::Closed)
+        }
+    }
+
+    fn is_used(&self, sector: usize, offset: usize) -> Result<bool, KvError> {
+        if offset + RECORD_HEADER_LENGTH > SECTOR_SIZE {
+            return Ok(false);
+        }
+
+        let mut bytes = [0; RECORD_HEADER_LENGTH];
+
+        self.flash.read(self.address(sector, offset), &mut bytes)?;
+
+        Ok(bytes.iter().any(|&byte| byte != ERASED_BYTE))
+    }
+
+    fn key_equals(&self, location: Location, key: &[u8]) -> Result<bool, KvError> {
+        if location.header.key_length as usize != key.len() {
+            return Ok(false);
+        }
+
+        let mut buffer = [0; MAX_KEY_LENGTH];
+
+        Ok(self.read_key(location, &mut buffer)? == key)
+    }
+
+    fn read_key<'b>(
+        &self,
+        location: Location,
+        buffer: &'b mut [u8; MAX_KEY_LENGTH],
+    ) -> Result<&'b [u8], KvError> {
+        let key = &mut buffer[..location.header.key_length as usize];
+
+        self.flash.read(
+            self.address(location.sector, location.offset) + RECORD_HEADER_LENGTH,
+            key,
+        )?;
+
+        Ok(key)
+    }
+
+    fn value_equals(&self, location: Location, value: &[u8]) -> Result<bool, KvError> {
+        if location.header.value_length as usize != value.len() {
+            return Ok(false);
+        }
+
+        let mut buffer = [0; CHUNK];
+        let address = self.value_address(location);
+
+        for (i, expected) in value.chunks(CHUNK).enumerate() {
+            let chunk = &mut buffer[..expected.len()];
+
+            self.flash.read(address + i * CHUNK, chunk)?;
+
+            if chunk != expected {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn sector_header(&self, sector: usize) -> Result<Option<SectorHeader>, KvError> {
+        let mut bytes = [0; SECTOR_HEADER_LENGTH];
+
+        self.flash.read(self.address(sector, 0), &mut bytes)?;
+
+        Ok(SectorHeader::from_bytes(&bytes))
+    }
+
+    fn value_address(&self, location: Location) -> usize {
+        self.address(location.sector, location.offset)
+            + RECORD_HEADER_LENGTH
+            + location.header.key_length as usize
+    }
+
+    fn address(&self, sector: usize, offset: usize) -> usize {
+        (self.first + sector) * SECTOR_SIZE + offset
+    }
+
+    fn next(&self, sector: usize) -> usize {
+        (sector + 1) % self.count
+    }
+}
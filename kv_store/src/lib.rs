@@ -0,0 +1,15 @@
+#![no_std]
+
+mod kv_error;
+mod kv_stats;
+mod kv_store;
+mod record_header;
+mod sector_header;
+
+pub use flash;
+pub use kv_error::KvError;
+pub use kv_stats::KvStats;
+pub use kv_store::KvStore;
+
+pub const MAX_KEY_LENGTH: usize = 32;
+pub const MAX_VALUE_LENGTH: usize = 1024;
@@ -0,0 +1,73 @@
+use crc32::Crc32;
+
+use crate::{MAX_KEY_LENGTH, MAX_VALUE_LENGTH};
+
+pub const RECORD_HEADER_LENGTH: usize = 8;
+
+const TOMBSTONE: u8 = 1 << 0;
+
+// `[key length][flags][value length][crc][key][value]`, the CRC covers everything but itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RecordHeader {
+    pub key_length: u8,
+    pub tombstone: bool,
+    pub value_length: u16,
+    pub crc: u32,
+}
+
+impl RecordHeader {
+    pub fn new(key: &[u8], value: &[u8], tombstone: bool) -> Self {
+        let mut header = Self {
+            key_length: key.len() as u8,
+            tombstone,
+            value_length: value.len() as u16,
+            crc: 0,
+        };
+        let mut crc = header.crc();
+
+        crc.update(key);
+        crc.update(value);
+        header.crc = crc.finalize();
+
+        header
+    }
+
+    pub fn len(&self) -> usize {
+        RECORD_HEADER_LENGTH + self.key_length as usize + self.value_length as usize
+    }
+
+    // CRC state after the header fields, to be continued with the key and value.
+    pub fn crc(&self) -> Crc32 {
+        let mut crc = Crc32::new();
+
+        crc.update(&self.to_bytes()[..4]);
+
+        crc
+    }
+
+    pub fn to_bytes(self) -> [u8; RECORD_HEADER_LENGTH] {
+        let mut bytes = [0; RECORD_HEADER_LENGTH];
+
+        bytes[0] = self.key_length;
+        bytes[1] = if self.tombstone { TOMBSTONE } else { 0 };
+        bytes[2..4].copy_from_slice(&self.value_length.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.crc.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; RECORD_HEADER_LENGTH]) -> Option<Self> {
+        let header = Self {
+            key_length: bytes[0],
+            tombstone: bytes[1] == TOMBSTONE,
+            value_length: u16::from_le_bytes([bytes[2], bytes[3]]),
+            crc: u32::from_le_bytes(bytes[4..].try_into().unwrap()),
+        };
+
+        let valid = (1..=MAX_KEY_LENGTH).contains(&(header.key_length as usize))
+            && bytes[1] & !TOMBSTONE == 0
+            && header.value_length as usize <= MAX_VALUE_LENGTH;
+
+        valid.then_some(header)
+    }
+}
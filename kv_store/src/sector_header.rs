@@ -0,0 +1,37 @@
+use crc32::crc32;
+
+pub const SECTOR_HEADER_LENGTH: usize = 12;
+
+const MAGIC: [u8; 4] = *b"KVS0";
+
+// Written right after a sector is erased and becomes the active one. Sectors without a valid
+// header are free, even if an interrupted erase left some data behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SectorHeader {
+    pub sequence: u32,
+}
+
+impl SectorHeader {
+    pub fn to_bytes(self) -> [u8; SECTOR_HEADER_LENGTH] {
+        let mut bytes = [0; SECTOR_HEADER_LENGTH];
+
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+
+        let crc = crc32(&bytes[..8]);
+
+        bytes[8..].copy_from_slice(&crc.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECTOR_HEADER_LENGTH]) -> Option<Self> {
+        if bytes[..4] != MAGIC || crc32(&bytes[..8]).to_le_bytes() != bytes[8..] {
+            return None;
+        }
+
+        Some(Self {
+            sequence: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+        })
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use kv_store::{
+    flash::{FlashStorage, MemoryFlash},
+    KvError, KvStore, MAX_KEY_LENGTH, MAX_VALUE_LENGTH,
+};
+
+const SECTORS: usize = 4;
+
+type Model = HashMap<String, Vec<u8>>;
+
+fn get(store: &KvStore<impl FlashStorage>, key: &str) -> Option<Vec<u8>> {
+    let mut buffer = [0; MAX_VALUE_LENGTH];
+
+    store
+        .get(key, &mut buffer)
+        .unwrap()
+        .map(|length| buffer[..length].to_vec())
+}
+
+fn assert_matches(store: &KvStore<impl FlashStorage>, model: &Model, keys: &[String]) {
+    for key in keys {
+        assert_eq!(get(store, key), model.get(key).cloned(), "key {key}");
+    }
+}
+
+fn value(seed: usize, length: usize) -> Vec<u8> {
+    (0..length).map(|i| (seed * 31 + i * 7) as u8).collect()
+}
+
+#[test]
+fn set_get_remove() {
+    let mut flash = MemoryFlash::<SECTORS>::new();
+    let mut store = KvStore::format(&mut flash, 0..SECTORS).unwrap();
+
+    assert_eq!(get(&store, "port.mode"), None);
+
+    store.set("port.mode", b"uplink").unwrap();
+    store.set("screen.brightness", &[80]).unwrap();
+    store.set("port.mode", b"access").unwrap();
+
+    assert_eq!(get(&store, "port.mode").unwrap(), b"access");
+    assert_eq!(get(&store, "screen.brightness").unwrap(), [80]);
+    assert!(store.contains("screen.brightness").unwrap());
+
+    assert!(store.remove("screen.brightness").unwrap());
+    assert!(!store.remove("screen.brightness").unwrap());
+    assert_eq!(get(&store, "screen.brightness"), None);
+
+    store.set("tts.voice", b"").unwrap();
+    assert_eq!(get(&store, "tts.voice").unwrap(), b"");
+
+    let stats = store.stats().unwrap();
+
+    assert_eq!(stats.keys, 2);
+    assert_eq!(stats.free_sectors, SECTORS - 1);
+}
+
+#[test]
+fn survives_remount() {
+    let mut flash = MemoryFlash::<SECTORS>::new();
+    let mut store = KvStore::format(&mut flash, 1..SECTORS).unwrap();
+
+    store.set("tts.voice", b"male-2").unwrap();
+    store.set("port.mode", b"uplink").unwrap();
+    store.remove("port.mode").unwrap();
+
+    let store = KvStore::mount(store.into_inner(), 1..SECTORS).unwrap();
+
+    assert_eq!(get(&store, "tts.voice").unwrap(), b"male-2");
+    assert_eq!(get(&store, "port.mode"), None);
+    assert!(flash.sector(0).iter().all(|&byte| byte == 0xFF));
+}
+
+#[test]
+fn unchanged_value_is_not_rewritten() {
+    let mut flash = MemoryFlash::<SECTORS>::new();
+    let mut store = KvStore::format(&mut flash, 0..SECTORS).unwrap();
+
+    store.set("screen.brightness", &[80]).unwrap();
+
+    let used = store.stats().unwrap().used;
+
+    store.set("screen.brightness", &[80]).unwrap();
+    assert_eq!(store.stats().unwrap().used, used);
+}
+
+#[test]
+fn rejects_invalid_input() {
+    let mut flash = MemoryFlash::<SECTORS>::new();
+
+    assert_eq!(
+        KvStore::format(&mut flash, 0..1).err(),
+        Some(KvError::TooFewSectors)
+    );
+
+    let mut store = KvStore::format(&mut flash, 0..SECTORS).unwrap();
+    let long_key = "k".repeat(MAX_KEY_LENGTH + 1);
+
+    assert_eq!(store.set("", b"value"), Err(KvError::InvalidKey));
+    assert_eq!(store.set(&long_key, b"value"), Err(KvError::InvalidKey));
+    assert_eq!(
+        store.set("key", &[0; MAX_VALUE_LENGTH + 1]),
+        Err(KvError::ValueTooLarge)
+    );
+
+    store.set("key", b"value").unwrap();
+    assert_eq!(store.get("key", &mut [0; 4]), Err(KvError::BufferTooSmall));
+}
+
+#[test]
+fn garbage_collection_levels_wear() {
+    let mut flash = MemoryFlash::<SECTORS>::new();
+    let mut store = KvStore::format(&mut flash, 0..SECTORS).unwrap();
+    let keys: Vec<_> = (0..6).map(|i| format!("setting.{i}")).collect();
+    let mut model = Model::new();
+
+    for i in 0..3000 {
+        let key = &keys[i % keys.len()];
+        let value = value(i, 50 + i % 150);
+
+        store.set(key, &value).unwrap();
+        model.insert(key.clone(), value);
+
+        if i % 7 == 0 {
+            store.remove(&keys[(i / 7) % keys.len()]).unwrap();
+            model.remove(&keys[(i / 7) % keys.len()]);
+        }
+    }
+
+    assert_matches(&store, &model, &keys);
+
+    let stats = store.stats().unwrap();
+
+    assert_eq!(stats.keys, model.len());
+    assert!(stats.free_sectors >= 1);
+    assert!(stats.sequence > 50);
+
+    let store = KvStore::mount(store.into_inner(), 0..SECTORS).unwrap();
+
+    assert_matches(&store, &model, &keys);
+
+    let counts = flash.erase_counts();
+    let (min, max) = (counts.iter().min().unwrap(), counts.iter().max().unwrap());
+
+    assert!(max - min <= 1, "uneven wear: {counts:?}");
+}
+
+#[test]
+fn reports_full_and_keeps_data() {
+    let mut flash = MemoryFlash::<SECTORS>::new();
+    let mut store = KvStore::format(&mut flash, 0..SECTORS).unwrap();
+    let mut model = Model::new();
+    let mut keys = Vec::new();
+
+    for i in 0.. {
+        let key = format!("key.{i}");
+        let value = value(i, 500);
+
+        match store.set(&key, &value) {
+            Ok(()) => {
+                model.insert(key.clone(), value);
+                keys.push(key);
+            }
+            Err(error) => {
+                assert_eq!(error, KvError::Full);
+                break;
+            }
+        }
+    }
+
+    // One sector is kept free for GC, the rest can be filled.
+    assert!(keys.len() >= (SECTORS - 1) * 7);
+
+    store.remove(&keys[0]).unwrap();
+    model.remove(&keys[0]);
+    store.set(&keys[1], b"smaller").unwrap();
+    model.insert(keys[1].clone(), b"smaller".to_vec());
+
+    let store = KvStore::mount(store.into_inner(), 0..SECTORS).unwrap();
+
+    assert_matches(&store, &model, &keys);
+}
+
+enum Op {
+    Set(usize, usize),
+    Remove(usize),
+}
+
+fn apply(store: &mut KvStore<impl FlashStorage>, model: &mut Model, keys: &[String], op: &Op) {
+    let result = match *op {
+        Op::Set(key, seed) => {
+            let value = value(seed, 60 + seed % 120);
+            let result = store.set(&keys[key], &value);
+
+            model.insert(keys[key].clone(), value);
+            result
+        }
+        Op::Remove(key) => {
+            model.remove(&keys[key]);
+            store.remove(&keys[key]).map(|_| ())
+        }
+    };
+
+    result.unwrap();
+}
+
+// Cuts the power at every point of a workload that wraps around the ring several times. After
+// a reboot each operation must have either fully happened or not happened at all.
+#[test]
+fn survives_power_cuts() {
+    const CUT_STEP: usize = 397;
+    const RECOVERY_OPS: usize = 80;
+
+    const CHURN_KEYS: usize = 5;
+
+    // A few keys are written once and have to be carried along by every GC.
+    let keys: Vec<_> = (0..CHURN_KEYS + 3)
+        .map(|i| format!("station.{i}"))
+        .collect();
+    let ops: Vec<_> = (0..300)
+        .map(|i| match i {
+            0..3 => Op::Set(CHURN_KEYS + i, i),
+            _ if i % 9 == 8 => Op::Remove(i % CHURN_KEYS),
+            _ => Op::Set(i % CHURN_KEYS, i),
+        })
+        .collect();
+
+    {
+        let mut flash = MemoryFlash::<3>::new();
+        let mut store = KvStore::format(&mut flash, 0..3).unwrap();
+        let mut model = Model::new();
+
+        for op in &ops {
+            apply(&mut store, &mut model, &keys, op);
+        }
+
+        // Enough writes to go around the ring a few times.
+        assert!(store.stats().unwrap().sequence > 6);
+    }
+
+    let mut cut = 0;
+    let mut cuts = 0;
+
+    loop {
+        let mut flash = MemoryFlash::<3>::new();
+        let mut model = Model::new();
+        let mut interrupted = None;
+
+        KvStore::format(&mut flash, 0..3).unwrap();
+        flash.cut_power_after(cut);
+
+        let mut store = KvStore::mount(&mut flash, 0..3).unwrap();
+
+        for (i, op) in ops.iter().enumerate() {
+            let previous = model.clone();
+
+            apply(&mut store, &mut model, &keys, op);
+
+            if !store.flash().is_powered() {
+                interrupted = Some((i, previous));
+                break;
+            }
+        }
+
+        let Some((interrupted, before)) = interrupted else {
+            break;
+        };
+
+        flash.restore_power();
+
+        let mut store = KvStore::mount(&mut flash, 0..3).unwrap();
+        let mut state: Model = keys
+            .iter()
+            .filter_map(|key| get(&store, key).map(|value| (key.clone(), value)))
+            .collect();
+
+        assert!(
+            state == before || state == model,
+            "inconsistent state after a power cut at byte {cut}"
+        );
+
+        // The store must keep working after the reboot, long enough to come around to the
+        // sector that was being collected.
+        for op in ops.iter().skip(interrupted + 1).take(RECOVERY_OPS) {
+            apply(&mut store, &mut state, &keys, op);
+        }
+
+        assert_matches(&store, &state, &keys);
+
+        cut += CUT_STEP;
+        cuts += 1;
+    }
+
+    assert!(cuts > 100, "only {cuts} power cuts");
+}
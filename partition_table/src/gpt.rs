@@ -0,0 +1,137 @@
+use crc32::crc32;
+
+use crate::{Guid, Partition, PartitionType};
+
+pub(crate) const HEADER_LENGTH: usize = 92;
+pub(crate) const ENTRY_LENGTH: usize = 128;
+pub(crate) const ENTRY_COUNT: usize = 128;
+pub(crate) const ENTRIES_LENGTH: usize = ENTRY_LENGTH * ENTRY_COUNT;
+// Tables with more entries than this are taken for garbage.
+pub(crate) const MAX_ENTRY_COUNT: u32 = 1024;
+
+const SIGNATURE: [u8; 8] = *b"EFI PART";
+const REVISION: u32 = 0x0001_0000;
+const CRC_OFFSET: usize = 16;
+const LEGACY_BOOTABLE: u64 = 1 << 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Header {
+    pub my_lba: u64,
+    pub alternate_lba: u64,
+    pub first_usable: u64,
+    pub last_usable: u64,
+    pub disk_guid: Guid,
+    pub entries_lba: u64,
+    pub entry_count: u32,
+    pub entry_length: u32,
+    pub entries_crc: u32,
+}
+
+impl Header {
+    pub fn to_bytes(self) -> [u8; HEADER_LENGTH] {
+        let mut bytes = [0; HEADER_LENGTH];
+
+        bytes[..8].copy_from_slice(&SIGNATURE);
+        bytes[8..12].copy_from_slice(&REVISION.to_le_bytes());
+        bytes[12..16].copy_from_slice(&(HEADER_LENGTH as u32).to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.my_lba.to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.alternate_lba.to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.first_usable.to_le_bytes());
+        bytes[48..56].copy_from_slice(&self.last_usable.to_le_bytes());
+        bytes[56..72].copy_from_slice(&self.disk_guid.0);
+        bytes[72..80].copy_from_slice(&self.entries_lba.to_le_bytes());
+        bytes[80..84].copy_from_slice(&self.entry_count.to_le_bytes());
+        bytes[84..88].copy_from_slice(&self.entry_length.to_le_bytes());
+        bytes[88..92].copy_from_slice(&self.entries_crc.to_le_bytes());
+
+        let crc = crc32(&bytes);
+
+        bytes[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+
+        bytes
+    }
+
+    // Takes the start of the header's sector. None unless the signature and the CRC match
+    // and the entry array has a shape we can read.
+    pub fn from_bytes(sector: &[u8]) -> Option<Self> {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(sector[offset..offset + 8].try_into().unwrap());
+        let length = u32_at(12) as usize;
+
+        if sector[..8] != SIGNATURE || !(HEADER_LENGTH..=sector.len()).contains(&length) {
+            return None;
+        }
+
+        let mut header = [0; 512];
+
+        header[..length].copy_from_slice(&sector[..length]);
+        header[CRC_OFFSET..CRC_OFFSET + 4].fill(0);
+
+        if crc32(&header[..length]) != u32_at(CRC_OFFSET) {
+            return None;
+        }
+
+        let header = Self {
+            my_lba: u64_at(24),
+            alternate_lba: u64_at(32),
+            first_usable: u64_at(40),
+            last_usable: u64_at(48),
+            disk_guid: Guid(sector[56..72].try_into().unwrap()),
+            entries_lba: u64_at(72),
+            entry_count: u32_at(80),
+            entry_length: u32_at(84),
+            entries_crc: u32_at(88),
+        };
+
+        (header.entry_count <= MAX_ENTRY_COUNT
+            && header.entry_length as usize >= ENTRY_LENGTH
+            && (header.entry_length as usize).is_multiple_of(ENTRY_LENGTH)
+            && header.first_usable <= header.last_usable)
+            .then_some(header)
+    }
+}
+
+pub(crate) fn encode_entry(partition: &Partition) -> [u8; ENTRY_LENGTH] {
+    let mut bytes = [0; ENTRY_LENGTH];
+    let PartitionType::Gpt(kind) = partition.kind else {
+        return bytes;
+    };
+    let attributes = if partition.bootable {
+        LEGACY_BOOTABLE
+    } else {
+        0
+    };
+
+    bytes[..16].copy_from_slice(&kind.0);
+    bytes[16..32].copy_from_slice(&partition.guid.0);
+    bytes[32..40].copy_from_slice(&partition.first_sector.to_le_bytes());
+    bytes[40..48].copy_from_slice(&(partition.end_sector() - 1).to_le_bytes());
+    bytes[48..56].copy_from_slice(&attributes.to_le_bytes());
+
+    bytes
+}
+
+// None for an unused entry.
+pub(crate) fn parse_entry(bytes: &[u8]) -> Option<Partition> {
+    let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+    let kind = Guid(bytes[..16].try_into().unwrap());
+
+    if kind.is_zero() {
+        return None;
+    }
+
+    let first_sector = u64_at(32);
+
+    Some(Partition {
+        kind: PartitionType::Gpt(kind),
+        first_sector,
+        // A last sector before the first makes an empty partition, which fails validation.
+        sector_count: u64_at(40)
+            .checked_add(1)
+            .map_or(0, |end| end.saturating_sub(first_sector)),
+        bootable: u64_at(48) & LEGACY_BOOTABLE != 0,
+        guid: Guid(bytes[16..32].try_into().unwrap()),
+    })
+}
@@ -0,0 +1,74 @@
+use core::fmt::Display;
+
+// Kept in the mixed-endian order GPT stores it in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    pub const ZERO: Self = Self([0; 16]);
+
+    pub const EFI_SYSTEM: Self = Self::from_fields(
+        0xC12A7328,
+        0xF81F,
+        0x11D2,
+        [0xBA, 0x4B, 0, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B],
+    );
+    pub const BIOS_BOOT: Self = Self::from_fields(
+        0x21686148,
+        0x6449,
+        0x6E6F,
+        [0x74, 0x4E, 0x65, 0x65, 0x64, 0x45, 0x46, 0x49],
+    );
+    pub const LINUX_FILESYSTEM: Self = Self::from_fields(
+        0x0FC63DAF,
+        0x8483,
+        0x4772,
+        [0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D, 0xE4],
+    );
+    pub const LINUX_SWAP: Self = Self::from_fields(
+        0x0657FD6D,
+        0xA4AB,
+        0x43C4,
+        [0x84, 0xE5, 0x09, 0x33, 0xC8, 0x4B, 0x4F, 0x4F],
+    );
+
+    // From the fields as written in the usual text form, 0FC63DAF-8483-4772-8E79-3D69D8477DE4.
+    pub const fn from_fields(first: u32, second: u16, third: u16, rest: [u8; 8]) -> Self {
+        let first = first.to_le_bytes();
+        let second = second.to_le_bytes();
+        let third = third.to_le_bytes();
+
+        Self([
+            first[0], first[1], first[2], first[3], second[0], second[1], third[0], third[1],
+            rest[0], rest[1], rest[2], rest[3], rest[4], rest[5], rest[6], rest[7],
+        ])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+}
+
+impl Display for Guid {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let bytes = &self.0;
+
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-",
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            u16::from_le_bytes([bytes[4], bytes[5]]),
+            u16::from_le_bytes([bytes[6], bytes[7]]),
+        )?;
+
+        for (i, byte) in bytes[8..].iter().enumerate() {
+            if i == 2 {
+                f.write_str("-")?;
+            }
+
+            write!(f, "{byte:02X}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+#![no_std]
+
+mod gpt;
+mod guid;
+mod mbr;
+mod partition;
+mod partition_device;
+mod partition_error;
+mod partition_table;
+
+pub use block_device;
+pub use guid::Guid;
+pub use partition::{Partition, PartitionType};
+pub use partition_device::PartitionDevice;
+pub use partition_error::PartitionError;
+pub use partition_table::{PartitionTable, Scheme};
+
+// GPT disks describe up to 128 partitions, only this many are kept in memory.
+pub const MAX_PARTITIONS: usize = 16;
+pub const MAX_MBR_PARTITIONS: usize = 4;
+// Partitions start on 4 KiB boundaries, which suits both 512 byte and 4 KiB sectors.
+pub const ALIGNMENT: usize = 4096;
@@ -0,0 +1,69 @@
+pub(crate) const PROTECTIVE: u8 = 0xEE;
+
+const DISK_SIGNATURE_OFFSET: usize = 440;
+const ENTRIES_OFFSET: usize = 446;
+const ENTRY_LENGTH: usize = 16;
+const SIGNATURE_OFFSET: usize = 510;
+const SIGNATURE: [u8; 2] = [0x55, 0xAA];
+const BOOTABLE: u8 = 0x80;
+// The largest CHS address, disks are addressed by LBA only.
+const CHS_UNUSED: [u8; 3] = [0xFE, 0xFF, 0xFF];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct MbrEntry {
+    pub kind: u8,
+    pub bootable: bool,
+    pub first_sector: u32,
+    pub sector_count: u32,
+}
+
+impl MbrEntry {
+    pub fn is_empty(&self) -> bool {
+        self.kind == 0 || self.sector_count == 0
+    }
+}
+
+// None without the boot signature, which means there is no partition table at all.
+pub(crate) fn parse(sector: &[u8; 512]) -> Option<(u32, [MbrEntry; 4])> {
+    let u32_at = |offset: usize| u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap());
+
+    if sector[SIGNATURE_OFFSET..] != SIGNATURE {
+        return None;
+    }
+
+    let mut entries = [MbrEntry::default(); 4];
+
+    for (i, entry) in entries.iter_mut().enumerate() {
+        let offset = ENTRIES_OFFSET + i * ENTRY_LENGTH;
+
+        *entry = MbrEntry {
+            kind: sector[offset + 4],
+            bootable: sector[offset] & BOOTABLE != 0,
+            first_sector: u32_at(offset + 8),
+            sector_count: u32_at(offset + 12),
+        };
+    }
+
+    Some((u32_at(DISK_SIGNATURE_OFFSET), entries))
+}
+
+// Fills in the table part of the first sector, the boot code before it is left alone.
+pub(crate) fn encode(sector: &mut [u8; 512], disk_signature: u32, entries: &[MbrEntry]) {
+    sector[DISK_SIGNATURE_OFFSET..DISK_SIGNATURE_OFFSET + 4]
+        .copy_from_slice(&disk_signature.to_le_bytes());
+    sector[DISK_SIGNATURE_OFFSET + 4..SIGNATURE_OFFSET].fill(0);
+
+    for (i, entry) in entries.iter().take(4).enumerate() {
+        let offset = ENTRIES_OFFSET + i * ENTRY_LENGTH;
+        let bytes = &mut sector[offset..offset + ENTRY_LENGTH];
+
+        bytes[0] = if entry.bootable { BOOTABLE } else { 0 };
+        bytes[1..4].copy_from_slice(&CHS_UNUSED);
+        bytes[4] = entry.kind;
+        bytes[5..8].copy_from_slice(&CHS_UNUSED);
+        bytes[8..12].copy_from_slice(&entry.first_sector.to_le_bytes());
+        bytes[12..16].copy_from_slice(&entry.sector_count.to_le_bytes());
+    }
+
+    sector[SIGNATURE_OFFSET..].copy_from_slice(&SIGNATURE);
+}
@@ -0,0 +1,46 @@
+use core::ops::Range;
+
+use crate::Guid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PartitionType {
+    Mbr(u8),
+    Gpt(Guid),
+}
+
+impl PartitionType {
+    pub const MBR_FAT12: Self = Self::Mbr(0x01);
+    pub const MBR_FAT16: Self = Self::Mbr(0x06);
+    pub const MBR_FAT32: Self = Self::Mbr(0x0C);
+    pub const MBR_LINUX_SWAP: Self = Self::Mbr(0x82);
+    pub const MBR_LINUX: Self = Self::Mbr(0x83);
+    pub const MBR_EFI_SYSTEM: Self = Self::Mbr(0xEF);
+
+    pub const GPT_EFI_SYSTEM: Self = Self::Gpt(Guid::EFI_SYSTEM);
+    pub const GPT_BIOS_BOOT: Self = Self::Gpt(Guid::BIOS_BOOT);
+    pub const GPT_LINUX_FILESYSTEM: Self = Self::Gpt(Guid::LINUX_FILESYSTEM);
+    pub const GPT_LINUX_SWAP: Self = Self::Gpt(Guid::LINUX_SWAP);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partition {
+    pub kind: PartitionType,
+    pub first_sector: u64,
+    pub sector_count: u64,
+    // The MBR boot flag, GPT keeps it in bit 2 of the attributes.
+    pub bootable: bool,
+    // Zero on MBR disks.
+    pub guid: Guid,
+}
+
+impl Partition {
+    pub fn end_sector(&self) -> u64 {
+        self.first_sector.saturating_add(self.sector_count)
+    }
+
+    pub fn byte_range(&self, sector_size: usize) -> Range<usize> {
+        let sector_size = sector_size as u64;
+
+        (self.first_sector * sector_size) as usize..(self.end_sector() * sector_size) as usize
+    }
+}
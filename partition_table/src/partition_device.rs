@@ -0,0 +1,63 @@
+use core::ops::Range;
+
+use block_device::{check_range, BlockDevice, BlockError};
+
+// A window onto part of a device, so a filesystem on a partition can't touch anything
+// outside it. Offsets are relative to the start of the window.
+pub struct PartitionDevice<D: BlockDevice> {
+    device: D,
+    start: usize,
+    size: usize,
+}
+
+impl<D: BlockDevice> PartitionDevice<D> {
+    pub fn new(device: D, range: Range<usize>) -> Result<Self, BlockError> {
+        if range.start > range.end {
+            return Err(BlockError::OutOfRange);
+        }
+
+        check_range(device.size(), range.start, range.len())?;
+
+        Ok(Self {
+            device,
+            start: range.start,
+            size: range.len(),
+        })
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for PartitionDevice<D> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), BlockError> {
+        check_range(self.size, offset, buffer.len())?;
+        self.device.read_at(self.start + offset, buffer)
+    }
+
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        check_range(self.size, offset, data.len())?;
+        self.device.write_at(self.start + offset, data)
+    }
+
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.device.flush()
+    }
+
+    fn sector_size(&self) -> usize {
+        self.device.sector_size()
+    }
+
+    fn media_generation(&self) -> u64 {
+        self.device.media_generation()
+    }
+}
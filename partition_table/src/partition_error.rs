@@ -0,0 +1,46 @@
+use core::fmt::Display;
+
+use block_device::BlockError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PartitionError {
+    Device(BlockError),
+    NoPartitionTable,
+    InvalidGpt,
+    OverlapsGpt,
+    UnsupportedSectorSize,
+    InvalidPartition,
+    TooManyPartitions,
+    WrongScheme,
+    NoSpace,
+    NotFound,
+}
+
+impl Display for PartitionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PartitionError::Device(error) => write!(f, "device error: {error}"),
+            PartitionError::NoPartitionTable => f.write_str("no partition table on the device"),
+            PartitionError::InvalidGpt => f.write_str("both GPT headers are damaged"),
+            PartitionError::OverlapsGpt => {
+                f.write_str("usable sectors overlap the GPT headers or entry arrays")
+            }
+            PartitionError::UnsupportedSectorSize => f.write_str("unsupported sector size"),
+            PartitionError::InvalidPartition => {
+                f.write_str("partition lies outside the disk or overlaps another")
+            }
+            PartitionError::TooManyPartitions => f.write_str("too many partitions"),
+            PartitionError::WrongScheme => {
+                f.write_str("partition type does not match the partition table")
+            }
+            PartitionError::NoSpace => f.write_str("not enough free space on the disk"),
+            PartitionError::NotFound => f.write_str("no such partition"),
+        }
+    }
+}
+
+impl From<BlockError> for PartitionError {
+    fn from(value: BlockError) -> Self {
+        Self::Device(value)
+    }
+}
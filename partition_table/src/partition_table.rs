@@ -0,0 +1,549 @@
+use core::ops::Range;
+
+use block_device::BlockDevice;
+use crc32::Crc32;
+
+use crate::{
+    gpt::{self, Header, ENTRIES_LENGTH, ENTRY_COUNT, ENTRY_LENGTH},
+    mbr::{self, MbrEntry, PROTECTIVE},
+    Guid, Partition, PartitionDevice, PartitionError, PartitionType, ALIGNMENT, MAX_MBR_PARTITIONS,
+    MAX_PARTITIONS,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scheme {
+    Mbr,
+    Gpt,
+}
+
+// The partitions of one disk. Build a new table with `new_mbr` or `new_gpt` and `add`, or
+// `read` the one on the disk, and `write` it back after changing it.
+//
+// Only primary MBR partitions are read, extended ones are kept as they are without looking
+// at the logical partitions inside. GPT partition names and attributes other than the legacy
+// boot flag are not kept. GPT entries past the first `MAX_PARTITIONS` are skipped, and a table
+// that skipped any can't be written back since that would drop them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionTable {
+    scheme: Scheme,
+    sector_size: usize,
+    sector_count: u64,
+    disk_signature: u32,
+    disk_guid: Guid,
+    usable: Range<u64>,
+    partitions: [Option<Partition>; MAX_PARTITIONS],
+    count: usize,
+    skipped: usize,
+}
+
+impl PartitionTable {
+    pub fn new_mbr<D: BlockDevice>(
+        device: &D,
+        disk_signature: u32,
+    ) -> Result<Self, PartitionError> {
+        let (sector_size, sector_count) = geometry(device)?;
+        // Both the start and the length of a partition have to fit in 32 bits.
+        let usable = 1..sector_count.min(u32::MAX as u64);
+
+        if usable.is_empty() {
+            return Err(PartitionError::NoSpace);
+        }
+
+        Ok(Self::empty(
+            Scheme::Mbr,
+            sector_size,
+            sector_count,
+            disk_signature,
+            Guid::ZERO,
+            usable,
+        ))
+    }
+
+    pub fn new_gpt<D: BlockDevice>(device: &D, disk_guid: Guid) -> Result<Self, PartitionError> {
+        let (sector_size, sector_count) = geometry(device)?;
+        let usable = gpt_usable(sector_count, entry_sectors(sector_size));
+
+        if usable.is_empty() {
+            return Err(PartitionError::NoSpace);
+        }
+
+        Ok(Self::empty(
+            Scheme::Gpt,
+            sector_size,
+            sector_count,
+            0,
+            disk_guid,
+            usable,
+        ))
+    }
+
+    // Falls back to the backup GPT at the end of the disk when the primary one is damaged.
+    pub fn read<D: BlockDevice>(device: &mut D) -> Result<Self, PartitionError> {
+        let (sector_size, sector_count) = geometry(device)?;
+        let mut sector = [0; 512];
+
+        device.read_at(0, &mut sector)?;
+
+        let (disk_signature, entries) =
+            mbr::parse(&sector).ok_or(PartitionError::NoPartitionTable)?;
+
+        if entries.iter().any(|entry| entry.kind == PROTECTIVE) {
+            for lba in [1, sector_count - 1] {
+                if let Some(table) = Self::read_gpt(device, sector_size, sector_count, lba)? {
+                    return Ok(table);
+                }
+            }
+
+            return Err(PartitionError::InvalidGpt);
+        }
+
+        let mut table = Self::new_mbr(device, disk_signature)?;
+
+        for entry in entries.iter().filter(|entry| !entry.is_empty()) {
+            table.insert(Partition {
+                kind: PartitionType::Mbr(entry.kind),
+                first_sector: entry.first_sector as u64,
+                sector_count: entry.sector_count as u64,
+                bootable: entry.bootable,
+                guid: Guid::ZERO,
+            })?;
+        }
+
+        Ok(table)
+    }
+
+    fn read_gpt<D: BlockDevice>(
+        device: &mut D,
+        sector_size: usize,
+        sector_count: u64,
+        lba: u64,
+    ) -> Result<Option<Self>, PartitionError> {
+        let mut sector = [0; 512];
+
+        device.read_at(lba as usize * sector_size, &mut sector)?;
+
+        let Some(header) = Header::from_bytes(&sector) else {
+            return Ok(None);
+        };
+        let entries_length = header.entry_count as usize * header.entry_length as usize;
+        let Some(entries_start) = (header.entries_lba as usize)
+            .checked_mul(sector_size)
+            .filter(|start| {
+                start
+                    .checked_add(entries_length)
+                    .is_some_and(|end| end <= device.size())
+            })
+        else {
+            return Ok(None);
+        };
+
+        let usable = header.first_usable..header.last_usable.saturating_add(1);
+        let entry_sectors = entries_length.div_ceil(sector_size) as u64;
+        let layout = gpt_usable(sector_count, entry_sectors);
+        let entries = header.entries_lba..header.entries_lba + entry_sectors;
+
+        // Partitions must stay clear of both headers and of the entry arrays, both where this
+        // header puts its own and where they go at either end of the disk.
+        if header.my_lba != lba
+            || usable.start < layout.start
+            || usable.end > layout.end
+            || (usable.start < entries.end && entries.start < usable.end)
+        {
+            return Ok(None);
+        }
+
+        // The entries are only trusted once the whole array matches its CRC.
+        let mut crc = Crc32::new();
+        let mut entry = [0; ENTRY_LENGTH];
+
+        for offset in (entries_start..entries_start + entries_length).step_by(ENTRY_LENGTH) {
+            device.read_at(offset, &mut entry)?;
+            crc.update(&entry);
+        }
+
+        if crc.finalize() != header.entries_crc {
+            return Ok(None);
+        }
+
+        let mut table = Self::empty(
+            Scheme::Gpt,
+            sector_size,
+            sector_count,
+            0,
+            header.disk_guid,
+            usable,
+        );
+
+        for offset in
+            (entries_start..entries_start + entries_length).step_by(header.entry_length as usize)
+        {
+            device.read_at(offset, &mut entry)?;
+
+            match gpt::parse_entry(&entry) {
+                Some(_) if table.count == MAX_PARTITIONS => table.skipped += 1,
+                Some(partition) => table.insert(partition)?,
+                None => {}
+            }
+        }
+
+        Ok(Some(table))
+    }
+
+    pub fn write<D: BlockDevice>(&self, device: &mut D) -> Result<(), PartitionError> {
+        if geometry(device)? != (self.sector_size, self.sector_count) {
+            return Err(PartitionError::InvalidPartition);
+        }
+
+        if self.skipped > 0 {
+            return Err(PartitionError::TooManyPartitions);
+        }
+
+        match self.scheme {
+            Scheme::Mbr => {
+                let mut entries = [MbrEntry::default(); MAX_MBR_PARTITIONS];
+
+                for (entry, partition) in entries.iter_mut().zip(self.partitions()) {
+                    let PartitionType::Mbr(kind) = partition.kind else {
+                        return Err(PartitionError::WrongScheme);
+                    };
+
+                    *entry = MbrEntry {
+                        kind,
+                        bootable: partition.bootable,
+                        first_sector: partition.first_sector as u32,
+                        sector_count: partition.sector_count as u32,
+                    };
+                }
+
+                self.write_mbr(device, &entries)?;
+            }
+            Scheme::Gpt => {
+                let last_lba = self.sector_count - 1;
+                let entry_sectors = entry_sectors(self.sector_size);
+                let layout = gpt_usable(self.sector_count, entry_sectors);
+
+                // A table read from a disk with a smaller entry array may start its usable
+                // sectors where ours go.
+                if self.usable.start < layout.start || self.usable.end > layout.end {
+                    return Err(PartitionError::OverlapsGpt);
+                }
+
+                let mut crc = Crc32::new();
+
+                for index in 0..ENTRY_COUNT {
+                    crc.update(&self.gpt_entry(index));
+                }
+
+                let primary = Header {
+                    my_lba: 1,
+                    alternate_lba: last_lba,
+                    first_usable: self.usable.start,
+                    last_usable: self.usable.end - 1,
+                    disk_guid: self.disk_guid,
+                    entries_lba: 2,
+                    entry_count: ENTRY_COUNT as u32,
+                    entry_length: ENTRY_LENGTH as u32,
+                    entries_crc: crc.finalize(),
+                };
+                let backup = Header {
+                    my_lba: last_lba,
+                    alternate_lba: 1,
+                    entries_lba: last_lba - entry_sectors,
+                    ..primary
+                };
+
+                // The backup first, so one of the two is whole whenever the writes stop.
+                for header in [backup, primary] {
+                    self.write_gpt_entries(device, header.entries_lba)?;
+                    self.write_sector(device, header.my_lba, &header.to_bytes())?;
+                }
+
+                let protective = MbrEntry {
+                    kind: PROTECTIVE,
+                    bootable: false,
+                    first_sector: 1,
+                    sector_count: last_lba.min(u32::MAX as u64) as u32,
+                };
+
+                self.write_mbr(device, &[protective])?;
+            }
+        }
+
+        device.flush()?;
+
+        Ok(())
+    }
+
+    fn write_mbr<D: BlockDevice>(
+        &self,
+        device: &mut D,
+        entries: &[MbrEntry],
+    ) -> Result<(), PartitionError> {
+        let mut sector = [0; 512];
+
+        device.read_at(0, &mut sector)?;
+        mbr::encode(&mut sector, self.disk_signature, entries);
+        device.write_at(0, &sector)?;
+
+        Ok(())
+    }
+
+    fn write_gpt_entries<D: BlockDevice>(
+        &self,
+        device: &mut D,
+        lba: u64,
+    ) -> Result<(), PartitionError> {
+        const PER_WRITE: usize = 512 / ENTRY_LENGTH;
+
+        let start = lba as usize * self.sector_size;
+        let mut buffer = [0; 512];
+
+        for first in (0..ENTRY_COUNT).step_by(PER_WRITE) {
+            for (i, bytes) in buffer.chunks_mut(ENTRY_LENGTH).enumerate() {
+                bytes.copy_from_slice(&self.gpt_entry(first + i));
+            }
+
+            device.write_at(start + first * ENTRY_LENGTH, &buffer)?;
+        }
+
+        Ok(())
+    }
+
+    // Writes `data` at the start of the sector and zeroes the rest of it.
+    fn write_sector<D: BlockDevice>(
+        &self,
+        device: &mut D,
+        lba: u64,
+        data: &[u8],
+    ) -> Result<(), PartitionError> {
+        let start = lba as usize * self.sector_size;
+        let mut buffer = [0; 512];
+
+        buffer[..data.len()].copy_from_slice(data);
+        device.write_at(start, &buffer)?;
+
+        for offset in (512..self.sector_size).step_by(512) {
+            device.write_at(start + offset, &[0; 512])?;
+        }
+
+        Ok(())
+    }
+
+    fn gpt_entry(&self, index: usize) -> [u8; ENTRY_LENGTH] {
+        match self.partitions.get(index).copied().flatten() {
+            Some(partition) => gpt::encode_entry(&partition),
+            None => [0; ENTRY_LENGTH],
+        }
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    pub fn sector_size(&self) -> usize {
+        self.sector_size
+    }
+
+    pub fn disk_signature(&self) -> u32 {
+        self.disk_signature
+    }
+
+    pub fn disk_guid(&self) -> Guid {
+        self.disk_guid
+    }
+
+    // The sectors partitions may occupy.
+    pub fn usable_sectors(&self) -> Range<u64> {
+        self.usable.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Used GPT entries on the disk that didn't fit in the table.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Partition> {
+        self.partitions[..self.count].get(index)?.as_ref()
+    }
+
+    pub fn partitions(&self) -> impl Iterator<Item = &Partition> {
+        self.partitions[..self.count].iter().flatten()
+    }
+
+    // What `add` can still hand out after the last partition.
+    pub fn free_sectors(&self) -> u64 {
+        self.usable.end.saturating_sub(self.next_start())
+    }
+
+    // Places a new partition after the last one and returns its index.
+    pub fn add(&mut self, kind: PartitionType, sector_count: u64) -> Result<usize, PartitionError> {
+        let guid = match self.scheme {
+            Scheme::Mbr => Guid::ZERO,
+            Scheme::Gpt => self.new_partition_guid(),
+        };
+        let first_sector = self.next_start();
+
+        if sector_count > self.free_sectors() {
+            return Err(PartitionError::NoSpace);
+        }
+
+        self.insert(Partition {
+            kind,
+            first_sector,
+            sector_count,
+            bootable: false,
+            guid,
+        })?;
+
+        Ok(self.count - 1)
+    }
+
+    pub fn set_bootable(&mut self, index: usize, bootable: bool) -> Result<(), PartitionError> {
+        let partition = self.partitions[..self.count]
+            .get_mut(index)
+            .and_then(Option::as_mut)
+            .ok_or(PartitionError::NotFound)?;
+
+        partition.bootable = bootable;
+
+        Ok(())
+    }
+
+    // Later partitions move down one index.
+    pub fn remove(&mut self, index: usize) -> Result<Partition, PartitionError> {
+        let partition = *self.get(index).ok_or(PartitionError::NotFound)?;
+
+        self.partitions.copy_within(index + 1..self.count, index);
+        self.count -= 1;
+        self.partitions[self.count] = None;
+
+        Ok(partition)
+    }
+
+    // The partition as a device of its own.
+    pub fn open<D: BlockDevice>(
+        &self,
+        device: D,
+        index: usize,
+    ) -> Result<PartitionDevice<D>, PartitionError> {
+        let partition = self.get(index).ok_or(PartitionError::NotFound)?;
+
+        Ok(PartitionDevice::new(
+            device,
+            partition.byte_range(self.sector_size),
+        )?)
+    }
+
+    fn empty(
+        scheme: Scheme,
+        sector_size: usize,
+        sector_count: u64,
+        disk_signature: u32,
+        disk_guid: Guid,
+        usable: Range<u64>,
+    ) -> Self {
+        Self {
+            scheme,
+            sector_size,
+            sector_count,
+            disk_signature,
+            disk_guid,
+            usable,
+            partitions: [None; MAX_PARTITIONS],
+            count: 0,
+            skipped: 0,
+        }
+    }
+
+    fn insert(&mut self, partition: Partition) -> Result<(), PartitionError> {
+        let limit = match self.scheme {
+            Scheme::Mbr => MAX_MBR_PARTITIONS,
+            Scheme::Gpt => MAX_PARTITIONS,
+        };
+        let matches = matches!(
+            (self.scheme, partition.kind),
+            (Scheme::Mbr, PartitionType::Mbr(_)) | (Scheme::Gpt, PartitionType::Gpt(_))
+        );
+        let fits = partition.sector_count != 0
+            && partition.first_sector >= self.usable.start
+            && partition
+                .first_sector
+                .checked_add(partition.sector_count)
+                .is_some_and(|end| end <= self.usable.end);
+        let overlaps = self.partitions().any(|other| {
+            partition.first_sector < other.end_sector()
+                && other.first_sector < partition.end_sector()
+        });
+
+        if !matches {
+            return Err(PartitionError::WrongScheme);
+        }
+
+        if !fits || overlaps {
+            return Err(PartitionError::InvalidPartition);
+        }
+
+        if self.count == limit {
+            return Err(PartitionError::TooManyPartitions);
+        }
+
+        self.partitions[self.count] = Some(partition);
+        self.count += 1;
+
+        Ok(())
+    }
+
+    fn next_start(&self) -> u64 {
+        let alignment = (ALIGNMENT / self.sector_size).max(1) as u64;
+        let end = self
+            .partitions()
+            .map(Partition::end_sector)
+            .fold(self.usable.start, u64::max);
+
+        end.next_multiple_of(alignment)
+    }
+
+    // Unique on this disk, made from the disk GUID so tables come out the same every time.
+    fn new_partition_guid(&self) -> Guid {
+        (1..=u16::MAX)
+            .map(|n| {
+                let mut guid = self.disk_guid;
+                let [low, high] = n.to_le_bytes();
+
+                guid.0[14] ^= high;
+                guid.0[15] ^= low;
+                guid
+            })
+            .find(|guid| {
+                *guid != self.disk_guid && self.partitions().all(|other| other.guid != *guid)
+            })
+            .unwrap_or(self.disk_guid)
+    }
+}
+
+fn geometry<D: BlockDevice>(device: &D) -> Result<(usize, u64), PartitionError> {
+    let sector_size = device.sector_size();
+
+    if !sector_size.is_power_of_two() || !(512..=4096).contains(&sector_size) {
+        return Err(PartitionError::UnsupportedSectorSize);
+    }
+
+    Ok((sector_size, device.sector_count() as u64))
+}
+
+fn entry_sectors(sector_size: usize) -> u64 {
+    ENTRIES_LENGTH.div_ceil(sector_size) as u64
+}
+
+// What is left between the protective MBR, a header and an entry array at each end.
+fn gpt_usable(sector_count: u64, entry_sectors: u64) -> Range<u64> {
+    2 + entry_sectors..sector_count.saturating_sub(1 + entry_sectors)
+}
@@ -0,0 +1,345 @@
+use partition_table::{
+    block_device::{BlockDevice, BlockError, MemoryDisk},
+    Guid, PartitionError, PartitionTable, PartitionType, Scheme, MAX_PARTITIONS,
+};
+
+const DISK: usize = 4 * 1024 * 1024;
+const DISK_GUID: Guid = Guid::from_fields(
+    0x5EC7A7E5,
+    0x0001,
+    0x4000,
+    [0x80, 0x00, 0x57, 0xA7, 0x10, 0x4E, 0x00, 0x01],
+);
+
+fn station_layout(
+    table: &mut PartitionTable,
+    boot: PartitionType,
+    fs: PartitionType,
+    swap: PartitionType,
+) {
+    table.add(boot, 256).unwrap();
+    table.set_bootable(0, true).unwrap();
+    table.add(fs, 4096).unwrap();
+
+    let rest = table.free_sectors();
+
+    table.add(swap, rest).unwrap();
+    assert_eq!(table.free_sectors(), 0);
+}
+
+#[test]
+fn mbr_round_trip() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+
+    assert_eq!(
+        PartitionTable::read(&mut disk),
+        Err(PartitionError::NoPartitionTable)
+    );
+
+    // Boot code in front of the table survives.
+    disk.write_at(0, b"boot code").unwrap();
+
+    let mut table = PartitionTable::new_mbr(&disk, 0x5EC7A7E5).unwrap();
+
+    station_layout(
+        &mut table,
+        PartitionType::MBR_FAT12,
+        PartitionType::MBR_LINUX,
+        PartitionType::MBR_LINUX_SWAP,
+    );
+    table.write(&mut disk).unwrap();
+
+    let read = PartitionTable::read(&mut disk).unwrap();
+
+    assert_eq!(read, table);
+    assert_eq!(read.scheme(), Scheme::Mbr);
+    assert_eq!(read.disk_signature(), 0x5EC7A7E5);
+    assert_eq!(read.len(), 3);
+
+    let boot = read.get(0).unwrap();
+
+    assert!(boot.bootable);
+    assert_eq!(boot.first_sector, 8);
+    assert_eq!(read.get(1).unwrap().first_sector, 264);
+    assert_eq!(read.get(2).unwrap().end_sector(), (DISK / 512) as u64);
+    assert_eq!(&disk.data()[..9], b"boot code");
+    assert_eq!(disk.data()[510..512], [0x55, 0xAA]);
+    assert_eq!(disk.data()[446 + 16 + 4], 0x83);
+}
+
+#[test]
+fn gpt_round_trip_and_backup() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_gpt(&disk, DISK_GUID).unwrap();
+
+    station_layout(
+        &mut table,
+        PartitionType::GPT_BIOS_BOOT,
+        PartitionType::GPT_LINUX_FILESYSTEM,
+        PartitionType::GPT_LINUX_SWAP,
+    );
+    table.write(&mut disk).unwrap();
+
+    let read = PartitionTable::read(&mut disk).unwrap();
+    let guids: Vec<Guid> = read.partitions().map(|partition| partition.guid).collect();
+
+    assert_eq!(read, table);
+    assert_eq!(read.scheme(), Scheme::Gpt);
+    assert_eq!(read.disk_guid(), DISK_GUID);
+    assert_eq!(read.usable_sectors(), 34..(DISK / 512 - 33) as u64);
+    assert_eq!(read.get(0).unwrap().first_sector, 40);
+    assert!(read.get(0).unwrap().bootable);
+    assert!(guids
+        .iter()
+        .all(|guid| !guid.is_zero() && *guid != DISK_GUID));
+    assert!(guids[1..].iter().all(|guid| *guid != guids[0]));
+    assert_eq!(&disk.data()[512..520], b"EFI PART");
+    assert_eq!(&disk.data()[DISK - 512..DISK - 504], b"EFI PART");
+    assert_eq!(disk.data()[446 + 4], 0xEE);
+
+    // A damaged primary header or entry array falls back to the backup.
+    disk.write_at(512 + 100, &[0xFF]).unwrap();
+    disk.write_at(1024 + 40, &[0xFF]).unwrap();
+    assert_eq!(PartitionTable::read(&mut disk).unwrap(), table);
+
+    disk.write_at(DISK - 512 + 60, &[0xFF]).unwrap();
+    assert_eq!(
+        PartitionTable::read(&mut disk),
+        Err(PartitionError::InvalidGpt)
+    );
+
+    // Writing the table again repairs both copies.
+    table.write(&mut disk).unwrap();
+    assert_eq!(PartitionTable::read(&mut disk).unwrap(), table);
+}
+
+#[test]
+fn gpt_on_large_sectors() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::with_sector_size(&mut data, 4096);
+    let mut table = PartitionTable::new_gpt(&disk, DISK_GUID).unwrap();
+
+    table.add(PartitionType::GPT_LINUX_FILESYSTEM, 100).unwrap();
+    table.write(&mut disk).unwrap();
+
+    let read = PartitionTable::read(&mut disk).unwrap();
+
+    assert_eq!(read, table);
+    assert_eq!(read.usable_sectors(), 6..(DISK / 4096 - 5) as u64);
+    assert_eq!(read.get(0).unwrap().byte_range(4096), 6 * 4096..106 * 4096);
+    assert_eq!(
+        PartitionTable::new_mbr(&MemoryDisk::with_sector_size(&mut [0; 4096], 1000), 0),
+        Err(PartitionError::UnsupportedSectorSize)
+    );
+}
+
+#[test]
+fn partition_device_stays_in_its_range() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_mbr(&disk, 1).unwrap();
+
+    table.add(PartitionType::MBR_FAT12, 16).unwrap();
+    table.add(PartitionType::MBR_LINUX, 16).unwrap();
+    table.write(&mut disk).unwrap();
+
+    let mut partition = table.open(&mut disk, 1).unwrap();
+
+    assert_eq!(partition.size(), 16 * 512);
+    assert_eq!(partition.start(), 24 * 512);
+    partition.write_at(0, b"log").unwrap();
+    assert_eq!(
+        partition.write_at(16 * 512 - 1, b"xx"),
+        Err(BlockError::OutOfRange)
+    );
+
+    let mut buffer = [0; 3];
+
+    partition.read_at(0, &mut buffer).unwrap();
+    assert_eq!(&buffer, b"log");
+    assert_eq!(&disk.data()[24 * 512..24 * 512 + 3], b"log");
+    assert!(disk.data()[24 * 512 + 3..].iter().all(|&byte| byte == 0));
+    assert_eq!(
+        table.open(&mut disk, 2).err(),
+        Some(PartitionError::NotFound)
+    );
+}
+
+#[test]
+fn rejects_bad_layouts() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_mbr(&disk, 1).unwrap();
+
+    assert_eq!(
+        table.add(PartitionType::GPT_LINUX_SWAP, 8),
+        Err(PartitionError::WrongScheme)
+    );
+    assert_eq!(
+        table.add(PartitionType::MBR_LINUX, 0),
+        Err(PartitionError::InvalidPartition)
+    );
+    assert_eq!(
+        table.add(PartitionType::MBR_LINUX, (DISK / 512) as u64),
+        Err(PartitionError::NoSpace)
+    );
+
+    for _ in 0..4 {
+        table.add(PartitionType::MBR_LINUX, 8).unwrap();
+    }
+
+    assert_eq!(
+        table.add(PartitionType::MBR_LINUX, 8),
+        Err(PartitionError::TooManyPartitions)
+    );
+
+    let removed = table.remove(1).unwrap();
+
+    assert_eq!(removed.first_sector, 16);
+    assert_eq!(table.len(), 3);
+    assert_eq!(table.get(1).unwrap().first_sector, 24);
+    assert_eq!(table.remove(3), Err(PartitionError::NotFound));
+
+    // Overlapping entries written by someone else are refused.
+    table.write(&mut disk).unwrap();
+    disk.write_at(446 + 16 + 8, &12u32.to_le_bytes()).unwrap();
+    assert_eq!(
+        PartitionTable::read(&mut disk),
+        Err(PartitionError::InvalidPartition)
+    );
+}
+
+#[test]
+fn gpt_entries_past_the_limit_are_skipped() {
+    const ENTRIES: usize = 2 * 512;
+
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_gpt(&disk, DISK_GUID).unwrap();
+
+    for _ in 0..MAX_PARTITIONS {
+        table.add(PartitionType::GPT_LINUX_FILESYSTEM, 8).unwrap();
+    }
+
+    table.write(&mut disk).unwrap();
+
+    // Another tool filled three more entries of the primary table.
+    let last = data[ENTRIES + 15 * 128..ENTRIES + 16 * 128].to_vec();
+
+    for extra in 1..=3u64 {
+        let entry = &mut data[ENTRIES + (15 + extra as usize) * 128..][..128];
+        let first = table.get(15).unwrap().first_sector + extra * 8;
+
+        entry.copy_from_slice(&last);
+        entry[31] ^= extra as u8;
+        entry[32..40].copy_from_slice(&first.to_le_bytes());
+        entry[40..48].copy_from_slice(&(first + 7).to_le_bytes());
+    }
+
+    let entries_crc = crc32::crc32(&data[ENTRIES..ENTRIES + 128 * 128]);
+
+    data[512 + 88..512 + 92].copy_from_slice(&entries_crc.to_le_bytes());
+    data[512 + 16..512 + 20].fill(0);
+
+    let header_crc = crc32::crc32(&data[512..512 + 92]);
+
+    data[512 + 16..512 + 20].copy_from_slice(&header_crc.to_le_bytes());
+
+    let mut disk = MemoryDisk::new(&mut data);
+    let read = PartitionTable::read(&mut disk).unwrap();
+
+    assert_eq!(read.len(), MAX_PARTITIONS);
+    assert_eq!(read.skipped(), 3);
+    assert_eq!(read.get(15), table.get(15));
+    assert!(read.open(&mut disk, 15).is_ok());
+    assert_eq!(
+        read.write(&mut disk),
+        Err(PartitionError::TooManyPartitions)
+    );
+    assert_eq!(table.skipped(), 0);
+}
+
+// Overwrites `field` of the GPT header at `offset` and seals it again.
+fn patch_header(data: &mut [u8], offset: usize, field: usize, value: &[u8]) {
+    data[offset + field..][..value.len()].copy_from_slice(value);
+    data[offset + 16..offset + 20].fill(0);
+
+    let crc = crc32::crc32(&data[offset..offset + 92]);
+
+    data[offset + 16..offset + 20].copy_from_slice(&crc.to_le_bytes());
+}
+
+#[test]
+fn gpt_usable_sectors_stay_clear_of_the_headers() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_gpt(&disk, DISK_GUID).unwrap();
+
+    table.add(PartitionType::GPT_LINUX_FILESYSTEM, 64).unwrap();
+    table.write(&mut disk).unwrap();
+
+    let last_lba = (DISK / 512 - 1) as u64;
+
+    // Usable sectors over the primary header, the backup takes over.
+    patch_header(&mut data, 512, 40, &1u64.to_le_bytes());
+    assert_eq!(
+        PartitionTable::read(&mut MemoryDisk::new(&mut data)).unwrap(),
+        table
+    );
+
+    // Usable sectors over the backup entry array.
+    patch_header(&mut data, DISK - 512, 48, &(last_lba - 10).to_le_bytes());
+    assert_eq!(
+        PartitionTable::read(&mut MemoryDisk::new(&mut data)),
+        Err(PartitionError::InvalidGpt)
+    );
+
+    // Usable sectors over an entry array put somewhere else.
+    table.write(&mut MemoryDisk::new(&mut data)).unwrap();
+    data[DISK - 512..].fill(0);
+    data.copy_within(1024..1024 + 32 * 512, 1000 * 512);
+    patch_header(&mut data, 512, 72, &1000u64.to_le_bytes());
+    assert_eq!(
+        PartitionTable::read(&mut MemoryDisk::new(&mut data)),
+        Err(PartitionError::InvalidGpt)
+    );
+}
+
+#[test]
+fn gpt_with_a_short_entry_array_is_not_written_over() {
+    let mut data = vec![0; DISK];
+    let mut disk = MemoryDisk::new(&mut data);
+    let mut table = PartitionTable::new_gpt(&disk, DISK_GUID).unwrap();
+
+    table.add(PartitionType::GPT_LINUX_FILESYSTEM, 64).unwrap();
+    table.write(&mut disk).unwrap();
+    disk.write_at(DISK - 512, &[0; 512]).unwrap();
+
+    // Another tool wrote a single sector of four entries and starts partitions right after.
+    let entries_crc = crc32::crc32(&data[1024..1024 + 4 * 128]);
+
+    patch_header(&mut data, 512, 40, &3u64.to_le_bytes());
+    patch_header(&mut data, 512, 80, &4u32.to_le_bytes());
+    patch_header(&mut data, 512, 88, &entries_crc.to_le_bytes());
+
+    let mut disk = MemoryDisk::new(&mut data);
+    let read = PartitionTable::read(&mut disk).unwrap();
+
+    assert_eq!(read.usable_sectors().start, 3);
+    assert_eq!(read.get(0), table.get(0));
+
+    // Our entry arrays are 32 sectors long and would land on partition space.
+    assert_eq!(read.write(&mut disk), Err(PartitionError::OverlapsGpt));
+    assert_eq!(&disk.data()[DISK - 512..], &[0; 512]);
+}
+
+#[test]
+fn guid_text_form() {
+    assert_eq!(
+        Guid::LINUX_FILESYSTEM.to_string(),
+        "0FC63DAF-8483-4772-8E79-3D69D8477DE4"
+    );
+    assert_eq!(Guid::LINUX_FILESYSTEM.0[..4], [0xAF, 0x3D, 0xC6, 0x0F]);
+}
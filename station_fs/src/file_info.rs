@@ -0,0 +1,30 @@
+use crate::{inode::Inode, MAX_NAME_LENGTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileInfo {
+    pub size: u32,
+    pub checksum: u32,
+    name: [u8; MAX_NAME_LENGTH],
+    name_length: usize,
+}
+
+impl FileInfo {
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_length]).unwrap_or("")
+    }
+}
+
+impl From<&Inode> for FileInfo {
+    fn from(value: &Inode) -> Self {
+        let mut info = Self {
+            size: value.size,
+            checksum: value.checksum,
+            name: [0; MAX_NAME_LENGTH],
+            name_length: value.name().len(),
+        };
+
+        info.name[..info.name_length].copy_from_slice(value.name().as_bytes());
+
+        info
+    }
+}
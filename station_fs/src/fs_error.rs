@@ -0,0 +1,46 @@
+use core::fmt::Display;
+
+use block_device::BlockError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FsError {
+    Device(BlockError),
+    NotFormatted,
+    UnsupportedSize,
+    InvalidLayout,
+    InvalidName,
+    NotFound,
+    AlreadyExists,
+    TooManyFiles,
+    DiskFull,
+    TooFragmented,
+    FileTooLarge,
+    BufferTooSmall,
+    ChecksumMismatch,
+}
+
+impl Display for FsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FsError::Device(error) => write!(f, "device error: {error}"),
+            FsError::NotFormatted => f.write_str("no filesystem on the device"),
+            FsError::UnsupportedSize => f.write_str("device is too small for a filesystem"),
+            FsError::InvalidLayout => f.write_str("invalid filesystem layout"),
+            FsError::InvalidName => f.write_str("invalid file name"),
+            FsError::NotFound => f.write_str("no such file"),
+            FsError::AlreadyExists => f.write_str("file already exists"),
+            FsError::TooManyFiles => f.write_str("no free file slots left"),
+            FsError::DiskFull => f.write_str("no free blocks left"),
+            FsError::TooFragmented => f.write_str("free space is too fragmented for the file"),
+            FsError::FileTooLarge => f.write_str("file is too large"),
+            FsError::BufferTooSmall => f.write_str("buffer is too small for the file"),
+            FsError::ChecksumMismatch => f.write_str("file contents don't match the checksum"),
+        }
+    }
+}
+
+impl From<BlockError> for FsError {
+    fn from(value: BlockError) -> Self {
+        FsError::Device(value)
+    }
+}
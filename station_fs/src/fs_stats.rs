@@ -0,0 +1,7 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsStats {
+    pub blocks: usize,
+    pub free_blocks: usize,
+    pub files: usize,
+    pub max_files: usize,
+}
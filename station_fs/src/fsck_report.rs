@@ -0,0 +1,21 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FsckReport {
+    pub files: usize,
+    // Entries with a broken layout or blocks shared with another file. Repair removes them.
+    pub invalid_files: usize,
+    // Files whose contents fail their checksum. They are left alone.
+    pub corrupted_files: usize,
+    // Blocks marked used that no file owns. Repair frees them.
+    pub leaked_blocks: usize,
+    // Blocks a file owns that are marked free. Repair marks them used.
+    pub missing_blocks: usize,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.invalid_files == 0
+            && self.corrupted_files == 0
+            && self.leaked_blocks == 0
+            && self.missing_blocks == 0
+    }
+}
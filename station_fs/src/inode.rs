@@ -0,0 +1,182 @@
+use core::ops::Range;
+
+use crate::{superblock::Superblock, BLOCK_SIZE, MAX_EXTENTS, MAX_NAME_LENGTH};
+
+pub(crate) const INODE_LENGTH: usize = 128;
+pub(crate) const INODES_PER_BLOCK: usize = BLOCK_SIZE / INODE_LENGTH;
+
+const USED: u8 = 1;
+const NAME_OFFSET: usize = 16;
+const EXTENTS_OFFSET: usize = NAME_OFFSET + MAX_NAME_LENGTH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Extent {
+    pub start: u32,
+    pub length: u32,
+}
+
+// A file: its name, size, checksum of the contents and the runs of blocks holding them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Inode {
+    pub size: u32,
+    pub checksum: u32,
+    name: [u8; MAX_NAME_LENGTH],
+    name_length: u8,
+    extents: [Extent; MAX_EXTENTS],
+    extent_count: u8,
+}
+
+impl Inode {
+    pub fn new(name: &str) -> Self {
+        let mut inode = Self {
+            size: 0,
+            // The CRC of nothing.
+            checksum: 0,
+            name: [0; MAX_NAME_LENGTH],
+            name_length: 0,
+            extents: [Extent::default(); MAX_EXTENTS],
+            extent_count: 0,
+        };
+
+        inode.set_name(name);
+
+        inode
+    }
+
+    pub fn name(&self) -> &str {
+        let length = (self.name_length as usize).min(MAX_NAME_LENGTH);
+
+        core::str::from_utf8(&self.name[..length]).unwrap_or("")
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = [0; MAX_NAME_LENGTH];
+        self.name[..name.len()].copy_from_slice(name.as_bytes());
+        self.name_length = name.len() as u8;
+    }
+
+    pub fn extents(&self) -> &[Extent] {
+        &self.extents[..(self.extent_count as usize).min(MAX_EXTENTS)]
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.extents()
+            .iter()
+            .map(|extent| extent.length as usize)
+            .sum()
+    }
+
+    // Appends a block to the file, growing the last extent when it is the next one along.
+    pub fn push_block(&mut self, block: u32) -> bool {
+        let count = self.extents().len();
+
+        match self.extents[..count].last_mut() {
+            Some(last) if last.start + last.length == block => last.length += 1,
+            _ if count == MAX_EXTENTS => return false,
+            _ => {
+                self.extents[count] = Extent {
+                    start: block,
+                    length: 1,
+                };
+                self.extent_count += 1;
+            }
+        }
+
+        true
+    }
+
+    // Where `length` bytes of the file starting at `offset` are on the device, along with
+    // where they go in the caller's buffer.
+    pub fn runs(
+        &self,
+        offset: usize,
+        length: usize,
+    ) -> impl Iterator<Item = (usize, Range<usize>)> + '_ {
+        let end = offset + length;
+        let mut extent_offset = 0;
+
+        self.extents().iter().filter_map(move |extent| {
+            let start = extent_offset;
+            let extent_end = start + extent.length as usize * BLOCK_SIZE;
+            let from = start.max(offset);
+            let to = extent_end.min(end);
+
+            extent_offset = extent_end;
+
+            (from < to).then(|| {
+                (
+                    extent.start as usize * BLOCK_SIZE + from - start,
+                    from - offset..to - offset,
+                )
+            })
+        })
+    }
+
+    // Whether the inode describes a file that can exist in this layout.
+    pub fn is_valid(&self, superblock: &Superblock) -> bool {
+        let data = superblock.data_start..superblock.blocks;
+
+        !self.name().is_empty()
+            && self.name().len() == self.name_length as usize
+            && self.extent_count as usize <= MAX_EXTENTS
+            && self.extents().iter().all(|extent| {
+                extent.length != 0
+                    && data.contains(&extent.start)
+                    && extent
+                        .start
+                        .checked_add(extent.length)
+                        .is_some_and(|end| end <= data.end)
+            })
+            && self.blocks() == (self.size as usize).div_ceil(BLOCK_SIZE)
+    }
+
+    pub fn to_bytes(self) -> [u8; INODE_LENGTH] {
+        let mut bytes = [0; INODE_LENGTH];
+
+        bytes[0] = USED;
+        bytes[1] = self.name_length;
+        bytes[2] = self.extent_count;
+        bytes[4..8].copy_from_slice(&self.size.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes[NAME_OFFSET..EXTENTS_OFFSET].copy_from_slice(&self.name);
+
+        for (i, extent) in self.extents.iter().enumerate() {
+            let offset = EXTENTS_OFFSET + i * 8;
+
+            bytes[offset..offset + 4].copy_from_slice(&extent.start.to_le_bytes());
+            bytes[offset + 4..offset + 8].copy_from_slice(&extent.length.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    // None for a free slot. Malformed fields are kept as they are for fsck to find.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        if bytes[0] & USED == 0 {
+            return None;
+        }
+
+        let mut extents = [Extent::default(); MAX_EXTENTS];
+
+        for (i, extent) in extents.iter_mut().enumerate() {
+            let offset = EXTENTS_OFFSET + i * 8;
+
+            *extent = Extent {
+                start: u32_at(offset),
+                length: u32_at(offset + 4),
+            };
+        }
+
+        Some(Self {
+            size: u32_at(4),
+            checksum: u32_at(8),
+            name: bytes[NAME_OFFSET..EXTENTS_OFFSET].try_into().unwrap(),
+            name_length: bytes[1],
+            extents,
+            extent_count: bytes[2],
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use block_device::{BlockDevice, BlockError};
+use crc32::Crc32;
+
+use crate::{superblock::BITS_PER_BLOCK, BLOCK_SIZE, MAX_BLOCKS};
+
+// One inode block and every bitmap block is the most a single operation can change.
+pub(crate) const MAX_TRANSACTION_BLOCKS: usize = 1 + MAX_BLOCKS / BITS_PER_BLOCK as usize;
+pub(crate) const JOURNAL_BLOCKS: u32 = 1 + MAX_TRANSACTION_BLOCKS as u32;
+
+const MAGIC: [u8; 4] = *b"ONJL";
+const BLOCKS_OFFSET: usize = 8;
+const CRC_OFFSET: usize = BLOCKS_OFFSET + MAX_TRANSACTION_BLOCKS * 4;
+
+// Metadata blocks changed by an operation, kept in memory until it commits. Reads of metadata
+// go through here first, so an operation sees its own changes.
+//
+// Committing writes the blocks to the journal, then a header with a CRC over all of them,
+// and only then to their home locations. A power cut before the header is complete leaves
+// the old metadata untouched, after it the journal is replayed on mount.
+pub(crate) struct Transaction {
+    blocks: [u32; MAX_TRANSACTION_BLOCKS],
+    data: [[u8; BLOCK_SIZE]; MAX_TRANSACTION_BLOCKS],
+    count: usize,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self {
+            blocks: [0; MAX_TRANSACTION_BLOCKS],
+            data: [[0; BLOCK_SIZE]; MAX_TRANSACTION_BLOCKS],
+            count: 0,
+        }
+    }
+
+    pub fn get(&self, block: u32) -> Option<&[u8; BLOCK_SIZE]> {
+        self.position(block).map(|i| &self.data[i])
+    }
+
+    pub fn put(&mut self, block: u32, data: &[u8; BLOCK_SIZE]) -> bool {
+        let i = match self.position(block) {
+            Some(i) => i,
+            None if self.count == MAX_TRANSACTION_BLOCKS => return false,
+            None => {
+                self.blocks[self.count] = block;
+                self.count += 1;
+                self.count - 1
+            }
+        };
+
+        self.data[i] = *data;
+
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    pub fn commit<D: BlockDevice>(
+        &mut self,
+        device: &mut D,
+        journal_start: u32,
+    ) -> Result<(), BlockError> {
+        let result = self.write(device, journal_start);
+
+        self.clear();
+
+        result
+    }
+
+    fn write<D: BlockDevice>(&self, device: &mut D, journal_start: u32) -> Result<(), BlockError> {
+        if self.count == 0 {
+            return Ok(());
+        }
+
+        let header_offset = journal_start as usize * BLOCK_SIZE;
+        let mut header = [0; BLOCK_SIZE];
+        let mut crc = Crc32::new();
+
+        header[..4].copy_from_slice(&MAGIC);
+        header[4..8].copy_from_slice(&(self.count as u32).to_le_bytes());
+
+        for (i, block) in self.blocks[..self.count].iter().enumerate() {
+            let offset = BLOCKS_OFFSET + i * 4;
+
+            header[offset..offset + 4].copy_from_slice(&block.to_le_bytes());
+        }
+
+        crc.update(&header[..CRC_OFFSET]);
+
+        for (i, data) in self.data[..self.count].iter().enumerate() {
+            device.write_at(header_offset + (i + 1) * BLOCK_SIZE, data)?;
+            crc.update(data);
+        }
+
+        header[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.finalize().to_le_bytes());
+
+        device.flush()?;
+        device.write_at(header_offset, &header)?;
+        device.flush()?;
+
+        for (block, data) in self.blocks[..self.count].iter().zip(&self.data) {
+            device.write_at(*block as usize * BLOCK_SIZE, data)?;
+        }
+
+        device.flush()?;
+        device.write_at(header_offset, &[0; 4])?;
+        device.flush()
+    }
+
+    fn position(&self, block: u32) -> Option<usize> {
+        self.blocks[..self.count].iter().position(|&b| b == block)
+    }
+}
+
+// Finishes a transaction a power cut interrupted after it was committed to the journal.
+// Returns whether there was one.
+pub(crate) fn replay<D: BlockDevice>(
+    device: &mut D,
+    journal_start: u32,
+) -> Result<bool, BlockError> {
+    let header_offset = journal_start as usize * BLOCK_SIZE;
+    let mut header = [0; BLOCK_SIZE];
+    let mut data = [0; BLOCK_SIZE];
+
+    device.read_at(header_offset, &mut header)?;
+
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+    let count = u32_at(4) as usize;
+
+    if header[..4] != MAGIC || count == 0 || count > MAX_TRANSACTION_BLOCKS {
+        return Ok(false);
+    }
+
+    let mut crc = Crc32::new();
+
+    crc.update(&header[..CRC_OFFSET]);
+
+    for i in 0..count {
+        device.read_at(header_offset + (i + 1) * BLOCK_SIZE, &mut data)?;
+        crc.update(&data);
+    }
+
+    // A torn commit, the transaction never happened.
+    if crc.finalize() != u32_at(CRC_OFFSET) {
+        return Ok(false);
+    }
+
+    for i in 0..count {
+        let block = u32_at(BLOCKS_OFFSET + i * 4) as usize;
+
+        device.read_at(header_offset + (i + 1) * BLOCK_SIZE, &mut data)?;
+        device.write_at(block * BLOCK_SIZE, &data)?;
+    }
+
+    device.flush()?;
+    device.write_at(header_offset, &[0; 4])?;
+    device.flush()?;
+
+    Ok(true)
+}
@@ -0,0 +1,24 @@
+#![no_std]
+
+mod file_info;
+mod fs_error;
+mod fs_stats;
+mod fsck_report;
+mod inode;
+mod journal;
+mod station_fs;
+mod superblock;
+
+pub use block_device;
+pub use file_info::FileInfo;
+pub use fs_error::FsError;
+pub use fs_stats::FsStats;
+pub use fsck_report::FsckReport;
+pub use station_fs::StationFs;
+
+pub const BLOCK_SIZE: usize = 512;
+pub const MAX_NAME_LENGTH: usize = 32;
+pub const MAX_EXTENTS: usize = 10;
+// Larger devices only use their first 32 MiB, the disks this is meant for are far smaller.
+pub const MAX_BLOCKS: usize = 65536;
+pub const MAX_FILES: usize = 4096;
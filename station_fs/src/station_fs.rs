@@ -0,0 +1,584 @@
+use block_device::BlockDevice;
+use crc32::{crc32, Crc32};
+
+use crate::{
+    inode::{Inode, INODES_PER_BLOCK, INODE_LENGTH},
+    journal::{self, Transaction},
+    superblock::{Superblock, BITS_PER_BLOCK},
+    FileInfo, FsError, FsStats, FsckReport, BLOCK_SIZE, MAX_BLOCKS, MAX_FILES, MAX_NAME_LENGTH,
+};
+
+// A flat filesystem for small disks that survives power cuts. Metadata changes go through a
+// journal, so every operation either happens completely or not at all, and file contents are
+// never overwritten in place: new data goes to free blocks and the old ones are freed by the
+// same transaction that switches the file over. Appends only fill the unused tail of the last
+// block before claiming new ones. Every file carries a CRC of its contents.
+pub struct StationFs<D: BlockDevice> {
+    device: D,
+    superblock: Superblock,
+    transaction: Transaction,
+}
+
+impl<D: BlockDevice> StationFs<D> {
+    pub fn mkfs(mut device: D, max_files: usize) -> Result<Self, FsError> {
+        if max_files == 0 || max_files > MAX_FILES {
+            return Err(FsError::InvalidLayout);
+        }
+
+        let blocks = (device.size() / BLOCK_SIZE).min(MAX_BLOCKS) as u32;
+        let superblock =
+            Superblock::new(blocks, max_files as u32).ok_or(FsError::UnsupportedSize)?;
+        let zeros = [0; BLOCK_SIZE];
+
+        for block in 1..superblock.bitmap_start {
+            device.write_at(block as usize * BLOCK_SIZE, &zeros)?;
+        }
+
+        // The metadata blocks are the only ones in use.
+        for i in 0..superblock.bitmap_blocks() {
+            let mut bitmap = [0; BLOCK_SIZE];
+
+            for index in 0..BITS_PER_BLOCK {
+                set_bit(
+                    &mut bitmap,
+                    index,
+                    i * BITS_PER_BLOCK + index < superblock.data_start,
+                );
+            }
+
+            device.write_at((superblock.bitmap_start + i) as usize * BLOCK_SIZE, &bitmap)?;
+        }
+
+        // The superblock goes last, an interrupted mkfs leaves nothing to mount.
+        device.flush()?;
+        device.write_at(0, &superblock.to_bytes())?;
+        device.flush()?;
+
+        Ok(Self {
+            device,
+            superblock,
+            transaction: Transaction::new(),
+        })
+    }
+
+    pub fn mount(mut device: D) -> Result<Self, FsError> {
+        let mut bytes = [0; BLOCK_SIZE];
+
+        device.read_at(0, &mut bytes)?;
+
+        let superblock = Superblock::from_bytes(&bytes).ok_or(FsError::NotFormatted)?;
+
+        if superblock.blocks as usize * BLOCK_SIZE > device.size() {
+            return Err(FsError::NotFormatted);
+        }
+
+        journal::replay(&mut device, superblock.journal_start)?;
+
+        Ok(Self {
+            device,
+            superblock,
+            transaction: Transaction::new(),
+        })
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    pub fn stats(&mut self) -> Result<FsStats, FsError> {
+        let mut free_blocks = 0;
+
+        for block in self.superblock.data_start..self.superblock.blocks {
+            if !self.is_used(block)? {
+                free_blocks += 1;
+            }
+        }
+
+        Ok(FsStats {
+            blocks: self.superblock.blocks as usize,
+            free_blocks,
+            files: self.files().count(),
+            max_files: self.superblock.max_files as usize,
+        })
+    }
+
+    pub fn files(&mut self) -> impl Iterator<Item = Result<FileInfo, FsError>> + '_ {
+        (0..self.superblock.max_files).filter_map(move |index| {
+            self.inode(index)
+                .map(|inode| inode.map(|inode| FileInfo::from(&inode)))
+                .transpose()
+        })
+    }
+
+    pub fn metadata(&mut self, name: &str) -> Result<FileInfo, FsError> {
+        let (_, inode) = self.find(name)?.ok_or(FsError::NotFound)?;
+
+        Ok(FileInfo::from(&inode))
+    }
+
+    // Reads part of a file without checking it against its checksum. Returns the number of
+    // bytes read, less than asked for only at the end of the file.
+    pub fn read(&mut self, name: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, FsError> {
+        let (_, inode) = self.find(name)?.ok_or(FsError::NotFound)?;
+        let length = buffer
+            .len()
+            .min((inode.size as usize).saturating_sub(offset));
+
+        for (device_offset, range) in inode.runs(offset, length) {
+            self.device.read_at(device_offset, &mut buffer[range])?;
+        }
+
+        Ok(length)
+    }
+
+    // Reads a whole file and checks it against its checksum.
+    pub fn read_file(&mut self, name: &str, buffer: &mut [u8]) -> Result<usize, FsError> {
+        let (_, inode) = self.find(name)?.ok_or(FsError::NotFound)?;
+        let size = inode.size as usize;
+
+        if buffer.len() < size {
+            return Err(FsError::BufferTooSmall);
+        }
+
+        for (device_offset, range) in inode.runs(0, size) {
+            self.device.read_at(device_offset, &mut buffer[range])?;
+        }
+
+        if crc32(&buffer[..size]) != inode.checksum {
+            return Err(FsError::ChecksumMismatch);
+        }
+
+        Ok(size)
+    }
+
+    pub fn verify(&mut self, name: &str) -> Result<(), FsError> {
+        let (_, inode) = self.find(name)?.ok_or(FsError::NotFound)?;
+
+        self.check(&inode)
+    }
+
+    // Creates the file or replaces its contents. Either the old or the new contents survive
+    // a power cut, never a mix of them.
+    pub fn write(&mut self, name: &str, data: &[u8]) -> Result<(), FsError> {
+        validate_name(name)?;
+
+        let size = u32::try_from(data.len()).map_err(|_| FsError::FileTooLarge)?;
+
+        self.transact(|fs| {
+            let (index, old) = match fs.find_valid(name)? {
+                Some((index, inode)) => (index, Some(inode)),
+                None => (fs.free_slot()?, None),
+            };
+            let mut inode = Inode::new(name);
+
+            inode.size = size;
+            inode.checksum = crc32(data);
+            fs.allocate(&mut inode, data.len().div_ceil(BLOCK_SIZE), None)?;
+            fs.write_data(&inode, 0, data)?;
+            fs.write_inode(index, Some(&inode))?;
+
+            // Only now, so none of the old blocks got handed out for the new contents.
+            if let Some(old) = old {
+                fs.free_blocks(&old)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    // Appends to the file, creating it if needed.
+    pub fn append(&mut self, name: &str, data: &[u8]) -> Result<(), FsError> {
+        validate_name(name)?;
+
+        self.transact(|fs| {
+            let (index, mut inode) = match fs.find_valid(name)? {
+                Some(found) => found,
+                None => (fs.free_slot()?, Inode::new(name)),
+            };
+            let size = inode.size as usize;
+            let new_size = size
+                .checked_add(data.len())
+                .and_then(|size| u32::try_from(size).ok())
+                .ok_or(FsError::FileTooLarge)?;
+            let hint = inode
+                .extents()
+                .last()
+                .map(|extent| extent.start + extent.length);
+            let needed = (new_size as usize).div_ceil(BLOCK_SIZE) - inode.blocks();
+            let mut checksum = Crc32::resume(inode.checksum);
+
+            fs.allocate(&mut inode, needed, hint)?;
+            fs.write_data(&inode, size, data)?;
+            checksum.update(data);
+            inode.size = new_size;
+            inode.checksum = checksum.finalize();
+            fs.write_inode(index, Some(&inode))
+        })
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), FsError> {
+        validate_name(to)?;
+
+        self.transact(|fs| {
+            let (index, mut inode) = fs.find(from)?.ok_or(FsError::NotFound)?;
+
+            if fs.find(to)?.is_some() {
+                return Err(FsError::AlreadyExists);
+            }
+
+            inode.set_name(to);
+            fs.write_inode(index, Some(&inode))
+        })
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), FsError> {
+        self.transact(|fs| {
+            let (index, inode) = fs.find_valid(name)?.ok_or(FsError::NotFound)?;
+
+            fs.write_inode(index, None)?;
+            fs.free_blocks(&inode)
+        })
+    }
+
+    // Checks the filesystem, and with `repair` fixes what can be fixed: files with a broken
+    // layout or sharing blocks with another file are removed and the allocation bitmap is
+    // rebuilt from the files that remain. Files failing their checksum are only reported.
+    pub fn fsck(&mut self, repair: bool) -> Result<FsckReport, FsError> {
+        let mut report = FsckReport::default();
+        let mut invalid = [0u8; MAX_FILES / 8];
+
+        for index in 0..self.superblock.max_files {
+            if let Some(inode) = self.inode(index)? {
+                if !inode.is_valid(&self.superblock) {
+                    set_bit(&mut invalid, index, true);
+                }
+            }
+        }
+
+        // Blocks claimed twice, one bitmap block's worth of blocks at a time.
+        for region in 0..self.superblock.bitmap_blocks() {
+            let mut claimed = [0u8; BLOCK_SIZE];
+
+            for index in 0..self.superblock.max_files {
+                let Some(inode) = self.inode(index)? else {
+                    continue;
+                };
+
+                if bit(&invalid, index) {
+                    continue;
+                }
+
+                for block in region_blocks(&inode, region) {
+                    if bit(&claimed, block % BITS_PER_BLOCK) {
+                        set_bit(&mut invalid, index, true);
+                    }
+
+                    set_bit(&mut claimed, block % BITS_PER_BLOCK, true);
+                }
+            }
+        }
+
+        for index in 0..self.superblock.max_files {
+            let Some(inode) = self.inode(index)? else {
+                continue;
+            };
+
+            if bit(&invalid, index) {
+                report.invalid_files += 1;
+
+                if repair {
+                    self.transact(|fs| fs.write_inode(index, None))?;
+                }
+
+                continue;
+            }
+
+            report.files += 1;
+
+            match self.check(&inode) {
+                Err(FsError::ChecksumMismatch) => report.corrupted_files += 1,
+                result => result?,
+            }
+        }
+
+        for region in 0..self.superblock.bitmap_blocks() {
+            let block = self.superblock.bitmap_start + region;
+            let bitmap = self.read_meta(block)?;
+            let expected = self.expected_bitmap(region, &invalid)?;
+
+            for (actual, expected) in bitmap.iter().zip(&expected) {
+                report.leaked_blocks += (actual & !expected).count_ones() as usize;
+                report.missing_blocks += (expected & !actual).count_ones() as usize;
+            }
+
+            if repair && bitmap != expected {
+                self.transact(|fs| fs.write_meta(block, &expected))?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    // What a bitmap block should hold: the metadata blocks and those of the valid files.
+    fn expected_bitmap(
+        &mut self,
+        region: u32,
+        invalid: &[u8],
+    ) -> Result<[u8; BLOCK_SIZE], FsError> {
+        let mut expected = [0u8; BLOCK_SIZE];
+        let first = region * BITS_PER_BLOCK;
+
+        for block in first..(first + BITS_PER_BLOCK).min(self.superblock.data_start) {
+            set_bit(&mut expected, block - first, true);
+        }
+
+        for index in 0..self.superblock.max_files {
+            if let Some(inode) = self.inode(index)? {
+                if !bit(invalid, index) {
+                    for block in region_blocks(&inode, region) {
+                        set_bit(&mut expected, block - first, true);
+                    }
+                }
+            }
+        }
+
+        Ok(expected)
+    }
+
+    fn transact<T>(
+        &mut self,
+        operation: impl FnOnce(&mut Self) -> Result<T, FsError>,
+    ) -> Result<T, FsError> {
+        match operation(self) {
+            Ok(value) => {
+                self.transaction
+                    .commit(&mut self.device, self.superblock.journal_start)?;
+
+                Ok(value)
+            }
+            Err(error) => {
+                self.transaction.clear();
+
+                Err(error)
+            }
+        }
+    }
+
+    fn read_meta(&mut self, block: u32) -> Result<[u8; BLOCK_SIZE], FsError> {
+        if let Some(data) = self.transaction.get(block) {
+            return Ok(*data);
+        }
+
+        let mut data = [0; BLOCK_SIZE];
+
+        self.device
+            .read_at(block as usize * BLOCK_SIZE, &mut data)?;
+
+        Ok(data)
+    }
+
+    // Operations are built to fit a transaction, running out of room means a broken layout.
+    fn write_meta(&mut self, block: u32, data: &[u8; BLOCK_SIZE]) -> Result<(), FsError> {
+        if self.transaction.put(block, data) {
+            Ok(())
+        } else {
+            Err(FsError::InvalidLayout)
+        }
+    }
+
+    fn inode(&mut self, index: u32) -> Result<Option<Inode>, FsError> {
+        let (block, offset) = self.inode_position(index);
+        let data = self.read_meta(block)?;
+
+        Ok(Inode::from_bytes(&data[offset..offset + INODE_LENGTH]))
+    }
+
+    fn write_inode(&mut self, index: u32, inode: Option<&Inode>) -> Result<(), FsError> {
+        let (block, offset) = self.inode_position(index);
+        let mut data = self.read_meta(block)?;
+        let bytes = inode.map_or([0; INODE_LENGTH], |inode| inode.to_bytes());
+
+        data[offset..offset + INODE_LENGTH].copy_from_slice(&bytes);
+        self.write_meta(block, &data)
+    }
+
+    fn inode_position(&self, index: u32) -> (u32, usize) {
+        (
+            self.superblock.inode_start + index / INODES_PER_BLOCK as u32,
+            index as usize % INODES_PER_BLOCK * INODE_LENGTH,
+        )
+    }
+
+    fn find(&mut self, name: &str) -> Result<Option<(u32, Inode)>, FsError> {
+        for index in 0..self.superblock.max_files {
+            if let Some(inode) = self.inode(index)? {
+                if inode.name() == name {
+                    return Ok(Some((index, inode)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Changing a file whose entry is broken could hand its blocks to another one, those are
+    // left for fsck.
+    fn find_valid(&mut self, name: &str) -> Result<Option<(u32, Inode)>, FsError> {
+        match self.find(name)? {
+            Some((_, inode)) if !inode.is_valid(&self.superblock) => Err(FsError::InvalidLayout),
+            found => Ok(found),
+        }
+    }
+
+    fn free_slot(&mut self) -> Result<u32, FsError> {
+        for index in 0..self.superblock.max_files {
+            if self.inode(index)?.is_none() {
+                return Ok(index);
+            }
+        }
+
+        Err(FsError::TooManyFiles)
+    }
+
+    fn check(&mut self, inode: &Inode) -> Result<(), FsError> {
+        let mut buffer = [0; BLOCK_SIZE];
+        let mut checksum = Crc32::new();
+        let mut offset = 0;
+
+        while offset < inode.size as usize {
+            let length = BLOCK_SIZE.min(inode.size as usize - offset);
+
+            for (device_offset, range) in inode.runs(offset, length) {
+                self.device.read_at(device_offset, &mut buffer[range])?;
+            }
+
+            checksum.update(&buffer[..length]);
+            offset += length;
+        }
+
+        if checksum.finalize() == inode.checksum {
+            Ok(())
+        } else {
+            Err(FsError::ChecksumMismatch)
+        }
+    }
+
+    fn write_data(&mut self, inode: &Inode, offset: usize, data: &[u8]) -> Result<(), FsError> {
+        for (device_offset, range) in inode.runs(offset, data.len()) {
+            self.device.write_at(device_offset, &data[range])?;
+        }
+
+        Ok(())
+    }
+
+    // Claims `count` free blocks for the file, starting the search at `hint` so appended
+    // blocks tend to continue the last extent.
+    fn allocate(
+        &mut self,
+        inode: &mut Inode,
+        count: usize,
+        hint: Option<u32>,
+    ) -> Result<(), FsError> {
+        let data = self.superblock.data_start..self.superblock.blocks;
+        let mut next = hint
+            .filter(|block| data.contains(block))
+            .unwrap_or(data.start);
+
+        for _ in 0..count {
+            let block = self.find_free(next)?.ok_or(FsError::DiskFull)?;
+
+            self.set_used(block, true)?;
+
+            if !inode.push_block(block) {
+                return Err(FsError::TooFragmented);
+            }
+
+            next = block + 1;
+        }
+
+        Ok(())
+    }
+
+    fn free_blocks(&mut self, inode: &Inode) -> Result<(), FsError> {
+        for extent in inode.extents() {
+            for block in extent.start..extent.start + extent.length {
+                self.set_used(block, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_free(&mut self, from: u32) -> Result<Option<u32>, FsError> {
+        let data = self.superblock.data_start..self.superblock.blocks;
+
+        for (start, end) in [(from.max(data.start), data.end), (data.start, from)] {
+            let mut block = start;
+
+            while block < end {
+                let bitmap =
+                    self.read_meta(self.superblock.bitmap_start + block / BITS_PER_BLOCK)?;
+                let region_end = ((block / BITS_PER_BLOCK + 1) * BITS_PER_BLOCK).min(end);
+
+                while block < region_end {
+                    if !bit(&bitmap, block % BITS_PER_BLOCK) {
+                        return Ok(Some(block));
+                    }
+
+                    block += 1;
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn is_used(&mut self, block: u32) -> Result<bool, FsError> {
+        let bitmap = self.read_meta(self.superblock.bitmap_start + block / BITS_PER_BLOCK)?;
+
+        Ok(bit(&bitmap, block % BITS_PER_BLOCK))
+    }
+
+    fn set_used(&mut self, block: u32, used: bool) -> Result<(), FsError> {
+        let bitmap_block = self.superblock.bitmap_start + block / BITS_PER_BLOCK;
+        let mut bitmap = self.read_meta(bitmap_block)?;
+
+        set_bit(&mut bitmap, block % BITS_PER_BLOCK, used);
+        self.write_meta(bitmap_block, &bitmap)
+    }
+}
+
+// The blocks of the file that fall into the part of the disk one bitmap block covers.
+fn region_blocks(inode: &Inode, region: u32) -> impl Iterator<Item = u32> + '_ {
+    let first = region * BITS_PER_BLOCK;
+    let end = first + BITS_PER_BLOCK;
+
+    inode
+        .extents()
+        .iter()
+        .flat_map(move |extent| extent.start.max(first)..(extent.start + extent.length).min(end))
+}
+
+fn bit(bits: &[u8], index: u32) -> bool {
+    bits[index as usize / 8] & (1 << (index % 8)) != 0
+}
+
+fn set_bit(bits: &mut [u8], index: u32, value: bool) {
+    if value {
+        bits[index as usize / 8] |= 1 << (index % 8);
+    } else {
+        bits[index as usize / 8] &= !(1 << (index % 8));
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), FsError> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH || name.chars().any(char::is_control) {
+        Err(FsError::InvalidName)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use crc32::crc32;
+
+use crate::{inode::INODES_PER_BLOCK, journal::JOURNAL_BLOCKS, BLOCK_SIZE};
+
+pub(crate) const BITS_PER_BLOCK: u32 = BLOCK_SIZE as u32 * 8;
+
+const MAGIC: [u8; 4] = *b"ONFS";
+const VERSION: u32 = 1;
+const CRC_OFFSET: usize = 32;
+
+// Written once by mkfs and never changed, the layout follows from the block and file counts:
+// the superblock, the journal, the inode table, the allocation bitmap and then data blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Superblock {
+    pub blocks: u32,
+    pub max_files: u32,
+    pub journal_start: u32,
+    pub inode_start: u32,
+    pub bitmap_start: u32,
+    pub data_start: u32,
+}
+
+impl Superblock {
+    pub fn new(blocks: u32, max_files: u32) -> Option<Self> {
+        let journal_start = 1;
+        let inode_start = journal_start + JOURNAL_BLOCKS;
+        let bitmap_start = inode_start + max_files.div_ceil(INODES_PER_BLOCK as u32);
+        let data_start = bitmap_start + blocks.div_ceil(BITS_PER_BLOCK);
+
+        (data_start < blocks).then_some(Self {
+            blocks,
+            max_files,
+            journal_start,
+            inode_start,
+            bitmap_start,
+            data_start,
+        })
+    }
+
+    pub fn bitmap_blocks(&self) -> u32 {
+        self.data_start - self.bitmap_start
+    }
+
+    pub fn to_bytes(self) -> [u8; BLOCK_SIZE] {
+        let mut bytes = [0; BLOCK_SIZE];
+        let fields = [
+            VERSION,
+            self.blocks,
+            self.max_files,
+            self.journal_start,
+            self.inode_start,
+            self.bitmap_start,
+            self.data_start,
+        ];
+
+        bytes[..4].copy_from_slice(&MAGIC);
+
+        for (i, field) in fields.into_iter().enumerate() {
+            bytes[4 + i * 4..8 + i * 4].copy_from_slice(&field.to_le_bytes());
+        }
+
+        let crc = crc32(&bytes[..CRC_OFFSET]);
+
+        bytes[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+
+        bytes
+    }
+
+    // Anything but the layout mkfs would have written for these counts is rejected.
+    pub fn from_bytes(bytes: &[u8; BLOCK_SIZE]) -> Option<Self> {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        if bytes[..4] != MAGIC
+            || u32_at(4) != VERSION
+            || crc32(&bytes[..CRC_OFFSET]) != u32_at(CRC_OFFSET)
+        {
+            return None;
+        }
+
+        let superblock = Self::new(u32_at(8), u32_at(12))?;
+
+        (superblock.to_bytes() == *bytes).then_some(superblock)
+    }
+}
@@ -0,0 +1,319 @@
+use station_fs::{block_device::MemoryDisk, FsError, StationFs, BLOCK_SIZE};
+
+const DISK: usize = 256 * 1024;
+
+fn contents(seed: u32, length: usize) -> Vec<u8> {
+    (0..length)
+        .map(|i| (i as u32).wrapping_mul(seed * 2 + 1).rotate_right(3) as u8)
+        .collect()
+}
+
+fn read_all(fs: &mut StationFs<MemoryDisk>, name: &str) -> Vec<u8> {
+    let mut data = vec![0; fs.metadata(name).unwrap().size as usize];
+
+    assert_eq!(fs.read_file(name, &mut data).unwrap(), data.len());
+
+    data
+}
+
+// Every file and its contents, sorted by name.
+fn snapshot(fs: &mut StationFs<MemoryDisk>) -> Vec<(String, Vec<u8>)> {
+    let names: Vec<String> = fs
+        .files()
+        .map(|info| info.unwrap().name().to_string())
+        .collect();
+    let mut files: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            let data = read_all(fs, &name);
+
+            (name, data)
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn layout_field(data: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize
+}
+
+#[test]
+fn mkfs_and_mount() {
+    let mut data = vec![0; DISK];
+
+    assert_eq!(
+        StationFs::mount(MemoryDisk::new(&mut data)).err(),
+        Some(FsError::NotFormatted)
+    );
+
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 16).unwrap();
+    let stats = fs.stats().unwrap();
+
+    assert_eq!(stats.blocks, DISK / BLOCK_SIZE);
+    assert_eq!(stats.files, 0);
+    assert_eq!(stats.max_files, 16);
+    assert!(stats.free_blocks > stats.blocks - 32);
+
+    fs.write("crew.log", b"day 1: all quiet").unwrap();
+    fs.into_inner();
+
+    let mut fs = StationFs::mount(MemoryDisk::new(&mut data)).unwrap();
+
+    assert_eq!(read_all(&mut fs, "crew.log"), b"day 1: all quiet");
+    assert_eq!(fs.stats().unwrap().files, 1);
+    assert!(fs.fsck(false).unwrap().is_clean());
+    assert_eq!(
+        StationFs::mkfs(MemoryDisk::new(&mut vec![0; 8 * 1024]), 16).err(),
+        Some(FsError::UnsupportedSize)
+    );
+}
+
+#[test]
+fn write_replaces_contents() {
+    let mut data = vec![0; DISK];
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 16).unwrap();
+    let free = fs.stats().unwrap().free_blocks;
+    let long = contents(1, 3000);
+
+    fs.write("sensors.dat", &long).unwrap();
+    assert_eq!(fs.stats().unwrap().free_blocks, free - 6);
+    assert_eq!(read_all(&mut fs, "sensors.dat"), long);
+
+    fs.write("sensors.dat", b"reset").unwrap();
+    assert_eq!(fs.stats().unwrap().free_blocks, free - 1);
+    assert_eq!(read_all(&mut fs, "sensors.dat"), b"reset");
+
+    let mut buffer = [0; 3];
+
+    assert_eq!(fs.read("sensors.dat", 3, &mut buffer).unwrap(), 2);
+    assert_eq!(&buffer[..2], b"et");
+
+    fs.write("empty", &[]).unwrap();
+    assert_eq!(read_all(&mut fs, "empty"), b"");
+    assert_eq!(fs.stats().unwrap().free_blocks, free - 1);
+}
+
+#[test]
+fn append_extends_file() {
+    let mut data = vec![0; DISK];
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 16).unwrap();
+    let free = fs.stats().unwrap().free_blocks;
+    let expected = contents(2, 7000);
+
+    // Odd pieces, so appends straddle block boundaries.
+    for chunk in expected.chunks(700) {
+        fs.append("events.log", chunk).unwrap();
+    }
+
+    fs.verify("events.log").unwrap();
+    assert_eq!(read_all(&mut fs, "events.log"), expected);
+    assert_eq!(
+        fs.stats().unwrap().free_blocks,
+        free - expected.len().div_ceil(BLOCK_SIZE)
+    );
+
+    let mut other = vec![0; DISK];
+    let mut copy = StationFs::mkfs(MemoryDisk::new(&mut other), 16).unwrap();
+
+    copy.write("events.log", &expected).unwrap();
+    assert_eq!(
+        copy.metadata("events.log").unwrap().checksum,
+        fs.metadata("events.log").unwrap().checksum
+    );
+}
+
+#[test]
+fn rename_and_remove() {
+    let mut data = vec![0; DISK];
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 4).unwrap();
+    let free = fs.stats().unwrap().free_blocks;
+
+    fs.write("a", b"alpha").unwrap();
+    fs.write("b", b"beta").unwrap();
+
+    assert_eq!(fs.rename("a", "b"), Err(FsError::AlreadyExists));
+    assert_eq!(fs.rename("c", "d"), Err(FsError::NotFound));
+    fs.rename("a", "c").unwrap();
+    assert_eq!(fs.metadata("a"), Err(FsError::NotFound));
+    assert_eq!(read_all(&mut fs, "c"), b"alpha");
+
+    fs.remove("b").unwrap();
+    fs.remove("c").unwrap();
+    assert_eq!(fs.remove("c"), Err(FsError::NotFound));
+    assert_eq!(fs.stats().unwrap().free_blocks, free);
+    assert_eq!(fs.files().count(), 0);
+}
+
+#[test]
+fn rejects_bad_requests() {
+    let mut data = vec![0; DISK];
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 2).unwrap();
+
+    assert_eq!(fs.write("", b"x"), Err(FsError::InvalidName));
+    assert_eq!(fs.write(&"n".repeat(33), b"x"), Err(FsError::InvalidName));
+    assert_eq!(fs.write("a\nb", b"x"), Err(FsError::InvalidName));
+
+    fs.write("one", b"1").unwrap();
+    fs.write("two", b"2").unwrap();
+    assert_eq!(fs.write("three", b"3"), Err(FsError::TooManyFiles));
+    assert_eq!(fs.read_file("two", &mut []), Err(FsError::BufferTooSmall));
+
+    let free = fs.stats().unwrap().free_blocks;
+    let huge = vec![0; (free + 1) * BLOCK_SIZE];
+
+    assert_eq!(fs.write("one", &huge), Err(FsError::DiskFull));
+    assert_eq!(fs.append("one", &huge), Err(FsError::DiskFull));
+    assert_eq!(fs.stats().unwrap().free_blocks, free);
+    assert_eq!(read_all(&mut fs, "one"), b"1");
+    assert!(fs.fsck(false).unwrap().is_clean());
+}
+
+#[test]
+fn detects_corrupted_contents() {
+    let mut data = vec![0; DISK];
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 16).unwrap();
+
+    fs.write("orbit.dat", b"apogee 412 km, perigee 398 km")
+        .unwrap();
+    fs.into_inner();
+
+    let position = data
+        .windows(6)
+        .position(|bytes| bytes == b"apogee")
+        .unwrap();
+
+    data[position] ^= 1;
+
+    let mut fs = StationFs::mount(MemoryDisk::new(&mut data)).unwrap();
+
+    assert_eq!(
+        fs.read_file("orbit.dat", &mut [0; 64]),
+        Err(FsError::ChecksumMismatch)
+    );
+    assert_eq!(fs.verify("orbit.dat"), Err(FsError::ChecksumMismatch));
+
+    let report = fs.fsck(true).unwrap();
+
+    assert_eq!(report.files, 1);
+    assert_eq!(report.corrupted_files, 1);
+}
+
+#[test]
+fn fsck_repairs_metadata() {
+    let mut data = vec![0; DISK];
+    let mut fs = StationFs::mkfs(MemoryDisk::new(&mut data), 16).unwrap();
+
+    fs.write("good", &contents(3, 2000)).unwrap();
+    fs.write("bad", &contents(4, 600)).unwrap();
+
+    let free = fs.stats().unwrap().free_blocks;
+
+    fs.into_inner();
+
+    let inode_start = layout_field(&data, 20) * BLOCK_SIZE;
+    let bitmap_start = layout_field(&data, 24) * BLOCK_SIZE;
+
+    // Point the second file at the blocks of the first and lose the bitmap.
+    let extent = inode_start + 48;
+    let start = data[extent..extent + 4].to_vec();
+
+    data[inode_start + 128 + 48..inode_start + 128 + 52].copy_from_slice(&start);
+    data[bitmap_start..bitmap_start + BLOCK_SIZE].fill(0);
+
+    let mut fs = StationFs::mount(MemoryDisk::new(&mut data)).unwrap();
+    let report = fs.fsck(false).unwrap();
+
+    assert_eq!(report.files, 1);
+    assert_eq!(report.invalid_files, 1);
+    assert!(report.missing_blocks > 4);
+    assert!(!report.is_clean());
+
+    fs.fsck(true).unwrap();
+
+    let report = fs.fsck(false).unwrap();
+
+    assert!(report.is_clean());
+    assert_eq!(report.files, 1);
+    assert_eq!(fs.metadata("bad"), Err(FsError::NotFound));
+    assert_eq!(read_all(&mut fs, "good"), contents(3, 2000));
+    assert_eq!(fs.stats().unwrap().free_blocks, free + 2);
+}
+
+#[test]
+fn survives_power_cuts() {
+    enum Op {
+        Write(&'static str, Vec<u8>),
+        Append(&'static str, Vec<u8>),
+        Rename(&'static str, &'static str),
+        Remove(&'static str),
+    }
+
+    let script = [
+        Op::Write("air.log", contents(5, 1200)),
+        Op::Append("air.log", contents(6, 900)),
+        Op::Write("crew", b"4 aboard".to_vec()),
+        Op::Write("air.log", contents(7, 300)),
+        Op::Rename("crew", "crew.old"),
+        Op::Append("water.log", contents(8, 700)),
+        Op::Remove("crew.old"),
+    ];
+    let run = |fs: &mut StationFs<MemoryDisk>, op: &Op| {
+        let _ = match op {
+            Op::Write(name, data) => fs.write(name, data),
+            Op::Append(name, data) => fs.append(name, data),
+            Op::Rename(from, to) => fs.rename(from, to),
+            Op::Remove(name) => fs.remove(name),
+        };
+    };
+
+    let mut image = vec![0; DISK];
+
+    StationFs::mkfs(MemoryDisk::new(&mut image), 8).unwrap();
+
+    // The state after each prefix of the script.
+    let mut states = Vec::new();
+    let mut data = image.clone();
+    let mut fs = StationFs::mount(MemoryDisk::new(&mut data)).unwrap();
+
+    states.push(snapshot(&mut fs));
+
+    for op in &script {
+        run(&mut fs, op);
+        states.push(snapshot(&mut fs));
+    }
+
+    let mut cut = 0;
+
+    loop {
+        let mut data = image.clone();
+        let mut disk = MemoryDisk::new(&mut data);
+
+        disk.cut_power_after(cut);
+
+        let mut fs = StationFs::mount(disk).unwrap();
+
+        for op in &script {
+            run(&mut fs, op);
+        }
+
+        let mut disk = fs.into_inner();
+        let finished = disk.is_powered();
+
+        disk.restore_power();
+
+        let mut fs = StationFs::mount(disk).unwrap();
+        let state = snapshot(&mut fs);
+
+        assert!(states.contains(&state), "cut after {cut} bytes");
+        assert!(fs.fsck(false).unwrap().is_clean(), "cut after {cut} bytes");
+
+        if finished {
+            assert_eq!(&state, states.last().unwrap());
+            break;
+        }
+
+        cut += 61;
+    }
+}
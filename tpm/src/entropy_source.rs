@@ -0,0 +1,11 @@
+use crate::Tpm;
+
+pub trait EntropySource {
+    fn entropy_byte(&mut self) -> u8;
+}
+
+impl EntropySource for Tpm {
+    fn entropy_byte(&mut self) -> u8 {
+        unsafe { self.random_byte() }
+    }
+}
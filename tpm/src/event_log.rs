@@ -0,0 +1,105 @@
+use crate::{Digest, PcrBank, TpmError};
+
+pub const MAX_EVENTS: usize = 64;
+pub const EVENT_LABEL_LENGTH: usize = 32;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventKind {
+    Firmware = 0,
+    FlashContents,
+    Program,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    pub pcr: u8,
+    pub kind: EventKind,
+    pub digest: Digest,
+    label: [u8; EVENT_LABEL_LENGTH],
+    label_length: usize,
+}
+
+impl Event {
+    // Labels longer than `EVENT_LABEL_LENGTH` are truncated on a character boundary.
+    pub fn new(pcr: u8, kind: EventKind, digest: Digest, label: &str) -> Self {
+        let mut label_length = label.len().min(EVENT_LABEL_LENGTH);
+
+        while !label.is_char_boundary(label_length) {
+            label_length -= 1;
+        }
+
+        let mut bytes = [0; EVENT_LABEL_LENGTH];
+
+        bytes[..label_length].copy_from_slice(&label.as_bytes()[..label_length]);
+
+        Self {
+            pcr,
+            kind,
+            digest,
+            label: bytes,
+            label_length,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        core::str::from_utf8(&self.label[..self.label_length]).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    events: [Option<Event>; MAX_EVENTS],
+    len: usize,
+}
+
+impl EventLog {
+    pub const fn new() -> Self {
+        Self {
+            events: [None; MAX_EVENTS],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, event: Event) -> Result<(), TpmError> {
+        let slot = self
+            .events
+            .get_mut(self.len)
+            .ok_or(TpmError::EventLogFull)?;
+
+        *slot = Some(event);
+        self.len += 1;
+
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events[..self.len].iter().flatten()
+    }
+
+    // Recomputes the PCRs from the log, a log that was tampered with won't match the bank.
+    pub fn replay(&self) -> Result<PcrBank, TpmError> {
+        let mut bank = PcrBank::new();
+
+        for event in self.iter() {
+            bank.extend(event.pcr, &event.digest)?;
+        }
+
+        Ok(bank)
+    }
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,80 @@
+use crate::TpmError;
+
+pub const APT_WINDOW: usize = 512;
+pub const STARTUP_SAMPLES: usize = 1024;
+pub const DEFAULT_MIN_ENTROPY: u8 = 4;
+
+// Cutoffs for 1..=8 bits of min-entropy per byte with a false positive rate of 2^-20, as
+// defined by NIST SP 800-90B section 4.4.
+const RCT_CUTOFFS: [usize; 8] = [21, 11, 8, 6, 5, 5, 4, 4];
+const APT_CUTOFFS: [usize; 8] = [311, 177, 103, 62, 39, 25, 18, 13];
+
+#[derive(Debug, Clone)]
+pub struct HealthTests {
+    rct_cutoff: usize,
+    apt_cutoff: usize,
+    last: Option<u8>,
+    repetitions: usize,
+    apt_reference: u8,
+    apt_count: usize,
+    apt_samples: usize,
+    failure: Option<TpmError>,
+}
+
+impl HealthTests {
+    pub fn new(min_entropy: u8) -> Self {
+        let bits = min_entropy.clamp(1, 8) as usize - 1;
+
+        Self {
+            rct_cutoff: RCT_CUTOFFS[bits],
+            apt_cutoff: APT_CUTOFFS[bits],
+            last: None,
+            repetitions: 0,
+            apt_reference: 0,
+            apt_count: 0,
+            apt_samples: 0,
+            failure: None,
+        }
+    }
+
+    pub fn status(&self) -> Result<(), TpmError> {
+        self.failure.map_or(Ok(()), Err)
+    }
+
+    // Failures are sticky, a source that failed once is never trusted again.
+    pub fn feed(&mut self, sample: u8) -> Result<(), TpmError> {
+        self.status()?;
+
+        if self.last == Some(sample) {
+            self.repetitions += 1;
+
+            if self.repetitions >= self.rct_cutoff {
+                self.failure = Some(TpmError::RepetitionCount);
+            }
+        } else {
+            self.last = Some(sample);
+            self.repetitions = 1;
+        }
+
+        if self.apt_samples == 0 {
+            self.apt_reference = sample;
+            self.apt_count = 1;
+        } else if sample == self.apt_reference {
+            self.apt_count += 1;
+
+            if self.apt_count >= self.apt_cutoff {
+                self.failure = Some(TpmError::AdaptiveProportion);
+            }
+        }
+
+        self.apt_samples = (self.apt_samples + 1) % APT_WINDOW;
+
+        self.status()
+    }
+}
+
+impl Default for HealthTests {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_ENTROPY)
+    }
+}
@@ -1,7 +1,33 @@
 #![allow(clippy::missing_safety_doc)]
 #![no_std]
 
+mod entropy_source;
+mod event_log;
+mod health_tests;
+mod pcr_bank;
+mod sealed_blob;
+mod secure_rng;
+mod software_tpm;
+mod tpm_error;
+mod tpm_rng;
+mod uuid;
+
+pub use entropy_source::EntropySource;
+pub use event_log::{Event, EventKind, EventLog, EVENT_LABEL_LENGTH, MAX_EVENTS};
+pub use health_tests::{HealthTests, APT_WINDOW, DEFAULT_MIN_ENTROPY, STARTUP_SAMPLES};
 use mmio::Mmio;
+pub use pcr_bank::{
+    Digest, PcrBank, PcrSelection, DIGEST_LENGTH, PCR_COUNT, PCR_FIRMWARE, PCR_FLASH, PCR_PROGRAMS,
+};
+pub use rand_core;
+pub use sealed_blob::{
+    SealedBlob, MAX_SEALED_LENGTH, NONCE_LENGTH, SEALED_BLOB_LENGTH, TAG_LENGTH,
+};
+pub use secure_rng::{SecureRng, RESEED_INTERVAL};
+pub use software_tpm::{SoftwareTpm, SRK_LENGTH, SRK_RECORD_LENGTH};
+pub use tpm_error::TpmError;
+pub use tpm_rng::TpmRng;
+pub use uuid::{Uuid, UUID_LENGTH};
 
 pub const MMIO_ADDRESS: usize = 0x10010000;
 const RANDOM_BYTE: usize = 0x1;
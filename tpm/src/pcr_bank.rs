@@ -0,0 +1,88 @@
+use crypto::Sha256;
+
+use crate::TpmError;
+
+pub const PCR_COUNT: usize = 8;
+pub const DIGEST_LENGTH: usize = 32;
+pub const PCR_FIRMWARE: u8 = 0;
+pub const PCR_FLASH: u8 = 1;
+pub const PCR_PROGRAMS: u8 = 2;
+
+pub type Digest = [u8; DIGEST_LENGTH];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PcrSelection(pub u8);
+
+impl PcrSelection {
+    pub fn with(self, pcr: u8) -> Result<Self, TpmError> {
+        Ok(Self(self.0 | Self::bit(pcr)?))
+    }
+
+    pub fn contains(&self, pcr: u8) -> Result<bool, TpmError> {
+        Ok(self.0 & Self::bit(pcr)? != 0)
+    }
+
+    fn bit(pcr: u8) -> Result<u8, TpmError> {
+        if (pcr as usize) < PCR_COUNT {
+            Ok(1 << pcr)
+        } else {
+            Err(TpmError::InvalidPcr)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcrBank {
+    pcrs: [Digest; PCR_COUNT],
+}
+
+impl PcrBank {
+    pub const fn new() -> Self {
+        Self {
+            pcrs: [[0; DIGEST_LENGTH]; PCR_COUNT],
+        }
+    }
+
+    pub fn read(&self, pcr: u8) -> Result<Digest, TpmError> {
+        self.pcrs
+            .get(pcr as usize)
+            .copied()
+            .ok_or(TpmError::InvalidPcr)
+    }
+
+    // PCR = SHA-256(PCR || measurement), so a PCR value commits to every measurement and to
+    // their order.
+    pub fn extend(&mut self, pcr: u8, measurement: &Digest) -> Result<(), TpmError> {
+        let value = self
+            .pcrs
+            .get_mut(pcr as usize)
+            .ok_or(TpmError::InvalidPcr)?;
+        let mut hasher = Sha256::new();
+
+        hasher.update(value);
+        hasher.update(measurement);
+        *value = hasher.finalize();
+
+        Ok(())
+    }
+
+    pub fn composite(&self, selection: PcrSelection) -> Digest {
+        let mut hasher = Sha256::new();
+
+        hasher.update(&[selection.0]);
+
+        for (pcr, value) in self.pcrs.iter().enumerate() {
+            if selection.contains(pcr as u8) == Ok(true) {
+                hasher.update(value);
+            }
+        }
+
+        hasher.finalize()
+    }
+}
+
+impl Default for PcrBank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
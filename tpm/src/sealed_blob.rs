@@ -0,0 +1,66 @@
+pub use crypto::{NONCE_LENGTH, TAG_LENGTH};
+
+use crate::{Digest, PcrSelection, TpmError, DIGEST_LENGTH};
+
+pub const MAX_SEALED_LENGTH: usize = 64;
+pub const SEALED_BLOB_LENGTH: usize = HEADER_LENGTH + NONCE_LENGTH + MAX_SEALED_LENGTH + TAG_LENGTH;
+
+const MAGIC: [u8; 4] = *b"SEAL";
+const HEADER_LENGTH: usize = MAGIC.len() + 2 + DIGEST_LENGTH;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedBlob {
+    pub selection: PcrSelection,
+    pub length: u8,
+    pub policy: Digest,
+    pub nonce: [u8; NONCE_LENGTH],
+    pub ciphertext: [u8; MAX_SEALED_LENGTH],
+    pub tag: [u8; TAG_LENGTH],
+}
+
+impl SealedBlob {
+    // Authenticated alongside the ciphertext, so the selection and policy can't be swapped.
+    pub fn header(&self) -> [u8; HEADER_LENGTH] {
+        let mut header = [0; HEADER_LENGTH];
+
+        header[..MAGIC.len()].copy_from_slice(&MAGIC);
+        header[MAGIC.len()] = self.selection.0;
+        header[MAGIC.len() + 1] = self.length;
+        header[MAGIC.len() + 2..].copy_from_slice(&self.policy);
+
+        header
+    }
+
+    pub fn to_bytes(&self) -> [u8; SEALED_BLOB_LENGTH] {
+        let mut bytes = [0; SEALED_BLOB_LENGTH];
+        let (header, rest) = bytes.split_at_mut(HEADER_LENGTH);
+        let (nonce, rest) = rest.split_at_mut(NONCE_LENGTH);
+        let (ciphertext, tag) = rest.split_at_mut(MAX_SEALED_LENGTH);
+
+        header.copy_from_slice(&self.header());
+        nonce.copy_from_slice(&self.nonce);
+        ciphertext.copy_from_slice(&self.ciphertext);
+        tag.copy_from_slice(&self.tag);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; SEALED_BLOB_LENGTH]) -> Result<Self, TpmError> {
+        let (header, rest) = bytes.split_at(HEADER_LENGTH);
+        let (nonce, rest) = rest.split_at(NONCE_LENGTH);
+        let (ciphertext, tag) = rest.split_at(MAX_SEALED_LENGTH);
+
+        if header[..MAGIC.len()] != MAGIC || header[MAGIC.len() + 1] as usize > MAX_SEALED_LENGTH {
+            return Err(TpmError::InvalidBlob);
+        }
+
+        Ok(Self {
+            selection: PcrSelection(header[MAGIC.len()]),
+            length: header[MAGIC.len() + 1],
+            policy: header[MAGIC.len() + 2..].try_into().unwrap(),
+            nonce: nonce.try_into().unwrap(),
+            ciphertext: ciphertext.try_into().unwrap(),
+            tag: tag.try_into().unwrap(),
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use core::ops::Range;
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{impls, CryptoRng, Error, RngCore, SeedableRng};
+
+use crate::{EntropySource, Tpm, TpmError, TpmRng, Uuid, UUID_LENGTH};
+
+pub const RESEED_INTERVAL: usize = 1024 * 1024;
+const SEED_LENGTH: usize = 32;
+
+// ChaCha20 seeded from the TPM. Reseeds mix fresh TPM entropy with the current stream, so a
+// broken entropy source can't make the output weaker than it already was. Once the TPM fails
+// a health test the generator refuses to produce any more output.
+pub struct SecureRng<S: EntropySource = Tpm> {
+    tpm: TpmRng<S>,
+    rng: ChaCha20Rng,
+    generated: usize,
+}
+
+impl<S: EntropySource> SecureRng<S> {
+    pub fn new(source: S) -> Result<Self, TpmError> {
+        Self::from_tpm_rng(TpmRng::new(source)?)
+    }
+
+    pub fn from_tpm_rng(mut tpm: TpmRng<S>) -> Result<Self, TpmError> {
+        let mut seed = [0; SEED_LENGTH];
+
+        tpm.try_fill(&mut seed)?;
+
+        Ok(Self {
+            tpm,
+            rng: ChaCha20Rng::from_seed(seed),
+            generated: 0,
+        })
+    }
+
+    pub fn health(&self) -> Result<(), TpmError> {
+        self.tpm.health()
+    }
+
+    pub fn reseed(&mut self) -> Result<(), TpmError> {
+        let mut seed = [0; SEED_LENGTH];
+        let mut entropy = [0; SEED_LENGTH];
+
+        self.tpm.try_fill(&mut entropy)?;
+        self.rng.fill_bytes(&mut seed);
+
+        for (s, e) in seed.iter_mut().zip(entropy) {
+            *s ^= e;
+        }
+
+        self.rng = ChaCha20Rng::from_seed(seed);
+        self.generated = 0;
+
+        Ok(())
+    }
+
+    pub fn try_fill(&mut self, dest: &mut [u8]) -> Result<(), TpmError> {
+        self.health()?;
+        self.rng.fill_bytes(dest);
+        self.generated += dest.len();
+
+        // A failed reseed is reported by the next call through `health`.
+        if self.generated >= RESEED_INTERVAL {
+            let _ = self.reseed();
+        }
+
+        Ok(())
+    }
+
+    // Unbiased, uses rejection sampling. Returns `range.start` for an empty range.
+    pub fn range_u64(&mut self, range: Range<u64>) -> Result<u64, TpmError> {
+        let span = range.end.saturating_sub(range.start);
+
+        if span == 0 {
+            return Ok(range.start);
+        }
+
+        let zone = u64::MAX - (u64::MAX - span + 1) % span;
+
+        loop {
+            let mut bytes = [0; core::mem::size_of::<u64>()];
+
+            self.try_fill(&mut bytes)?;
+
+            let value = u64::from_le_bytes(bytes);
+
+            if value <= zone {
+                return Ok(range.start + value % span);
+            }
+        }
+    }
+
+    pub fn range_u32(&mut self, range: Range<u32>) -> Result<u32, TpmError> {
+        self.range_u64(range.start as u64..range.end as u64)
+            .map(|value| value as u32)
+    }
+
+    pub fn range_usize(&mut self, range: Range<usize>) -> Result<usize, TpmError> {
+        self.range_u64(range.start as u64..range.end as u64)
+            .map(|value| value as usize)
+    }
+
+    pub fn uuid_v4(&mut self) -> Result<Uuid, TpmError> {
+        let mut bytes = [0; UUID_LENGTH];
+
+        self.try_fill(&mut bytes)?;
+
+        Ok(Uuid::new_v4(bytes))
+    }
+}
+
+impl<S: EntropySource> RngCore for SecureRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(error) = self.try_fill(dest) {
+            panic!("{error}");
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        Ok(self.try_fill(dest)?)
+    }
+}
+
+impl<S: EntropySource> CryptoRng for SecureRng<S> {}
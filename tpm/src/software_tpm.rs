@@ -0,0 +1,211 @@
+use core::ops::Range;
+
+use crypto::{decrypt_in_place, encrypt_in_place, sha256, HmacSha256, Key, Nonce, Sha256};
+use flash::{FlashStorage, SECTOR_SIZE};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    Digest, Event, EventKind, EventLog, PcrBank, PcrSelection, SealedBlob, TpmError,
+    MAX_SEALED_LENGTH, NONCE_LENGTH, SEALED_BLOB_LENGTH, TAG_LENGTH,
+};
+
+pub const SRK_LENGTH: usize = 32;
+pub const SRK_RECORD_LENGTH: usize = SRK_MAGIC.len() + SRK_LENGTH;
+
+const SRK_MAGIC: [u8; 4] = *b"SRK0";
+const SEAL_CONTEXT: &[u8] = b"onyx-seal";
+const FLASH_CHUNK: usize = 256;
+
+// A TPM implemented in software, a functional stand-in with no security. PCRs and the event log
+// behave like the real thing, but the storage root key sits on flash in plaintext and the sealing
+// key is derived from it and the policy stored in the blob. Anything that can read flash can
+// unseal every blob, the PCR check only keeps honest firmware from unsealing in the wrong state.
+pub struct SoftwareTpm {
+    pub pcrs: PcrBank,
+    pub log: EventLog,
+    srk: [u8; SRK_LENGTH],
+}
+
+impl SoftwareTpm {
+    pub fn new(srk: [u8; SRK_LENGTH]) -> Self {
+        Self {
+            pcrs: PcrBank::new(),
+            log: EventLog::new(),
+            srk,
+        }
+    }
+
+    // Loads the storage root key kept at the start of flash sector `sector`, creating it on
+    // first boot. The key has the sector to itself and is not protected in any way, the board
+    // has no storage that firmware can't read.
+    pub fn load_or_create(
+        flash: &mut impl FlashStorage,
+        sector: usize,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self, TpmError> {
+        let mut record = [0; SRK_RECORD_LENGTH];
+
+        flash.read(sector * SECTOR_SIZE, &mut record)?;
+
+        let (magic, key) = record.split_at_mut(SRK_MAGIC.len());
+
+        if magic != SRK_MAGIC {
+            magic.copy_from_slice(&SRK_MAGIC);
+            rng.try_fill_bytes(key).map_err(|_| TpmError::RngFailure)?;
+            write_sector(flash, sector, &record)?;
+        }
+
+        Ok(Self::new(record[SRK_MAGIC.len()..].try_into().unwrap()))
+    }
+
+    pub fn measure(
+        &mut self,
+        pcr: u8,
+        kind: EventKind,
+        label: &str,
+        data: &[u8],
+    ) -> Result<Digest, TpmError> {
+        self.extend(pcr, kind, label, sha256(data))
+    }
+
+    pub fn measure_flash(
+        &mut self,
+        pcr: u8,
+        label: &str,
+        flash: &impl FlashStorage,
+        range: Range<usize>,
+    ) -> Result<Digest, TpmError> {
+        let mut hasher = Sha256::new();
+        let mut buffer = [0; FLASH_CHUNK];
+        let mut offset = range.start;
+
+        while offset < range.end {
+            let chunk = &mut buffer[..FLASH_CHUNK.min(range.end - offset)];
+
+            flash.read(offset, chunk)?;
+            hasher.update(&*chunk);
+            offset += chunk.len();
+        }
+
+        self.extend(pcr, EventKind::FlashContents, label, hasher.finalize())
+    }
+
+    pub fn extend(
+        &mut self,
+        pcr: u8,
+        kind: EventKind,
+        label: &str,
+        digest: Digest,
+    ) -> Result<Digest, TpmError> {
+        // Checked before logging, an event for a PCR that doesn't exist would break replay.
+        self.pcrs.read(pcr)?;
+        self.log.push(Event::new(pcr, kind, digest, label))?;
+        self.pcrs.extend(pcr, &digest)?;
+
+        Ok(digest)
+    }
+
+    pub fn verify_log(&self) -> Result<(), TpmError> {
+        if self.log.replay()? == self.pcrs {
+            Ok(())
+        } else {
+            Err(TpmError::LogMismatch)
+        }
+    }
+
+    pub fn seal(
+        &self,
+        selection: PcrSelection,
+        secret: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<SealedBlob, TpmError> {
+        if secret.len() > MAX_SEALED_LENGTH {
+            return Err(TpmError::SecretTooLarge);
+        }
+
+        let mut blob = SealedBlob {
+            selection,
+            length: secret.len() as u8,
+            policy: self.pcrs.composite(selection),
+            nonce: [0; NONCE_LENGTH],
+            ciphertext: [0; MAX_SEALED_LENGTH],
+            tag: [0; TAG_LENGTH],
+        };
+
+        rng.try_fill_bytes(&mut blob.nonce)
+            .map_err(|_| TpmError::RngFailure)?;
+        blob.ciphertext[..secret.len()].copy_from_slice(secret);
+
+        blob.tag = encrypt_in_place(
+            &self.key(&blob.policy, selection),
+            &Nonce(blob.nonce),
+            &blob.header(),
+            &mut blob.ciphertext[..secret.len()],
+        )
+        .map_err(|_| TpmError::SecretTooLarge)?;
+
+        Ok(blob)
+    }
+
+    pub fn unseal(&self, blob: &SealedBlob, secret: &mut [u8]) -> Result<usize, TpmError> {
+        let length = blob.length as usize;
+
+        if length > MAX_SEALED_LENGTH || secret.len() < length {
+            return Err(TpmError::SecretTooLarge);
+        }
+
+        if self.pcrs.composite(blob.selection) != blob.policy {
+            return Err(TpmError::PcrMismatch);
+        }
+
+        let mut plaintext = blob.ciphertext;
+
+        decrypt_in_place(
+            &self.key(&blob.policy, blob.selection),
+            &Nonce(blob.nonce),
+            &blob.header(),
+            &mut plaintext[..length],
+            &blob.tag,
+        )
+        .map_err(|_| TpmError::AuthenticationFailed)?;
+
+        secret[..length].copy_from_slice(&plaintext[..length]);
+
+        Ok(length)
+    }
+
+    // Like the storage root key, a blob has its flash sector to itself.
+    pub fn store_blob(
+        flash: &mut impl FlashStorage,
+        sector: usize,
+        blob: &SealedBlob,
+    ) -> Result<(), TpmError> {
+        write_sector(flash, sector, &blob.to_bytes())
+    }
+
+    pub fn load_blob(flash: &impl FlashStorage, sector: usize) -> Result<SealedBlob, TpmError> {
+        let mut bytes = [0; SEALED_BLOB_LENGTH];
+
+        flash.read(sector * SECTOR_SIZE, &mut bytes)?;
+
+        SealedBlob::from_bytes(&bytes)
+    }
+
+    fn key(&self, policy: &Digest, selection: PcrSelection) -> Key {
+        let mut mac = HmacSha256::new(&self.srk);
+
+        mac.update(SEAL_CONTEXT);
+        mac.update(&[selection.0]);
+        mac.update(policy);
+
+        Key::from_bytes(mac.finalize())
+    }
+}
+
+// Flash can only clear bits, the sector has to be erased before it takes new data.
+fn write_sector(flash: &mut impl FlashStorage, sector: usize, data: &[u8]) -> Result<(), TpmError> {
+    flash.erase_sector(sector)?;
+    flash.write(sector * SECTOR_SIZE, data)?;
+
+    Ok(())
+}
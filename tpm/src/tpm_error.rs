@@ -0,0 +1,67 @@
+use core::{fmt::Display, num::NonZeroU32};
+
+use flash::FlashError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TpmError {
+    RepetitionCount,
+    AdaptiveProportion,
+    RngFailure,
+    InvalidPcr,
+    EventLogFull,
+    LogMismatch,
+    SecretTooLarge,
+    InvalidBlob,
+    PcrMismatch,
+    AuthenticationFailed,
+    Flash(FlashError),
+}
+
+impl Display for TpmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TpmError::RepetitionCount => {
+                f.write_str("TPM entropy failed the repetition count test")
+            }
+            TpmError::AdaptiveProportion => {
+                f.write_str("TPM entropy failed the adaptive proportion test")
+            }
+            TpmError::RngFailure => f.write_str("random number generator failed"),
+            TpmError::InvalidPcr => f.write_str("invalid PCR index"),
+            TpmError::EventLogFull => f.write_str("event log is full"),
+            TpmError::LogMismatch => f.write_str("event log does not match PCRs"),
+            TpmError::SecretTooLarge => f.write_str("secret is too large"),
+            TpmError::InvalidBlob => f.write_str("invalid sealed blob"),
+            TpmError::PcrMismatch => f.write_str("PCRs do not match the sealing policy"),
+            TpmError::AuthenticationFailed => f.write_str("sealed blob failed authentication"),
+            TpmError::Flash(error) => write!(f, "flash error: {error}"),
+        }
+    }
+}
+
+impl From<FlashError> for TpmError {
+    fn from(value: FlashError) -> Self {
+        TpmError::Flash(value)
+    }
+}
+
+impl From<TpmError> for rand_core::Error {
+    fn from(value: TpmError) -> Self {
+        let index = match value {
+            TpmError::RepetitionCount => 0,
+            TpmError::AdaptiveProportion => 1,
+            TpmError::RngFailure => 2,
+            TpmError::InvalidPcr => 3,
+            TpmError::EventLogFull => 4,
+            TpmError::LogMismatch => 5,
+            TpmError::SecretTooLarge => 6,
+            TpmError::InvalidBlob => 7,
+            TpmError::PcrMismatch => 8,
+            TpmError::AuthenticationFailed => 9,
+            TpmError::Flash(_) => 10,
+        };
+        let code = rand_core::Error::CUSTOM_START + index;
+
+        rand_core::Error::from(NonZeroU32::new(code).unwrap())
+    }
+}
@@ -0,0 +1,72 @@
+use rand_core::{impls, CryptoRng, Error, RngCore};
+
+use crate::{EntropySource, HealthTests, Tpm, TpmError, DEFAULT_MIN_ENTROPY, STARTUP_SAMPLES};
+
+// Reads every byte straight from the entropy source, slow but useful for seeding. All bytes
+// pass through the health tests before being handed out.
+#[derive(Debug, Clone)]
+pub struct TpmRng<S: EntropySource = Tpm> {
+    pub source: S,
+    health: HealthTests,
+}
+
+impl<S: EntropySource> TpmRng<S> {
+    pub fn new(source: S) -> Result<Self, TpmError> {
+        Self::with_min_entropy(source, DEFAULT_MIN_ENTROPY)
+    }
+
+    pub fn with_min_entropy(source: S, min_entropy: u8) -> Result<Self, TpmError> {
+        let mut rng = Self {
+            source,
+            health: HealthTests::new(min_entropy),
+        };
+
+        for _ in 0..STARTUP_SAMPLES {
+            rng.sample()?;
+        }
+
+        Ok(rng)
+    }
+
+    pub fn health(&self) -> Result<(), TpmError> {
+        self.health.status()
+    }
+
+    pub fn try_fill(&mut self, dest: &mut [u8]) -> Result<(), TpmError> {
+        for byte in dest {
+            *byte = self.sample()?;
+        }
+
+        Ok(())
+    }
+
+    fn sample(&mut self) -> Result<u8, TpmError> {
+        let byte = self.source.entropy_byte();
+
+        self.health.feed(byte)?;
+
+        Ok(byte)
+    }
+}
+
+impl<S: EntropySource> RngCore for TpmRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(error) = self.try_fill(dest) {
+            panic!("{error}");
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        Ok(self.try_fill(dest)?)
+    }
+}
+
+impl<S: EntropySource> CryptoRng for TpmRng<S> {}
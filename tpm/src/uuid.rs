@@ -0,0 +1,33 @@
+use core::fmt::Display;
+
+pub const UUID_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(pub [u8; UUID_LENGTH]);
+
+impl Uuid {
+    pub fn new_v4(mut bytes: [u8; UUID_LENGTH]) -> Self {
+        bytes[6] = (bytes[6] & 0x0F) | 0x40;
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+
+        Self(bytes)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.0[6] >> 4
+    }
+}
+
+impl Display for Uuid {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+
+            write!(f, "{byte:02x}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use tpm::{
+    rand_core::RngCore, EntropySource, HealthTests, SecureRng, TpmError, TpmRng, RESEED_INTERVAL,
+    STARTUP_SAMPLES,
+};
+
+// Xorshift noise for the first `good` bytes, then stuck on one value.
+struct FailingSource {
+    state: u64,
+    good: usize,
+}
+
+impl FailingSource {
+    fn new(good: usize) -> Self {
+        Self {
+            state: 0x9E37_79B9_7F4A_7C15,
+            good,
+        }
+    }
+}
+
+impl EntropySource for FailingSource {
+    fn entropy_byte(&mut self) -> u8 {
+        if self.good == 0 {
+            return 0x42;
+        }
+
+        self.good -= 1;
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        (self.state >> 32) as u8
+    }
+}
+
+#[test]
+fn stuck_source_trips_repetition_count() {
+    // 4 bits of min-entropy per byte, six equal bytes in a row fail.
+    let mut health = HealthTests::new(4);
+
+    for _ in 0..5 {
+        assert_eq!(health.feed(0x42), Ok(()));
+    }
+
+    assert_eq!(health.feed(0x42), Err(TpmError::RepetitionCount));
+
+    // Runs shorter than the cutoff are fine.
+    let mut health = HealthTests::new(4);
+
+    for i in 0..100 {
+        assert_eq!(health.feed(i / 5), Ok(()));
+    }
+}
+
+#[test]
+fn biased_source_trips_adaptive_proportion() {
+    let mut health = HealthTests::new(4);
+    let mut sample = 1u8;
+
+    // Every other byte is zero, never twice in a row. The 62nd zero in the window fails.
+    for i in 0..122 {
+        let byte = if i % 2 == 0 { 0 } else { sample };
+
+        sample = sample % 255 + 1;
+        assert_eq!(health.feed(byte), Ok(()), "sample {i}");
+    }
+
+    assert_eq!(health.feed(0), Err(TpmError::AdaptiveProportion));
+}
+
+#[test]
+fn failures_are_sticky() {
+    let mut health = HealthTests::default();
+
+    while health.feed(7).is_ok() {}
+
+    assert_eq!(health.status(), Err(TpmError::RepetitionCount));
+
+    for byte in [1, 2, 3, 4] {
+        assert_eq!(health.feed(byte), Err(TpmError::RepetitionCount));
+    }
+
+    assert_eq!(health.status(), Err(TpmError::RepetitionCount));
+}
+
+#[test]
+fn tpm_rng_checks_the_source_at_startup() {
+    assert_eq!(
+        TpmRng::new(FailingSource::new(100)).err(),
+        Some(TpmError::RepetitionCount)
+    );
+
+    let mut rng = TpmRng::new(FailingSource::new(STARTUP_SAMPLES + 16)).unwrap();
+    let mut buffer = [0; 32];
+
+    assert_eq!(rng.try_fill(&mut buffer), Err(TpmError::RepetitionCount));
+    assert_eq!(rng.health(), Err(TpmError::RepetitionCount));
+}
+
+#[test]
+fn secure_rng_stops_once_the_source_fails() {
+    // Enough good bytes for startup and the seed, then the source sticks.
+    let mut rng = SecureRng::new(FailingSource::new(STARTUP_SAMPLES + 32)).unwrap();
+    let mut buffer = [0xAA; 64];
+
+    rng.try_fill(&mut buffer).unwrap();
+    assert_ne!(buffer, [0xAA; 64]);
+
+    assert_eq!(rng.reseed(), Err(TpmError::RepetitionCount));
+
+    let mut buffer = [0xAA; 64];
+
+    assert_eq!(rng.try_fill(&mut buffer), Err(TpmError::RepetitionCount));
+    assert_eq!(buffer, [0xAA; 64]);
+    assert!(rng.try_fill_bytes(&mut buffer).is_err());
+    assert_eq!(rng.range_u64(0..10), Err(TpmError::RepetitionCount));
+}
+
+#[test]
+fn failed_automatic_reseed_shows_up_on_the_next_fill() {
+    let mut rng = SecureRng::new(FailingSource::new(STARTUP_SAMPLES + 32)).unwrap();
+    let mut buffer = vec![0; RESEED_INTERVAL];
+
+    // Reaching the interval reseeds from the stuck source behind the scenes.
+    rng.try_fill(&mut buffer).unwrap();
+    assert_eq!(rng.health(), Err(TpmError::RepetitionCount));
+    assert_eq!(rng.try_fill(&mut [0; 4]), Err(TpmError::RepetitionCount));
+}
@@ -0,0 +1,125 @@
+use core::ops::Range;
+
+use crypto::{decrypt_in_place, encrypt_in_place, Key, Nonce};
+use tpm::{EntropySource, SecureRng};
+
+// Stands in for the TPM's noise source, xorshift passes the health tests.
+struct FakeEntropy(u64);
+
+impl EntropySource for FakeEntropy {
+    fn entropy_byte(&mut self) -> u8 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 32) as u8
+    }
+}
+
+fn rng() -> SecureRng<FakeEntropy> {
+    SecureRng::new(FakeEntropy(0x9E37_79B9_7F4A_7C15)).unwrap()
+}
+
+#[test]
+fn ranges_stay_in_bounds() {
+    let mut rng = rng();
+    let mut seen = [false; 10];
+
+    for _ in 0..1000 {
+        let value = rng.range_u64(10..20).unwrap();
+
+        assert!((10..20).contains(&value));
+        seen[value as usize - 10] = true;
+    }
+
+    assert!(seen.iter().all(|&seen| seen));
+
+    for _ in 0..100 {
+        assert!((3..7).contains(&rng.range_u32(3..7).unwrap()));
+        assert!((1000..1003).contains(&rng.range_usize(1000..1003).unwrap()));
+    }
+}
+
+#[test]
+fn ranges_at_the_edges() {
+    let mut rng = rng();
+
+    // Empty ranges give their start, a single value is the only answer.
+    assert_eq!(rng.range_u64(5..5), Ok(5));
+    assert_eq!(rng.range_u64(Range { start: 9, end: 2 }), Ok(9));
+    assert_eq!(rng.range_u64(u64::MAX..u64::MAX), Ok(u64::MAX));
+
+    for _ in 0..100 {
+        assert_eq!(rng.range_u64(41..42), Ok(41));
+        assert_eq!(rng.range_u64(u64::MAX - 1..u64::MAX), Ok(u64::MAX - 1));
+    }
+
+    // The widest span, every value but `u64::MAX`.
+    let values: Vec<u64> = (0..100)
+        .map(|_| rng.range_u64(0..u64::MAX).unwrap())
+        .collect();
+
+    assert!(values.iter().all(|&value| value != u64::MAX));
+    assert!(values.iter().any(|&value| value > u64::MAX / 2));
+    assert!(values.iter().any(|&value| value < u64::MAX / 2));
+    assert!((1..u64::MAX).contains(&rng.range_u64(1..u64::MAX).unwrap()));
+}
+
+#[test]
+fn uuid_v4_sets_version_and_variant() {
+    let mut rng = rng();
+    let first = rng.uuid_v4().unwrap();
+
+    for _ in 0..100 {
+        let uuid = rng.uuid_v4().unwrap();
+
+        assert_eq!(uuid.version(), 4);
+        assert_eq!(uuid.0[6] & 0xF0, 0x40);
+        assert_eq!(uuid.0[8] & 0xC0, 0x80);
+        assert_ne!(uuid, first);
+    }
+
+    let text = first.to_string();
+
+    assert_eq!(text.len(), 36);
+    assert_eq!(&text[14..15], "4");
+    assert!(matches!(&text[19..20], "8" | "9" | "a" | "b"));
+}
+
+#[test]
+fn reseed_changes_the_stream() {
+    let mut plain = rng();
+    let mut reseeded = rng();
+    let mut expected = [0; 64];
+    let mut actual = [0; 64];
+
+    // Same source, same stream.
+    plain.try_fill(&mut expected).unwrap();
+    reseeded.try_fill(&mut actual).unwrap();
+    assert_eq!(actual, expected);
+
+    reseeded.reseed().unwrap();
+    plain.try_fill(&mut expected).unwrap();
+    reseeded.try_fill(&mut actual).unwrap();
+    assert_ne!(actual, expected);
+}
+
+#[test]
+fn keys_and_nonces_from_tpm_rng() {
+    let mut rng = rng();
+    let key = Key::generate(&mut rng).unwrap();
+    let other = Key::generate(&mut rng).unwrap();
+    let nonce = Nonce::generate(&mut rng).unwrap();
+
+    assert_ne!(key, other);
+    assert_ne!(nonce, Nonce::generate(&mut rng).unwrap());
+
+    let mut buffer = *b"sealed with a tpm-seeded key";
+    let tag = encrypt_in_place(&key, &nonce, b"", &mut buffer).unwrap();
+
+    assert_ne!(&buffer, b"sealed with a tpm-seeded key");
+    assert!(decrypt_in_place(&other, &nonce, b"", &mut buffer, &tag).is_err());
+
+    decrypt_in_place(&key, &nonce, b"", &mut buffer, &tag).unwrap();
+    assert_eq!(&buffer, b"sealed with a tpm-seeded key");
+}
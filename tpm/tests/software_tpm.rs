@@ -0,0 +1,208 @@
+use crypto::{sha256, Sha256};
+use flash::{FlashStorage, MemoryFlash, SECTOR_SIZE};
+use rand_chacha::ChaCha20Rng;
+use tpm::{
+    rand_core::SeedableRng, EventKind, PcrBank, PcrSelection, SealedBlob, SoftwareTpm, TpmError,
+    PCR_COUNT, PCR_FIRMWARE, PCR_FLASH, PCR_PROGRAMS, SRK_LENGTH,
+};
+
+const SECRET: &[u8] = b"station disk key";
+
+fn rng() -> ChaCha20Rng {
+    ChaCha20Rng::from_seed([7; 32])
+}
+
+fn booted() -> SoftwareTpm {
+    let mut tpm = SoftwareTpm::new([0x5A; SRK_LENGTH]);
+
+    tpm.measure(PCR_FIRMWARE, EventKind::Firmware, "bootloader", b"boot")
+        .unwrap();
+    tpm.measure(PCR_FLASH, EventKind::FlashContents, "kernel", b"kernel")
+        .unwrap();
+    tpm
+}
+
+fn firmware_and_flash() -> PcrSelection {
+    PcrSelection::default()
+        .with(PCR_FIRMWARE)
+        .and_then(|selection| selection.with(PCR_FLASH))
+        .unwrap()
+}
+
+#[test]
+fn log_replays_to_the_pcrs() {
+    let mut tpm = booted();
+    let mut expected = Sha256::new();
+
+    expected.update(&[0; 32]);
+    expected.update(&sha256(b"boot"));
+    assert_eq!(tpm.pcrs.read(PCR_FIRMWARE).unwrap(), expected.finalize());
+    assert_eq!(tpm.pcrs.read(PCR_PROGRAMS).unwrap(), [0; 32]);
+    assert_eq!(tpm.log.len(), 2);
+    assert_eq!(tpm.log.iter().nth(1).unwrap().label(), "kernel");
+    assert_eq!(tpm.log.replay().unwrap(), tpm.pcrs);
+    tpm.verify_log().unwrap();
+
+    // The same measurements in another order end up elsewhere.
+    let mut reordered = PcrBank::new();
+
+    reordered.extend(PCR_FIRMWARE, &sha256(b"kernel")).unwrap();
+    reordered.extend(PCR_FIRMWARE, &sha256(b"boot")).unwrap();
+    assert_ne!(reordered.read(PCR_FIRMWARE), tpm.pcrs.read(PCR_FIRMWARE));
+
+    assert_eq!(
+        tpm.measure(PCR_COUNT as u8, EventKind::Other, "nowhere", b""),
+        Err(TpmError::InvalidPcr)
+    );
+
+    // An extend that bypasses the log.
+    tpm.pcrs.extend(PCR_PROGRAMS, &sha256(b"hidden")).unwrap();
+    assert_eq!(tpm.verify_log(), Err(TpmError::LogMismatch));
+}
+
+#[test]
+fn measures_flash_like_its_bytes() {
+    let mut flash = MemoryFlash::<2>::new();
+    let contents: Vec<u8> = (0..1000).map(|i| (i * 13) as u8).collect();
+
+    flash.write(SECTOR_SIZE - 300, &contents).unwrap();
+
+    let mut tpm = SoftwareTpm::new([0; SRK_LENGTH]);
+    let digest = tpm
+        .measure_flash(
+            PCR_FLASH,
+            "image",
+            &flash,
+            SECTOR_SIZE - 300..SECTOR_SIZE + 700,
+        )
+        .unwrap();
+
+    assert_eq!(digest, sha256(&contents));
+    tpm.verify_log().unwrap();
+}
+
+#[test]
+fn seal_unseal_round_trip() {
+    let tpm = booted();
+    let blob = tpm.seal(firmware_and_flash(), SECRET, &mut rng()).unwrap();
+    let mut secret = [0; 32];
+
+    assert!(!blob.ciphertext.windows(SECRET.len()).any(|w| w == SECRET));
+    assert_eq!(tpm.unseal(&blob, &mut secret), Ok(SECRET.len()));
+    assert_eq!(&secret[..SECRET.len()], SECRET);
+
+    // Through bytes, as it is kept on flash.
+    let blob = SealedBlob::from_bytes(&blob.to_bytes()).unwrap();
+
+    assert_eq!(tpm.unseal(&blob, &mut secret), Ok(SECRET.len()));
+    assert_eq!(
+        tpm.unseal(&blob, &mut [0; 4]),
+        Err(TpmError::SecretTooLarge)
+    );
+    assert_eq!(
+        tpm.seal(firmware_and_flash(), &[0; 65], &mut rng()),
+        Err(TpmError::SecretTooLarge)
+    );
+}
+
+#[test]
+fn unseal_fails_once_a_selected_pcr_changes() {
+    let mut tpm = booted();
+    let blob = tpm.seal(firmware_and_flash(), SECRET, &mut rng()).unwrap();
+    let mut secret = [0; 32];
+
+    // PCRs outside the selection don't matter.
+    tpm.measure(PCR_PROGRAMS, EventKind::Program, "shell", b"shell")
+        .unwrap();
+    assert_eq!(tpm.unseal(&blob, &mut secret), Ok(SECRET.len()));
+
+    tpm.measure(PCR_FLASH, EventKind::FlashContents, "patch", b"patch")
+        .unwrap();
+    assert_eq!(tpm.unseal(&blob, &mut secret), Err(TpmError::PcrMismatch));
+}
+
+#[test]
+fn tampered_blob_fails_authentication() {
+    let tpm = booted();
+    let blob = tpm.seal(firmware_and_flash(), SECRET, &mut rng()).unwrap();
+    let mut secret = [0; 32];
+    let unseal = |blob: &SealedBlob| tpm.unseal(blob, &mut [0; 32]);
+
+    let mut ciphertext = blob.clone();
+
+    ciphertext.ciphertext[3] ^= 1;
+    assert_eq!(unseal(&ciphertext), Err(TpmError::AuthenticationFailed));
+
+    let mut tag = blob.clone();
+
+    tag.tag[0] ^= 1;
+    assert_eq!(unseal(&tag), Err(TpmError::AuthenticationFailed));
+
+    let mut length = blob.clone();
+
+    length.length -= 1;
+    assert_eq!(unseal(&length), Err(TpmError::AuthenticationFailed));
+
+    // A looser policy that matches the current PCRs doesn't unlock it either.
+    let mut loosened = blob.clone();
+
+    loosened.selection = PcrSelection::default().with(PCR_PROGRAMS).unwrap();
+    loosened.policy = tpm.pcrs.composite(loosened.selection);
+    assert_eq!(unseal(&loosened), Err(TpmError::AuthenticationFailed));
+
+    let mut bytes = blob.to_bytes();
+
+    bytes[0] ^= 1;
+    assert_eq!(SealedBlob::from_bytes(&bytes), Err(TpmError::InvalidBlob));
+    assert_eq!(tpm.unseal(&blob, &mut secret), Ok(SECRET.len()));
+}
+
+#[test]
+fn srk_and_blobs_live_on_flash() {
+    let mut flash = MemoryFlash::<4>::new();
+
+    // Leftovers from something else, the key has to erase them to be read back.
+    flash.write(SECTOR_SIZE, &[0; 64]).unwrap();
+
+    let tpm = SoftwareTpm::load_or_create(&mut flash, 1, &mut rng()).unwrap();
+    let blob = tpm
+        .seal(PcrSelection::default(), SECRET, &mut rng())
+        .unwrap();
+    let other = tpm
+        .seal(
+            PcrSelection::default(),
+            b"newer",
+            &mut ChaCha20Rng::from_seed([8; 32]),
+        )
+        .unwrap();
+
+    SoftwareTpm::store_blob(&mut flash, 2, &blob).unwrap();
+    SoftwareTpm::store_blob(&mut flash, 2, &other).unwrap();
+    assert_eq!(SoftwareTpm::load_blob(&flash, 2), Ok(other.clone()));
+    assert_eq!(flash.erase_counts(), &[0, 1, 2, 0]);
+
+    // The next boot finds the same key and leaves it alone.
+    let rebooted =
+        SoftwareTpm::load_or_create(&mut flash, 1, &mut ChaCha20Rng::from_seed([9; 32])).unwrap();
+    let mut secret = [0; 32];
+
+    assert_eq!(rebooted.unseal(&other, &mut secret), Ok(5));
+    assert_eq!(&secret[..5], b"newer");
+    assert_eq!(flash.erase_counts(), &[0, 1, 2, 0]);
+    assert_eq!(
+        SoftwareTpm::load_blob(&flash, 3),
+        Err(TpmError::InvalidBlob)
+    );
+    assert!(SoftwareTpm::load_or_create(&mut flash, 4, &mut rng()).is_err());
+}
+
+#[test]
+fn selection_rejects_missing_pcrs() {
+    let selection = firmware_and_flash();
+
+    assert_eq!(selection.0, 0b11);
+    assert_eq!(selection.contains(PCR_FLASH), Ok(true));
+    assert_eq!(selection.contains(PCR_PROGRAMS), Ok(false));
+    assert_eq!(selection.with(PCR_COUNT as u8), Err(TpmError::InvalidPcr));
+    assert_eq!(selection.contains(200), Err(TpmError::InvalidPcr));
+}